@@ -0,0 +1,200 @@
+use stpc_core::{CertificateVersion, SignatureAlgorithm, StpcError};
+use stpc_crypto::{TypedPrivateKey, TypedPublicKey, TypedSignature};
+use stpc_encoding::{Framing, TlvDecode, TlvEncode};
+
+use crate::extensions::{self, Extension, ExtensionValue, KeyPurpose, KeyUsage};
+use crate::{tlv_serializable, CertSerializable, DistinguishedName, TbsCertificate, Validity};
+
+// === TYPES ===
+
+// CertificationRequestInfo
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct CertificationRequestInfo {
+    #[tlv(tag = 1, with = "crate::tlv::version")]
+    pub version:             CertificateVersion,
+    #[tlv(tag = 2)]
+    pub subject:             DistinguishedName,
+    #[tlv(tag = 3, with = "crate::tlv::signature_algorithm")]
+    pub signature_algorithm: SignatureAlgorithm,
+    #[tlv(tag = 4)]
+    pub subject_public_key:  Vec<u8>,
+    #[tlv(tag = 5, repeated, optional, check = "extensions::check_unique")]
+    pub extensions:          Vec<Extension>,
+    /// Framing the request is signed in; set from the packet when decoded
+    #[tlv(framing)]
+    pub framing:             Framing,
+}
+
+// CertificationRequest
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct CertificationRequest {
+    /// Signed part, accepted only in canonical encoding
+    #[tlv(tag = 1, with = "stpc_encoding::schema::canonical")]
+    pub request_info:        CertificationRequestInfo,
+    #[tlv(tag = 2, with = "crate::tlv::signature_algorithm")]
+    pub signature_algorithm: SignatureAlgorithm,
+    #[tlv(tag = 3)]
+    pub signature_value:     Vec<u8>,
+}
+
+// IssuancePolicy
+#[derive(Debug, Clone)]
+pub struct IssuancePolicy {
+    /// Algorithm the CA signs issued certificates with
+    pub signature_algorithm: SignatureAlgorithm,
+    /// Seconds from `not_before` until the certificate expires
+    pub validity_period:     u64,
+    pub ocsp_url:            String,
+    /// Algorithms accepted for subject keys, empty accepts any
+    pub allowed_algorithms:  Vec<SignatureAlgorithm>,
+    /// Whether requests may ask for BasicConstraints with `ca: true` and for
+    /// KeyUsage with certificate or CRL signing
+    pub allow_ca:            bool,
+    /// Whether requests may ask for the OcspSigning purpose, which makes the
+    /// subject a delegated responder for everything this CA issues
+    pub allow_ocsp_signing:  bool,
+}
+
+// === CERTIFICATION REQUEST INFO ===
+
+impl CertificationRequestInfo {
+    pub fn new(
+        subject:             DistinguishedName,
+        signature_algorithm: SignatureAlgorithm,
+        subject_public_key:  Vec<u8>,
+        extensions:          Vec<Extension>,
+    ) -> Self {
+        Self {
+            version: CertificateVersion::V1,
+            subject,
+            signature_algorithm,
+            subject_public_key,
+            extensions,
+            framing: Framing::V1,
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Self-sign with the subject's private key as proof-of-possession
+    pub fn sign(self, private_key: &TypedPrivateKey) -> Result<CertificationRequest, StpcError> {
+        let message = self.serialize()?;
+        let signature = private_key.sign_as(self.signature_algorithm, &message)?;
+        let signature_algorithm = self.signature_algorithm;
+
+        Ok(CertificationRequest {
+            request_info: self,
+            signature_algorithm,
+            signature_value: signature.signature().as_bytes().to_vec(),
+        })
+    }
+}
+
+tlv_serializable!(CertificationRequestInfo);
+
+// === CERTIFICATION REQUEST ===
+
+impl CertificationRequest {
+    /// Verify proof-of-possession: the request is signed by the key it carries
+    pub fn verify(&self) -> Result<bool, StpcError> {
+        if self.request_info.signature_algorithm != self.signature_algorithm {
+            return Err(StpcError::AlgorithmMismatchError(format!(
+                "Request info declares {:?}, request declares {:?}",
+                self.request_info.signature_algorithm, self.signature_algorithm
+            )));
+        }
+
+        let message = self.request_info.serialize()?;
+        let public_key = TypedPublicKey::from_bytes(self.signature_algorithm, &self.request_info.subject_public_key)?;
+        let signature = TypedSignature::from_bytes(self.signature_algorithm, &self.signature_value)?;
+
+        public_key.verify(&message, &signature)
+    }
+
+    /// CA side: check proof-of-possession and `policy`, then build the TBS to be signed by `issuer`
+    pub fn to_tbs_certificate(
+        &self,
+        issuer:     DistinguishedName,
+        not_before: u64,
+        policy:     &IssuancePolicy,
+    ) -> Result<TbsCertificate, StpcError> {
+        if !self.verify()? {
+            return Err(StpcError::SignatureVerifyError);
+        }
+
+        let info = &self.request_info;
+        if !policy.allowed_algorithms.is_empty()
+            && !policy.allowed_algorithms.contains(&info.signature_algorithm)
+        {
+            return Err(StpcError::IssuancePolicyError(format!(
+                "Subject key algorithm {:?} is not allowed",
+                info.signature_algorithm
+            )));
+        }
+
+        let mut extensions = Vec::new();
+        for ext in &info.extensions {
+            match &ext.value {
+                ExtensionValue::BasicConstraints { ca: true, .. } if !policy.allow_ca => {
+                    return Err(StpcError::IssuancePolicyError("CA certificates are not allowed".into()));
+                }
+                ExtensionValue::KeyUsage(usage)
+                    if !policy.allow_ca && usage.0 & (KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN) != 0 =>
+                {
+                    return Err(StpcError::IssuancePolicyError("Certificate and CRL signing are not allowed".into()));
+                }
+                ExtensionValue::ExtendedKeyUsage(purposes)
+                    if !policy.allow_ocsp_signing && purposes.contains(&KeyPurpose::OcspSigning) =>
+                {
+                    return Err(StpcError::IssuancePolicyError("OCSP signing is not allowed".into()));
+                }
+                ExtensionValue::Unknown { id, .. } if ext.critical => {
+                    return Err(StpcError::IssuancePolicyError(format!("Unknown critical extension {}", id)));
+                }
+                // key ids are the CA's to set, never the requester's
+                ExtensionValue::SubjectKeyId(_) | ExtensionValue::AuthorityKeyId(_) => {}
+                _ => extensions.push(ext.clone()),
+            }
+        }
+        // Say so explicitly when the subject is not a CA
+        if !extensions.iter().any(|ext| ext.id() == extensions::BASIC_CONSTRAINTS) {
+            let end_entity = ExtensionValue::BasicConstraints { ca: false, path_len: None };
+            extensions.insert(0, Extension::new(true, end_entity));
+        }
+
+        let not_after = not_before
+            .checked_add(policy.validity_period)
+            .ok_or_else(|| StpcError::IssuancePolicyError("Validity period overflow".into()))?;
+
+        let tbs = TbsCertificate::new(
+            CertificateVersion::V1,
+            policy.signature_algorithm,
+            issuer,
+            Validity::new(not_before, not_after),
+            info.subject.clone(),
+            info.subject_public_key.clone(),
+            policy.ocsp_url.clone(),
+        );
+
+        // Proof-of-possession was checked with this algorithm, so it is the subject key's
+        Ok(tbs.with_extensions(extensions).with_subject_key_algorithm(info.signature_algorithm))
+    }
+}
+
+impl CertSerializable for CertificationRequest {
+    /// In the framing of the request info
+    fn serialize(&self) -> Result<Vec<u8>, StpcError> {
+        self.tlv_encode_as(self.request_info.framing)
+    }
+
+    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
+        let request = Self::tlv_decode(data)?;
+        if Framing::detect(data)? != request.request_info.framing {
+            return Err(StpcError::NonCanonicalError("Request and its info use different framings".into()));
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,268 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use stpc_core::StpcError;
+use stpc_encoding::schema::{decode_repeated, encode_repeated};
+use stpc_encoding::{Framing, TLVParser, TlvDecode, TlvEncode, TLV};
+
+use crate::tlv_serializable;
+
+// Extension ids (wire tags)
+pub const BASIC_CONSTRAINTS:  u8 = 1;
+pub const KEY_USAGE:          u8 = 2;
+pub const EXTENDED_KEY_USAGE: u8 = 3;
+pub const SUBJECT_ALT_NAME:   u8 = 4;
+pub const SUBJECT_KEY_ID:     u8 = 5;
+pub const AUTHORITY_KEY_ID:   u8 = 6;
+
+// === TYPES ===
+
+// KeyUsage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyUsage(pub u16);
+
+impl KeyUsage {
+    pub const DIGITAL_SIGNATURE: u16 = 1 << 0;
+    pub const NON_REPUDIATION:   u16 = 1 << 1;
+    pub const KEY_ENCIPHERMENT:  u16 = 1 << 2;
+    pub const KEY_AGREEMENT:     u16 = 1 << 3;
+    pub const KEY_CERT_SIGN:     u16 = 1 << 4;
+    pub const CRL_SIGN:          u16 = 1 << 5;
+
+    pub fn contains(&self, flags: u16) -> bool {
+        self.0 & flags == flags
+    }
+}
+
+// ExtendedKeyUsage purposes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPurpose {
+    ServerAuth,
+    ClientAuth,
+    CodeSigning,
+    EmailProtection,
+    TimeStamping,
+    OcspSigning,
+    Other(u8),
+}
+
+impl KeyPurpose {
+    fn tag(&self) -> u8 {
+        match self {
+            KeyPurpose::ServerAuth      => 1,
+            KeyPurpose::ClientAuth      => 2,
+            KeyPurpose::CodeSigning     => 3,
+            KeyPurpose::EmailProtection => 4,
+            KeyPurpose::TimeStamping    => 5,
+            KeyPurpose::OcspSigning     => 6,
+            KeyPurpose::Other(tag)      => *tag,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Self {
+        match tag {
+            1 => KeyPurpose::ServerAuth,
+            2 => KeyPurpose::ClientAuth,
+            3 => KeyPurpose::CodeSigning,
+            4 => KeyPurpose::EmailProtection,
+            5 => KeyPurpose::TimeStamping,
+            6 => KeyPurpose::OcspSigning,
+            tag => KeyPurpose::Other(tag),
+        }
+    }
+}
+
+// SubjectAltName entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneralName {
+    Dns(String),
+    Ip(IpAddr),
+    Email(String),
+    Uri(String),
+}
+
+// ExtensionValue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionValue {
+    BasicConstraints { ca: bool, path_len: Option<u8> },
+    KeyUsage(KeyUsage),
+    ExtendedKeyUsage(Vec<KeyPurpose>),
+    SubjectAltName(Vec<GeneralName>),
+    SubjectKeyId(Vec<u8>),
+    AuthorityKeyId(Vec<u8>),
+    /// Extension this version does not understand, kept verbatim so the TBS re-serializes identically
+    Unknown { id: u8, value: Vec<u8> },
+}
+
+// Extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub critical: bool,
+    pub value:    ExtensionValue,
+}
+
+// === EXTENSION ===
+
+impl Extension {
+    pub fn new(critical: bool, value: ExtensionValue) -> Self {
+        Self { critical, value }
+    }
+
+    pub fn id(&self) -> u8 {
+        match &self.value {
+            ExtensionValue::BasicConstraints { .. } => BASIC_CONSTRAINTS,
+            ExtensionValue::KeyUsage(_)             => KEY_USAGE,
+            ExtensionValue::ExtendedKeyUsage(_)     => EXTENDED_KEY_USAGE,
+            ExtensionValue::SubjectAltName(_)       => SUBJECT_ALT_NAME,
+            ExtensionValue::SubjectKeyId(_)         => SUBJECT_KEY_ID,
+            ExtensionValue::AuthorityKeyId(_)       => AUTHORITY_KEY_ID,
+            ExtensionValue::Unknown { id, .. }      => *id,
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        !matches!(self.value, ExtensionValue::Unknown { .. })
+    }
+
+    fn encode_value(&self, framing: Framing) -> Result<Vec<u8>, StpcError> {
+        match &self.value {
+            ExtensionValue::BasicConstraints { ca, path_len } => {
+                let mut temp: Vec<(u8, Vec<u8>)> = vec![(1, vec![*ca as u8])];
+                if let Some(path_len) = path_len {
+                    temp.push((2, vec![*path_len]));
+                }
+                TLVParser::pack_owned(framing, &temp)
+            }
+            ExtensionValue::KeyUsage(usage) => Ok(usage.0.to_be_bytes().to_vec()),
+            ExtensionValue::ExtendedKeyUsage(purposes) => {
+                Ok(purposes.iter().map(|p| p.tag()).collect())
+            }
+            // A list of names, each a packet with one block tagged by its kind
+            ExtensionValue::SubjectAltName(names) => encode_repeated(framing, names, |name| {
+                let entry = match name {
+                    GeneralName::Dns(dns) => (1, dns.as_bytes().to_vec()),
+                    GeneralName::Ip(IpAddr::V4(ip)) => (2, ip.octets().to_vec()),
+                    GeneralName::Ip(IpAddr::V6(ip)) => (2, ip.octets().to_vec()),
+                    GeneralName::Email(email) => (3, email.as_bytes().to_vec()),
+                    GeneralName::Uri(uri) => (4, uri.as_bytes().to_vec()),
+                };
+                TLVParser::pack_owned(framing, &[entry])
+            }),
+            ExtensionValue::SubjectKeyId(id) | ExtensionValue::AuthorityKeyId(id) => Ok(id.clone()),
+            ExtensionValue::Unknown { value, .. } => Ok(value.clone()),
+        }
+    }
+
+    fn decode_value(id: u8, data: Vec<u8>) -> Result<ExtensionValue, StpcError> {
+        let value = match id {
+            BASIC_CONSTRAINTS => {
+                let mut ca = false;
+                let mut path_len = None;
+                for (tag, value) in TLVParser::unpack(&data)? {
+                    match (tag, value.as_slice()) {
+                        (1, [flag]) => ca = *flag != 0,
+                        (2, [len]) => path_len = Some(*len),
+                        _ => return Err(StpcError::DeserilizateError("Invalid BasicConstraints".into())),
+                    }
+                }
+                ExtensionValue::BasicConstraints { ca, path_len }
+            }
+            KEY_USAGE => {
+                let bits: [u8; 2] = data
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| StpcError::DeserilizateError("Invalid KeyUsage length".into()))?;
+                ExtensionValue::KeyUsage(KeyUsage(u16::from_be_bytes(bits)))
+            }
+            EXTENDED_KEY_USAGE => {
+                ExtensionValue::ExtendedKeyUsage(data.into_iter().map(KeyPurpose::from_tag).collect())
+            }
+            SUBJECT_ALT_NAME => ExtensionValue::SubjectAltName(decode_repeated(&data, decode_name)?),
+            SUBJECT_KEY_ID => ExtensionValue::SubjectKeyId(data),
+            AUTHORITY_KEY_ID => ExtensionValue::AuthorityKeyId(data),
+            id => ExtensionValue::Unknown { id, value: data },
+        };
+
+        Ok(value)
+    }
+}
+
+impl TlvEncode for Extension {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        self.tlv_encode_as(Framing::V1)
+    }
+
+    fn tlv_encode_as(&self, framing: Framing) -> Result<Vec<u8>, StpcError> {
+        let temp = vec![
+            (1u8, vec![self.id()]),
+            (2u8, vec![self.critical as u8]),
+            (3u8, self.encode_value(framing)?),
+        ];
+
+        TLVParser::pack_owned(framing, &temp)
+    }
+}
+
+impl TlvDecode for Extension {
+    fn tlv_decode(data: &[u8]) -> Result<Self, StpcError> {
+        let mut fields = TLVParser::fields_strict(data)?;
+
+        let id = u8::tlv_decode(&fields.required(1, "id")?)?;
+        let critical = match fields.required(2, "critical")?.as_slice() {
+            [flag] => *flag != 0,
+            _ => return Err(StpcError::DeserilizateError("Invalid extension criticality".into())),
+        };
+        let value = Self::decode_value(id, fields.required(3, "value")?)?;
+        fields.finish()?;
+
+        Ok(Self { critical, value })
+    }
+}
+
+tlv_serializable!(Extension);
+
+// === EXTENSION LIST ===
+
+/// `#[tlv(check = ...)]` for extension lists: each id appears at most once
+pub(crate) fn check_unique(extensions: &[Extension]) -> Result<(), StpcError> {
+    for (index, ext) in extensions.iter().enumerate() {
+        if extensions[..index].iter().any(|e| e.id() == ext.id()) {
+            return Err(StpcError::DeserilizateError(format!("Duplicate extension {}", ext.id())));
+        }
+    }
+
+    Ok(())
+}
+
+// === HELPERS ===
+
+fn decode_name(data: &[u8]) -> Result<GeneralName, StpcError> {
+    let [(tag, value)]: [(u8, Vec<u8>); 1] = TLVParser::unpack(data)?
+        .try_into()
+        .map_err(|_| StpcError::DeserilizateError("SubjectAltName entry must have 1 field".into()))?;
+
+    let name = match tag {
+        1 => GeneralName::Dns(utf8(value, "DNS name")?),
+        2 => GeneralName::Ip(decode_ip(&value)?),
+        3 => GeneralName::Email(utf8(value, "email")?),
+        4 => GeneralName::Uri(utf8(value, "URI")?),
+        _ => return Err(StpcError::DeserilizateError("Unknown SubjectAltName tag".into())),
+    };
+
+    Ok(name)
+}
+
+fn utf8(value: Vec<u8>, field: &str) -> Result<String, StpcError> {
+    String::from_utf8(value)
+        .map_err(|_| StpcError::DeserilizateError(format!("Invalid UTF-8 in {}", field)))
+}
+
+fn decode_ip(value: &[u8]) -> Result<IpAddr, StpcError> {
+    if let Ok(octets) = <[u8; 4]>::try_from(value) {
+        return Ok(IpAddr::V4(Ipv4Addr::from(octets)));
+    }
+    if let Ok(octets) = <[u8; 16]>::try_from(value) {
+        return Ok(IpAddr::V6(Ipv6Addr::from(octets)));
+    }
+
+    Err(StpcError::DeserilizateError("Invalid IP address length".into()))
+}
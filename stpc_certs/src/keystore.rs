@@ -0,0 +1,256 @@
+// Password-protected private key files.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::{rngs::OsRng, RngCore};
+use zeroize::Zeroizing;
+
+use stpc_core::{Key, PrivateKey, StpcError};
+use stpc_crypto::TypedPrivateKey;
+use stpc_encoding::{TLVParser, TLV};
+
+use crate::{algorithm_from_tag, algorithm_tag};
+
+const VERSION:           u8 = 1;
+const KDF_ARGON2ID:      u8 = 1;
+const CIPHER_CHACHA20:   u8 = 1;
+
+const SALT_SIZE:  usize = 16;
+const NONCE_SIZE: usize = 12;
+
+/// Upper bounds for writing and reading a file. A crafted file inside them costs
+/// at most 256 MiB and 10 passes over it, about 70 times the default parameters
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_ITERATIONS: u32 = 10;
+const MAX_LANES:      u32 = 16;
+
+// KdfParams: Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub lanes:      u32,
+}
+
+impl Default for KdfParams {
+    /// The Argon2 crate defaults: 19 MiB, 2 passes, 1 lane
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            lanes:      Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+// === FILES ===
+
+/// Write `key` to `path` encrypted under `passphrase`, readable by the owner only.
+/// The envelope goes to a fresh file next to `path` that is synced and renamed over
+/// it, so an existing file never keeps its old permissions or ends up half written
+pub fn save_private_key(path: impl AsRef<Path>, key: &TypedPrivateKey, passphrase: &[u8]) -> Result<(), StpcError> {
+    let path = path.as_ref();
+    let envelope = encrypt_private_key(key, passphrase, &KdfParams::default())?;
+    let io_error = |e: std::io::Error| StpcError::IoError(format!("{}: {}", path.display(), e));
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| StpcError::IoError(format!("{}: not a file path", path.display())))?;
+    let mut suffix = [0u8; 8];
+    OsRng.fill_bytes(&mut suffix);
+    let temp_name = format!(".{}.{}.tmp", file_name.to_string_lossy(), u64::from_be_bytes(suffix));
+    let temp_path = path.with_file_name(temp_name);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let written = options.open(&temp_path).and_then(|mut file| {
+        file.write_all(&envelope)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(io_error(e));
+    }
+
+    Ok(())
+}
+
+/// Read a key written by `save_private_key`
+pub fn load_private_key(path: impl AsRef<Path>, passphrase: &[u8]) -> Result<TypedPrivateKey, StpcError> {
+    let path = path.as_ref();
+    let envelope = fs::read(path).map_err(|e| StpcError::IoError(format!("{}: {}", path.display(), e)))?;
+
+    decrypt_private_key(&envelope, passphrase)
+}
+
+// === ENVELOPE ===
+
+/// Encrypt `key` with ChaCha20-Poly1305 under a key derived from `passphrase` with
+/// Argon2id. The envelope is a TLV packet:
+///
+///   1 version          [1]
+///   2 algorithm        wire tag of the key's SignatureAlgorithm
+///   3 kdf              TLV: 1 kdf id, 2 memory (KiB), 3 iterations, 4 lanes, 5 salt
+///   4 cipher           cipher id
+///   5 nonce
+///   6 ciphertext
+///
+/// Fields 1..=5 are the associated data, so a file whose algorithm or KDF
+/// parameters were edited no longer decrypts
+pub fn encrypt_private_key(key: &TypedPrivateKey, passphrase: &[u8], params: &KdfParams) -> Result<Vec<u8>, StpcError> {
+    let mut salt = [0u8; SALT_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    check_limits(params)?;
+    let encryption_key = derive_key(passphrase, &salt, params)?;
+    let algorithm = [algorithm_tag(key.algorithm())];
+    let kdf = pack_kdf(params, &salt)?;
+    let aad = TLVParser::pack(&[(1, &[VERSION]), (2, &algorithm), (3, &kdf), (4, &[CIPHER_CHACHA20]), (5, &nonce)])?;
+
+    let ciphertext = ChaCha20Poly1305::new(encryption_key.as_slice().into())
+        .encrypt(&nonce.into(), Payload { msg: key.key().as_bytes(), aad: &aad })
+        .map_err(|_| StpcError::KeyStoreError("Private key encryption failed".into()))?;
+
+    TLVParser::pack(&[
+        (1, &[VERSION]),
+        (2, &algorithm),
+        (3, &kdf),
+        (4, &[CIPHER_CHACHA20]),
+        (5, &nonce),
+        (6, &ciphertext),
+    ])
+}
+
+pub fn decrypt_private_key(envelope: &[u8], passphrase: &[u8]) -> Result<TypedPrivateKey, StpcError> {
+    let blocks = TLVParser::unpack(envelope)?;
+    if blocks.len() != 6 {
+        return Err(StpcError::KeyStoreError("Key file must have 6 fields".into()));
+    }
+    let mut blocks = blocks.into_iter();
+
+    match blocks.next() {
+        Some((1, value)) if value == [VERSION] => {}
+        _ => return Err(StpcError::KeyStoreError("Unknown key file version".into())),
+    }
+    let algorithm = match blocks.next() {
+        Some((2, value)) => value,
+        _ => return Err(StpcError::KeyStoreError("Tag algorithm != 2".into())),
+    };
+    let kdf = match blocks.next() {
+        Some((3, value)) => value,
+        _ => return Err(StpcError::KeyStoreError("Tag kdf != 3".into())),
+    };
+    match blocks.next() {
+        Some((4, value)) if value == [CIPHER_CHACHA20] => {}
+        _ => return Err(StpcError::KeyStoreError("Unknown key file cipher".into())),
+    }
+    let nonce: [u8; NONCE_SIZE] = match blocks.next() {
+        Some((5, value)) => value
+            .try_into()
+            .map_err(|_| StpcError::KeyStoreError(format!("Nonce must be {} bytes", NONCE_SIZE)))?,
+        _ => return Err(StpcError::KeyStoreError("Tag nonce != 5".into())),
+    };
+    let ciphertext = match blocks.next() {
+        Some((6, value)) => value,
+        _ => return Err(StpcError::KeyStoreError("Tag ciphertext != 6".into())),
+    };
+
+    let signature_algorithm = algorithm_from_tag(&algorithm)?;
+    let (params, salt) = unpack_kdf(&kdf)?;
+    let encryption_key = derive_key(passphrase, &salt, &params)?;
+    let aad = TLVParser::pack(&[(1, &[VERSION]), (2, &algorithm), (3, &kdf), (4, &[CIPHER_CHACHA20]), (5, &nonce)])?;
+
+    let plaintext = ChaCha20Poly1305::new(encryption_key.as_slice().into())
+        .decrypt(&nonce.into(), Payload { msg: &ciphertext, aad: &aad })
+        .map(Zeroizing::new)
+        .map_err(|_| StpcError::KeyDecryptionError)?;
+
+    TypedPrivateKey::new(signature_algorithm, PrivateKey::from_bytes(&plaintext))
+}
+
+// === HELPERS ===
+
+fn derive_key(passphrase: &[u8], salt: &[u8], params: &KdfParams) -> Result<Zeroizing<[u8; 32]>, StpcError> {
+    let params = Params::new(params.memory_kib, params.iterations, params.lanes, Some(32))
+        .map_err(|e| StpcError::KeyDerivationError(format!("Invalid Argon2 parameters: {}", e)))?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase, salt, key.as_mut_slice())
+        .map_err(|e| StpcError::KeyDerivationError(format!("Argon2id failed: {}", e)))?;
+    Ok(key)
+}
+
+fn pack_kdf(params: &KdfParams, salt: &[u8]) -> Result<Vec<u8>, StpcError> {
+    TLVParser::pack(&[
+        (1, &[KDF_ARGON2ID]),
+        (2, &params.memory_kib.to_be_bytes()),
+        (3, &params.iterations.to_be_bytes()),
+        (4, &params.lanes.to_be_bytes()),
+        (5, salt),
+    ])
+}
+
+fn unpack_kdf(data: &[u8]) -> Result<(KdfParams, Vec<u8>), StpcError> {
+    let blocks = TLVParser::unpack(data)?;
+    if blocks.len() != 5 {
+        return Err(StpcError::KeyStoreError("KDF parameters must have 5 fields".into()));
+    }
+    let mut blocks = blocks.into_iter();
+
+    match blocks.next() {
+        Some((1, value)) if value == [KDF_ARGON2ID] => {}
+        _ => return Err(StpcError::KeyStoreError("Unknown key derivation function".into())),
+    }
+    let memory_kib = match blocks.next() {
+        Some((2, value)) => read_u32(&value, "memory")?,
+        _ => return Err(StpcError::KeyStoreError("Tag memory != 2".into())),
+    };
+    let iterations = match blocks.next() {
+        Some((3, value)) => read_u32(&value, "iterations")?,
+        _ => return Err(StpcError::KeyStoreError("Tag iterations != 3".into())),
+    };
+    let lanes = match blocks.next() {
+        Some((4, value)) => read_u32(&value, "lanes")?,
+        _ => return Err(StpcError::KeyStoreError("Tag lanes != 4".into())),
+    };
+    let salt = match blocks.next() {
+        Some((5, value)) if value.len() >= 8 => value,
+        _ => return Err(StpcError::KeyStoreError("Tag salt != 5 or salt too short".into())),
+    };
+
+    let params = KdfParams { memory_kib, iterations, lanes };
+    check_limits(&params)?;
+    Ok((params, salt))
+}
+
+/// Parameters a file may be written and read with
+fn check_limits(params: &KdfParams) -> Result<(), StpcError> {
+    if params.memory_kib > MAX_MEMORY_KIB || params.iterations > MAX_ITERATIONS || params.lanes > MAX_LANES {
+        return Err(StpcError::KeyStoreError(format!(
+            "KDF parameters above the accepted limits: {} KiB, {} iterations, {} lanes",
+            params.memory_kib, params.iterations, params.lanes
+        )));
+    }
+    Ok(())
+}
+
+fn read_u32(value: &[u8], name: &str) -> Result<u32, StpcError> {
+    value
+        .try_into()
+        .map(u32::from_be_bytes)
+        .map_err(|_| StpcError::KeyStoreError(format!("Invalid {} length", name)))
+}
@@ -1,46 +1,85 @@
-use stpc_core::{StpcError, CertificateVersion, SignatureAlgorithm};
-use stpc_encoding::{TLVParser, TLV};
+use stpc_core::{StpcError, CertificateVersion, HashAlgorithm, SignatureAlgorithm};
+use stpc_core::{Fingerprint, PublicKey};
+use stpc_crypto::{PublicKeyExt, TypedPrivateKey, TypedPublicKey, TypedSignature};
+use stpc_encoding::{Framing, TlvDecode, TlvEncode};
 use rand::{rngs::OsRng, RngCore};
 
+pub mod csr;
+pub mod extensions;
+pub mod keystore;
+pub mod path;
+
+use extensions::{Extension, ExtensionValue, KeyUsage};
+
 // === TYPES ===
 
 // Validity
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
 pub struct Validity {
+    #[tlv(tag = 2)]
     pub not_before: u64,
+    #[tlv(tag = 1)]
     pub not_after:  u64,
 }
 
 // DistinguishedName
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, TlvEncode, TlvDecode)]
 pub struct DistinguishedName {
+    #[tlv(tag = 1)]
     pub common_name:    String,
+    #[tlv(tag = 2, optional)]
     pub organization:   Option<String>,
+    #[tlv(tag = 3, optional)]
     pub department:     Option<String>,
+    #[tlv(tag = 4, optional)]
     pub country:        Option<String>,
+    #[tlv(tag = 5, optional)]
     pub state:          Option<String>,
+    #[tlv(tag = 6, optional)]
     pub locality:       Option<String>,
+    #[tlv(tag = 7, optional)]
     pub email_address:  Option<String>,
 }
 
 // TbsCertificate
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
 pub struct TbsCertificate {
+    #[tlv(tag = 1, with = "crate::tlv::version")]
     pub version:             CertificateVersion,
+    #[tlv(tag = 2)]
     pub serial_number:       [u8; 8],
+    #[tlv(tag = 3, with = "crate::tlv::signature_algorithm")]
     pub signature_algorithm:  SignatureAlgorithm,
+    #[tlv(tag = 4)]
     pub issuser:             DistinguishedName,
+    #[tlv(tag = 5)]
     pub validity:            Validity,
+    #[tlv(tag = 6)]
     pub subject:             DistinguishedName,
+    #[tlv(tag = 7)]
     pub subject_public_key:  Vec<u8>,
+    #[tlv(tag = 8)]
     pub ocsp_url:            String,
+    /// Left out when empty, so certificates without extensions keep the original encoding
+    #[tlv(tag = 9, repeated, optional, check = "extensions::check_unique")]
+    pub extensions:          Vec<Extension>,
+    /// Algorithm of `subject_public_key`; `signature_algorithm` is the issuer's
+    #[tlv(tag = 10, optional, with = "crate::tlv::signature_algorithm")]
+    pub subject_key_algorithm: Option<SignatureAlgorithm>,
+    /// Framing the TBS is signed in; set from the packet when decoded
+    #[tlv(framing)]
+    pub framing:             Framing,
 }
 
 // Certificate
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
 pub struct Certificate {
+    /// Signed part, accepted only in canonical encoding
+    #[tlv(tag = 1, with = "stpc_encoding::schema::canonical")]
     pub tbs_certificate:      TbsCertificate,
+    #[tlv(tag = 2, with = "crate::tlv::signature_algorithm")]
     pub signature_algorithm:  SignatureAlgorithm,
+    #[tlv(tag = 3)]
     pub signature_value:      Vec<u8>,
 }
 
@@ -52,54 +91,51 @@ pub trait CertSerializable {
     fn deserialize(data: &[u8]) -> Result<Self, StpcError>
     where
         Self: Sized;
-}
-
-// === VALIDITY ===
-
-impl CertSerializable for Validity {
-    fn serialize(&self) -> Result<Vec<u8>, StpcError> {
-        let blocks_vec = [
-            (1u8, self.not_after.to_be_bytes().to_vec()),
-            (2u8, self.not_before.to_be_bytes().to_vec()),
-        ];
 
-        let blocks: Vec<(u8, &[u8])> = blocks_vec
-            .iter()
-            .map(|(tag, val)| (*tag, val.as_slice()))
-            .collect();
+    /// Serialize in `framing`, nested values included. Signed structures keep
+    /// the framing they were signed in, which the default does by calling `serialize`
+    fn serialize_as(&self, framing: Framing) -> Result<Vec<u8>, StpcError> {
+        let _ = framing;
+        self.serialize()
+    }
 
-        TLVParser::pack(&blocks)
+    /// Deserialize, accepting only the canonical encoding: the one `serialize_as`
+    /// gives back in the framing `data` came in. Signed parts are read this way,
+    /// so the bytes a signature was checked against are the bytes that were received
+    fn deserialize_canonical(data: &[u8]) -> Result<Self, StpcError>
+    where
+        Self: Sized,
+    {
+        let value = Self::deserialize(data)?;
+        stpc_encoding::schema::require_canonical(data, &value.serialize_as(Framing::detect(data)?)?)?;
+        Ok(value)
     }
+}
 
-    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
-        let mut blocks = TLVParser::unpack(data)?;
+/// CertSerializable for types declared with `#[derive(TlvEncode, TlvDecode)]`
+#[macro_export]
+macro_rules! tlv_serializable {
+    ($($type:ty),* $(,)?) => {$(
+        impl $crate::CertSerializable for $type {
+            fn serialize(&self) -> Result<Vec<u8>, ::stpc_core::StpcError> {
+                ::stpc_encoding::TlvEncode::tlv_encode(self)
+            }
 
-        let (tag, not_after_bytes) = blocks.remove(0);
-        if tag != 1 {
-            return Err(StpcError::DeserilizateError("Tag not_after != 1".into()));
-        }
-        let not_after = u64::from_be_bytes(
-            not_after_bytes
-                .as_slice()
-                .try_into()
-                .map_err(|_| StpcError::DeserilizateError("Invalid not_after length".into()))?,
-        );
-
-        let (tag, not_before_bytes) = blocks.remove(0);
-        if tag != 2 {
-            return Err(StpcError::DeserilizateError("Tag not_before != 2".into()));
+            fn serialize_as(&self, framing: ::stpc_encoding::Framing) -> Result<Vec<u8>, ::stpc_core::StpcError> {
+                ::stpc_encoding::TlvEncode::tlv_encode_as(self, framing)
+            }
+
+            fn deserialize(data: &[u8]) -> Result<Self, ::stpc_core::StpcError> {
+                <Self as ::stpc_encoding::TlvDecode>::tlv_decode(data)
+            }
         }
-        let not_before = u64::from_be_bytes(
-            not_before_bytes
-                .as_slice()
-                .try_into()
-                .map_err(|_| StpcError::DeserilizateError("Invalid not_before length".into()))?,
-        );
-
-        Ok(Self { not_before, not_after })
-    }
+    )*};
 }
 
+// === VALIDITY ===
+
+tlv_serializable!(Validity);
+
 impl Validity {
     pub fn new(not_before: u64, not_after: u64) -> Self {
         Self { not_before, not_after }
@@ -140,69 +176,7 @@ impl DistinguishedName {
     }
 }
 
-impl CertSerializable for DistinguishedName {
-    fn serialize(&self) -> Result<Vec<u8>, StpcError> {
-        let mut temp: Vec<(u8, Vec<u8>)> = Vec::new();
-        temp.push((1, self.common_name.as_bytes().to_vec()));
-
-        if let Some(org) = &self.organization {
-            temp.push((2, org.as_bytes().to_vec()));
-        }
-        if let Some(dep) = &self.department {
-            temp.push((3, dep.as_bytes().to_vec()));
-        }
-        if let Some(country) = &self.country {
-            temp.push((4, country.as_bytes().to_vec()));
-        }
-        if let Some(state) = &self.state {
-            temp.push((5, state.as_bytes().to_vec()));
-        }
-        if let Some(locality) = &self.locality {
-            temp.push((6, locality.as_bytes().to_vec()));
-        }
-        if let Some(email) = &self.email_address {
-            temp.push((7, email.as_bytes().to_vec()));
-        }
-
-        let blocks: Vec<(u8, &[u8])> = temp
-            .iter()
-            .map(|(tag, val)| (*tag, val.as_slice()))
-            .collect();
-
-        TLVParser::pack(&blocks)
-    }
-
-    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
-        let blocks = TLVParser::unpack(data)?;
-        let mut dn = Self {
-            common_name: String::new(),
-            organization: None,
-            department: None,
-            country: None,
-            state: None,
-            locality: None,
-            email_address: None,
-        };
-
-        for (tag, value) in blocks {
-            let s = String::from_utf8(value).map_err(|_| {
-                StpcError::DeserilizateError("Invalid UTF-8 in DistinguishedName".into())
-            })?;
-            match tag {
-                1 => dn.common_name = s,
-                2 => dn.organization = Some(s),
-                3 => dn.department = Some(s),
-                4 => dn.country = Some(s),
-                5 => dn.state = Some(s),
-                6 => dn.locality = Some(s),
-                7 => dn.email_address = Some(s),
-                _ => {}
-            }
-        }
-
-        Ok(dn)
-    }
-}
+tlv_serializable!(DistinguishedName);
 
 // === TBS CERTIFICATE ===
 
@@ -229,88 +203,88 @@ impl TbsCertificate {
             subject,
             subject_public_key,
             ocsp_url,
+            extensions: Vec::new(),
+            subject_key_algorithm: None,
+            framing: Framing::V1,
         }
     }
-}
 
-impl CertSerializable for TbsCertificate {
-    fn serialize(&self) -> Result<Vec<u8>, StpcError> {
-        let mut temp: Vec<(u8, Vec<u8>)> = Vec::new();
-
-        temp.push((
-            1,
-            vec![match self.version {
-                CertificateVersion::V1 => 1,
-            }],
-        ));
-        temp.push((2, self.serial_number.to_vec()));
-
-        let sig_alg = match self.signature_algorithm {
-            SignatureAlgorithm::Ed25519 => 1,
-            SignatureAlgorithm::Falcon512 => 2,
-            SignatureAlgorithm::Falcon1024 => 3,
-        };
-        temp.push((3, vec![sig_alg]));
-
-        temp.push((4, self.issuser.serialize()?));
-        temp.push((5, self.validity.serialize()?));
-        temp.push((6, self.subject.serialize()?));
-        temp.push((7, self.subject_public_key.clone()));
-        temp.push((8, self.ocsp_url.as_bytes().to_vec()));
-
-        let blocks: Vec<(u8, &[u8])> = temp
-            .iter()
-            .map(|(tag, val)| (*tag, val.as_slice()))
-            .collect();
-
-        TLVParser::pack(&blocks)
+    /// Framing to sign in; v2 gives a smaller certificate that v1-only peers cannot read
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
     }
 
-    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
-        let mut blocks = TLVParser::unpack(data)?;
+    /// Attach extensions to the TBS
+    pub fn with_extensions(mut self, extensions: Vec<Extension>) -> Self {
+        self.extensions = extensions;
+        self
+    }
 
-        let version = match blocks.remove(0).1[0] {
-            1 => CertificateVersion::V1,
-            _ => {
-                return Err(StpcError::DeserilizateError("Unknown certificate version".into()))
-            }
-        };
-
-        let serial_number: [u8; 8] = blocks
-            .remove(0)
-            .1
-            .try_into()
-            .map_err(|_| StpcError::DeserilizateError("Invalid serial_number length".into()))?;
-
-        let signature_algorithm = match blocks.remove(0).1[0] {
-            1 => SignatureAlgorithm::Ed25519,
-            2 => SignatureAlgorithm::Falcon512,
-            3 => SignatureAlgorithm::Falcon1024,
-            _ => {
-                return Err(StpcError::DeserilizateError("Unknown signature algorithm".into()))
-            }
-        };
+    /// Declare the algorithm of the subject key, so the subject's signatures
+    /// are checked with it rather than with one the subject names
+    pub fn with_subject_key_algorithm(mut self, algorithm: SignatureAlgorithm) -> Self {
+        self.subject_key_algorithm = Some(algorithm);
+        self
+    }
 
-        let issuser = DistinguishedName::deserialize(&blocks.remove(0).1)?;
-        let validity = Validity::deserialize(&blocks.remove(0).1)?;
-        let subject = DistinguishedName::deserialize(&blocks.remove(0).1)?;
-        let subject_public_key = blocks.remove(0).1;
-        let ocsp_url = String::from_utf8(blocks.remove(0).1)
-            .map_err(|_| StpcError::DeserilizateError("Invalid UTF-8 in ocsp_url".into()))?;
+    /// Subject key under the algorithm the certificate declares for it
+    pub fn subject_key(&self) -> Result<TypedPublicKey, StpcError> {
+        let algorithm = self
+            .subject_key_algorithm
+            .ok_or_else(|| StpcError::InvalidKeyError("Certificate does not declare its subject key algorithm".into()))?;
 
-        Ok(Self {
-            version,
-            serial_number,
-            signature_algorithm,
-            issuser,
-            validity,
-            subject,
-            subject_public_key,
-            ocsp_url,
-        })
+        TypedPublicKey::from_bytes(algorithm, &self.subject_public_key)
+    }
+
+    /// Subject key for checking a signature made with `algorithm`. A declared subject
+    /// key algorithm must be that algorithm, so the key is never used under another one
+    pub fn subject_key_for(&self, algorithm: SignatureAlgorithm) -> Result<TypedPublicKey, StpcError> {
+        if let Some(declared) = self.subject_key_algorithm
+            && declared != algorithm
+        {
+            return Err(StpcError::AlgorithmMismatchError(format!(
+                "Subject key is declared for {:?}, signature uses {:?}",
+                declared, algorithm
+            )));
+        }
+
+        TypedPublicKey::from_bytes(algorithm, &self.subject_public_key)
+    }
+
+    /// Find extension by its id
+    pub fn extension(&self, id: u8) -> Option<&Extension> {
+        self.extensions.iter().find(|ext| ext.id() == id)
+    }
+
+    /// BasicConstraints as (ca, path_len), if present
+    pub fn basic_constraints(&self) -> Option<(bool, Option<u8>)> {
+        match self.extension(extensions::BASIC_CONSTRAINTS).map(|ext| &ext.value) {
+            Some(ExtensionValue::BasicConstraints { ca, path_len }) => Some((*ca, *path_len)),
+            _ => None,
+        }
+    }
+
+    /// KeyUsage, if present
+    pub fn key_usage(&self) -> Option<KeyUsage> {
+        match self.extension(extensions::KEY_USAGE).map(|ext| &ext.value) {
+            Some(ExtensionValue::KeyUsage(usage)) => Some(*usage),
+            _ => None,
+        }
+    }
+
+    /// Sign the serialized TBS with the issuer's private key, which must be of `signature_algorithm`
+    pub fn sign(self, private_key: &TypedPrivateKey) -> Result<Certificate, StpcError> {
+        let message = self.serialize()?;
+        let signature = private_key.sign_as(self.signature_algorithm, &message)?;
+        let signature_algorithm = self.signature_algorithm;
+
+        Ok(Certificate::new(self, signature_algorithm, signature.signature().as_bytes().to_vec()))
     }
 }
 
+tlv_serializable!(TbsCertificate);
+
 
 // === CERTFICATE ===
 impl Certificate {
@@ -325,55 +299,152 @@ impl Certificate {
             signature_value,
         }
     }
+
+    /// Verify the signature over the serialized TBS with the issuer's public key,
+    /// which must be of `signature_algorithm`
+    pub fn verify(&self, public_key: &TypedPublicKey) -> Result<bool, StpcError> {
+        if self.tbs_certificate.signature_algorithm != self.signature_algorithm {
+            return Err(StpcError::AlgorithmMismatchError(format!(
+                "TBS declares {:?}, certificate declares {:?}",
+                self.tbs_certificate.signature_algorithm, self.signature_algorithm
+            )));
+        }
+
+        let message = self.tbs_certificate.serialize()?;
+        let signature = TypedSignature::from_bytes(self.signature_algorithm, &self.signature_value)?;
+
+        public_key.verify(&message, &signature)
+    }
+
+    /// Digest of the complete serialized certificate, signature included
+    pub fn fingerprint(&self, algorithm: HashAlgorithm) -> Result<Fingerprint, StpcError> {
+        let encoded = self.serialize()?;
+
+        Ok(Fingerprint::new(algorithm, stpc_crypto::hash::digest(algorithm, &encoded)))
+    }
+
+    /// Key id of the subject public key
+    pub fn subject_key_id(&self) -> Fingerprint {
+        PublicKey::from_bytes(&self.tbs_certificate.subject_public_key).key_id()
+    }
 }
 
 
 impl CertSerializable for Certificate {
+    /// In the framing of the TBS
     fn serialize(&self) -> Result<Vec<u8>, StpcError> {
-        let mut temp: Vec<(u8, Vec<u8>)> = Vec::new();
+        self.tlv_encode_as(self.tbs_certificate.framing)
+    }
 
-        // tbs
-        temp.push((1, self.tbs_certificate.serialize()?));
+    /// Only the canonical encoding is accepted, so a certificate has one
+    /// encoding and one fingerprint
+    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
+        let cert: Self = stpc_encoding::schema::canonical::decode(data)?;
+        if Framing::detect(data)? != cert.tbs_certificate.framing {
+            return Err(StpcError::NonCanonicalError("Certificate and its TBS use different framings".into()));
+        }
+        Ok(cert)
+    }
+}
 
-        // signature algorithm
-        let sig_alg = match self.signature_algorithm {
-            SignatureAlgorithm::Ed25519 => 1u8,
-            SignatureAlgorithm::Falcon512 => 2u8,
-            SignatureAlgorithm::Falcon1024 => 3u8,
-        };
-        temp.push((2, vec![sig_alg]));
 
-        // signature value
-        temp.push((3, self.signature_value.clone()));
+// === WIRE TAGS ===
+
+/// Wire tag of a signature algorithm, shared by every signed structure
+pub fn algorithm_tag(algorithm: SignatureAlgorithm) -> u8 {
+    match algorithm {
+        SignatureAlgorithm::Ed25519 => 1,
+        SignatureAlgorithm::Falcon512 => 2,
+        SignatureAlgorithm::Falcon1024 => 3,
+        SignatureAlgorithm::MlDsa44 => 4,
+        SignatureAlgorithm::MlDsa65 => 5,
+        SignatureAlgorithm::MlDsa87 => 6,
+        SignatureAlgorithm::SlhDsaSha2_128s => 7,
+        SignatureAlgorithm::SlhDsaShake256s => 8,
+        SignatureAlgorithm::Ed25519Falcon512 => 9,
+    }
+}
 
-        // теперь собираем безопасно
-        let blocks: Vec<(u8, &[u8])> = temp
-            .iter()
-            .map(|(tag, data)| (*tag, data.as_slice()))
-            .collect();
+pub fn algorithm_from_tag(value: &[u8]) -> Result<SignatureAlgorithm, StpcError> {
+    match value {
+        [1] => Ok(SignatureAlgorithm::Ed25519),
+        [2] => Ok(SignatureAlgorithm::Falcon512),
+        [3] => Ok(SignatureAlgorithm::Falcon1024),
+        [4] => Ok(SignatureAlgorithm::MlDsa44),
+        [5] => Ok(SignatureAlgorithm::MlDsa65),
+        [6] => Ok(SignatureAlgorithm::MlDsa87),
+        [7] => Ok(SignatureAlgorithm::SlhDsaSha2_128s),
+        [8] => Ok(SignatureAlgorithm::SlhDsaShake256s),
+        [9] => Ok(SignatureAlgorithm::Ed25519Falcon512),
+        _ => Err(StpcError::DeserilizateError("Unknown signature algorithm".into())),
+    }
+}
 
-        TLVParser::pack(&blocks)
+/// Wire tag of a hash algorithm, for structures that name the hash they use
+pub fn hash_tag(algorithm: HashAlgorithm) -> u8 {
+    match algorithm {
+        HashAlgorithm::Sha256 => 1,
+        HashAlgorithm::Sha384 => 2,
+        HashAlgorithm::Sha512 => 3,
+        HashAlgorithm::Sha3_256 => 4,
+        HashAlgorithm::Blake3 => 5,
     }
+}
 
-    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
-        let mut blocks = TLVParser::unpack(data)?;
+pub fn hash_from_tag(value: &[u8]) -> Result<HashAlgorithm, StpcError> {
+    match value {
+        [1] => Ok(HashAlgorithm::Sha256),
+        [2] => Ok(HashAlgorithm::Sha384),
+        [3] => Ok(HashAlgorithm::Sha512),
+        [4] => Ok(HashAlgorithm::Sha3_256),
+        [5] => Ok(HashAlgorithm::Blake3),
+        _ => Err(StpcError::DeserilizateError("Unknown hash algorithm".into())),
+    }
+}
 
-        let tbs_certificate = TbsCertificate::deserialize(&blocks.remove(0).1)?;
+/// `#[tlv(with = ...)]` adapters for the wire tags above
+pub mod tlv {
+    pub mod signature_algorithm {
+        use stpc_core::{SignatureAlgorithm, StpcError};
 
-        let sig_alg = match blocks.remove(0).1[0] {
-            1 => SignatureAlgorithm::Ed25519,
-            2 => SignatureAlgorithm::Falcon512,
-            3 => SignatureAlgorithm::Falcon1024,
-            _ => return Err(StpcError::DeserilizateError("Unknown signature algorithm".into())),
-        };
+        pub fn encode(algorithm: &SignatureAlgorithm) -> Result<Vec<u8>, StpcError> {
+            Ok(vec![crate::algorithm_tag(*algorithm)])
+        }
 
-        let signature_value = blocks.remove(0).1;
+        pub fn decode(value: &[u8]) -> Result<SignatureAlgorithm, StpcError> {
+            crate::algorithm_from_tag(value)
+        }
+    }
 
-        Ok(Self {
-            tbs_certificate,
-            signature_algorithm: sig_alg,
-            signature_value,
-        })
+    /// A certificate nested in another structure, kept in the framing it was signed in
+    pub mod certificate {
+        use stpc_core::StpcError;
+
+        use crate::{CertSerializable, Certificate};
+
+        pub fn encode(certificate: &Certificate) -> Result<Vec<u8>, StpcError> {
+            certificate.serialize()
+        }
+
+        pub fn decode(value: &[u8]) -> Result<Certificate, StpcError> {
+            Certificate::deserialize(value)
+        }
     }
-}
 
+    pub mod version {
+        use stpc_core::{CertificateVersion, StpcError};
+
+        pub fn encode(version: &CertificateVersion) -> Result<Vec<u8>, StpcError> {
+            match version {
+                CertificateVersion::V1 => Ok(vec![1]),
+            }
+        }
+
+        pub fn decode(value: &[u8]) -> Result<CertificateVersion, StpcError> {
+            match value {
+                [1] => Ok(CertificateVersion::V1),
+                _ => Err(StpcError::DeserilizateError("Unknown version".into())),
+            }
+        }
+    }
+}
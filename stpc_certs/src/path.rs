@@ -0,0 +1,264 @@
+use stpc_core::StpcError;
+
+use crate::{Certificate, DistinguishedName};
+use crate::extensions::KeyUsage;
+
+/// Upper bound on chain length, guards against issuer loops in the pool
+pub const MAX_PATH_DEPTH: usize = 16;
+
+// === TYPES ===
+
+// LinkFailureReason
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkFailureReason {
+    /// Certificate is outside its validity window at the supplied time
+    NotValidAt { not_before: u64, not_after: u64, time: u64 },
+    /// No intermediate or trust anchor has a subject matching the issuer
+    IssuerNotFound,
+    /// Candidate issuers exist but none of their keys verify the signature
+    SignatureInvalid,
+    /// Inner and outer signature algorithms disagree, or differ from the one
+    /// the issuer declares for its key
+    AlgorithmMismatch,
+    /// Chain exceeded `MAX_PATH_DEPTH` without reaching a trust anchor
+    PathTooLong,
+    /// Critical extension with an id this version does not understand
+    UnhandledCriticalExtension(u8),
+    /// Issuer has no BasicConstraints marking it as a CA
+    NotCa,
+    /// Issuer's BasicConstraints path length is exceeded by the CAs below it
+    PathLenExceeded { path_len: u8 },
+    /// Issuer's KeyUsage does not allow certificate signing
+    KeyCertSignNotAllowed,
+}
+
+// TrustAnchor
+#[derive(Debug, Clone)]
+pub struct TrustAnchor {
+    pub certificate: Certificate,
+    /// Accept the root without BasicConstraints, for roots issued before extensions existed
+    pub legacy:      bool,
+}
+
+// LinkFailure
+#[derive(Debug, Clone)]
+pub struct LinkFailure {
+    /// Position in the chain, the leaf is at depth 0
+    pub depth:   usize,
+    pub subject: DistinguishedName,
+    pub issuer:  DistinguishedName,
+    pub reason:  LinkFailureReason,
+}
+
+// PathReport
+#[derive(Debug, Clone)]
+pub struct PathReport {
+    /// Certificates from the leaf up to (not including) the trust anchor
+    pub chain:   Vec<Certificate>,
+    pub anchor:  Option<Certificate>,
+    pub failure: Option<LinkFailure>,
+}
+
+impl TrustAnchor {
+    pub fn new(certificate: Certificate) -> Self {
+        Self { certificate, legacy: false }
+    }
+
+    /// Root that predates BasicConstraints and is trusted as a CA without it
+    pub fn legacy(certificate: Certificate) -> Self {
+        Self { certificate, legacy: true }
+    }
+}
+
+impl PathReport {
+    pub fn is_valid(&self) -> bool {
+        self.failure.is_none() && self.anchor.is_some()
+    }
+}
+
+// === VALIDATION ===
+
+/// Build a chain from `leaf` to one of `trust_anchors` through `intermediates`
+/// and validate names, signatures and validity of every link at `time`.
+/// Issuers sharing a subject (cross-signed or re-keyed CAs) are all tried; when
+/// no path works, the report holds the failure that got furthest from the leaf
+pub fn validate_path(
+    leaf:          &Certificate,
+    intermediates: &[Certificate],
+    trust_anchors: &[TrustAnchor],
+    time:          u64,
+) -> PathReport {
+    let mut search = PathSearch {
+        intermediates,
+        trust_anchors,
+        time,
+        used:    vec![false; intermediates.len()],
+        path:    Vec::new(),
+        failure: None,
+    };
+
+    match search.extend(leaf) {
+        Some(anchor) => PathReport {
+            chain:   search.path,
+            anchor:  Some(anchor.clone()),
+            failure: None,
+        },
+        None => {
+            let (failure, chain) = search.failure.expect("a failed search records why");
+            PathReport {
+                chain,
+                anchor:  None,
+                failure: Some(failure),
+            }
+        }
+    }
+}
+
+/// Depth-first search for a path, backtracking over candidate issuers
+struct PathSearch<'a> {
+    intermediates: &'a [Certificate],
+    trust_anchors: &'a [TrustAnchor],
+    time:          u64,
+    used:          Vec<bool>,
+    /// Certificates from the leaf up to the one being extended
+    path:          Vec<Certificate>,
+    /// Deepest failure so far, with the path that led to it
+    failure:       Option<(LinkFailure, Vec<Certificate>)>,
+}
+
+impl<'a> PathSearch<'a> {
+    /// Check `current` and find a path from it to a trust anchor. On success `path`
+    /// holds the chain and the anchor is returned, on failure `path` is left as it was
+    fn extend(&mut self, current: &Certificate) -> Option<&'a Certificate> {
+        let depth = self.path.len();
+
+        if depth >= MAX_PATH_DEPTH {
+            self.fail(current, depth, LinkFailureReason::PathTooLong);
+            return None;
+        }
+        if let Err(reason) = check_time(current, self.time).and_then(|_| check_extensions(current, depth, false)) {
+            self.fail(current, depth, reason);
+            return None;
+        }
+
+        self.path.push(current.clone());
+        let issuer = &current.tbs_certificate.issuser;
+        let mut found = false;
+
+        // Trust anchors first: a chain ends as soon as one of them signs it
+        for trust_anchor in self.trust_anchors.iter().filter(|a| &a.certificate.tbs_certificate.subject == issuer) {
+            found = true;
+            let anchor = &trust_anchor.certificate;
+
+            let checked = check_signature(current, anchor).map_err(|reason| (depth, reason)).and_then(|_| {
+                check_time(anchor, self.time)
+                    .and_then(|_| check_extensions(anchor, depth + 1, trust_anchor.legacy))
+                    .map_err(|reason| (depth + 1, reason))
+            });
+            match checked {
+                Ok(()) => return Some(anchor),
+                Err((failed, reason)) if failed == depth => self.fail(current, depth, reason),
+                Err((failed, reason)) => self.fail(anchor, failed, reason),
+            }
+        }
+
+        for (index, candidate) in self.intermediates.iter().enumerate() {
+            if self.used[index] || &candidate.tbs_certificate.subject != issuer {
+                continue;
+            }
+            found = true;
+
+            if let Err(reason) = check_signature(current, candidate) {
+                self.fail(current, depth, reason);
+                continue;
+            }
+
+            self.used[index] = true;
+            if let Some(anchor) = self.extend(candidate) {
+                return Some(anchor);
+            }
+            self.used[index] = false;
+        }
+
+        self.path.pop();
+        if !found {
+            self.fail(current, depth, LinkFailureReason::IssuerNotFound);
+        }
+        None
+    }
+
+    /// Record why `cert` at `depth` failed, unless an earlier attempt got further.
+    /// `path` holds at least the `depth` certificates below it
+    fn fail(&mut self, cert: &Certificate, depth: usize, reason: LinkFailureReason) {
+        if self.failure.as_ref().is_some_and(|(failure, _)| failure.depth > depth) {
+            return;
+        }
+
+        let failure = LinkFailure {
+            depth,
+            subject: cert.tbs_certificate.subject.clone(),
+            issuer:  cert.tbs_certificate.issuser.clone(),
+            reason,
+        };
+        self.failure = Some((failure, self.path[..depth].to_vec()));
+    }
+}
+
+fn check_time(cert: &Certificate, time: u64) -> Result<(), LinkFailureReason> {
+    let validity = &cert.tbs_certificate.validity;
+
+    validity
+        .check_validity(time)
+        .map(|_| ())
+        .map_err(|_| LinkFailureReason::NotValidAt {
+            not_before: validity.not_before,
+            not_after:  validity.not_after,
+            time,
+        })
+}
+
+/// `depth` is the position in the chain; anything above the leaf acts as an issuer
+/// and must carry BasicConstraints with the CA flag, unless it is a legacy root
+fn check_extensions(cert: &Certificate, depth: usize, legacy: bool) -> Result<(), LinkFailureReason> {
+    let tbs = &cert.tbs_certificate;
+
+    if let Some(ext) = tbs.extensions.iter().find(|ext| ext.critical && !ext.is_known()) {
+        return Err(LinkFailureReason::UnhandledCriticalExtension(ext.id()));
+    }
+
+    if depth == 0 {
+        return Ok(());
+    }
+
+    match tbs.basic_constraints() {
+        // CAs below this issuer, not counting the leaf
+        Some((true, Some(path_len))) if depth - 1 > path_len as usize => {
+            return Err(LinkFailureReason::PathLenExceeded { path_len });
+        }
+        Some((true, _)) => {}
+        None if legacy => {}
+        _ => return Err(LinkFailureReason::NotCa),
+    }
+
+    if let Some(usage) = tbs.key_usage()
+        && !usage.contains(KeyUsage::KEY_CERT_SIGN)
+    {
+        return Err(LinkFailureReason::KeyCertSignNotAllowed);
+    }
+
+    Ok(())
+}
+
+/// The issuer's key is used under the algorithm it declares, whatever `cert` names
+fn check_signature(cert: &Certificate, issuer: &Certificate) -> Result<(), LinkFailureReason> {
+    let verified = issuer
+        .tbs_certificate
+        .subject_key_for(cert.signature_algorithm)
+        .and_then(|public_key| cert.verify(&public_key));
+
+    match verified {
+        Ok(true) => Ok(()),
+        Err(StpcError::AlgorithmMismatchError(_)) => Err(LinkFailureReason::AlgorithmMismatch),
+        _ => Err(LinkFailureReason::SignatureInvalid),
+    }
+}
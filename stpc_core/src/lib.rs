@@ -1,5 +1,7 @@
+use core::fmt;
 use core::str;
 
+
 use zeroize::{Zeroize, Zeroizing};
 use thiserror::Error;
 
@@ -12,6 +14,9 @@ pub enum StpcError {
     #[error("Key generation error: {0}")]
     KeyGenerationError(String),
 
+    #[error("Invalid key: {0}")]
+    InvalidKeyError(String),
+
     #[error("Signature computing error: {0}")]
     SignatureComputingError(String),
 
@@ -32,6 +37,69 @@ pub enum StpcError {
 
     #[error("Deserilizate error: {0}")]
     DeserilizateError(String),
+
+    #[error("Missing field {field} (tag {tag})")]
+    MissingTagError { tag: u32, field: String },
+
+    #[error("Duplicate tag {0}")]
+    DuplicateTagError(u32),
+
+    #[error("Unexpected tag {0}")]
+    UnexpectedTagError(u32),
+
+    #[error("{0} trailing bytes after the packet")]
+    TrailingBytesError(usize),
+
+    #[error("Non-canonical encoding: {0}")]
+    NonCanonicalError(String),
+
+    #[error("Signature algorithm mismatch: {0}")]
+    AlgorithmMismatchError(String),
+
+    #[error("Issuance policy error: {0}")]
+    IssuancePolicyError(String),
+
+    #[error("CRL error: {0}")]
+    CrlError(String),
+
+    #[error("OCSP error: {0}")]
+    OcspError(String),
+
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
+    #[error("IO error: {0}")]
+    IoError(String),
+
+    #[error("Key encapsulation error: {0}")]
+    KeyEncapsulationError(String),
+
+    #[error("Key derivation error: {0}")]
+    KeyDerivationError(String),
+
+    #[error("Key store error: {0}")]
+    KeyStoreError(String),
+
+    #[error("Wrong passphrase or corrupted key file")]
+    KeyDecryptionError,
+
+    #[error("Handshake error: {0}")]
+    HandshakeError(String),
+
+    #[error("Record authentication failed")]
+    RecordAuthenticationError,
+
+    #[error("Replayed record: sequence {received}, expected {expected}")]
+    RecordReplayError { expected: u64, received: u64 },
+
+    #[error("Record out of order: sequence {received}, expected {expected}")]
+    RecordSequenceError { expected: u64, received: u64 },
+
+    #[error("Record too large: {0} bytes")]
+    RecordTooLargeError(usize),
+
+    #[error("Unexpected record type {0}")]
+    RecordTypeError(u8),
 }
 
 
@@ -77,11 +145,219 @@ pub enum CertificateVersion{
 } 
 
 // Enum: Signature Algorithm
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SignatureAlgorithm {
     Ed25519,
     Falcon512,
     Falcon1024,
+    MlDsa44,
+    MlDsa65,
+    MlDsa87,
+    SlhDsaSha2_128s,
+    SlhDsaShake256s,
+    /// Composite: both an Ed25519 and a Falcon-512 signature, both must verify
+    Ed25519Falcon512,
+}
+
+impl SignatureAlgorithm {
+    pub fn all() -> &'static [SignatureAlgorithm] {
+        &[
+            SignatureAlgorithm::Ed25519,
+            SignatureAlgorithm::Falcon512,
+            SignatureAlgorithm::Falcon1024,
+            SignatureAlgorithm::MlDsa44,
+            SignatureAlgorithm::MlDsa65,
+            SignatureAlgorithm::MlDsa87,
+            SignatureAlgorithm::SlhDsaSha2_128s,
+            SignatureAlgorithm::SlhDsaShake256s,
+            SignatureAlgorithm::Ed25519Falcon512,
+        ]
+    }
+
+    /// Sizes, security category and relative cost, for choosing an algorithm per use case
+    pub fn info(&self) -> AlgorithmInfo {
+        match self {
+            SignatureAlgorithm::Ed25519 => AlgorithmInfo {
+                name:               "Ed25519",
+                security_level:     0,
+                public_key_size:    32,
+                private_key_size:   32,
+                signature_size:     64,
+                signing_speed:      Speed::Fast,
+                verification_speed: Speed::Fast,
+                notes:              "Classical only, broken by a large quantum computer",
+            },
+            SignatureAlgorithm::Falcon512 => AlgorithmInfo {
+                name:               "Falcon-512",
+                security_level:     1,
+                public_key_size:    897,
+                private_key_size:   1281,
+                signature_size:     752,
+                signing_speed:      Speed::Moderate,
+                verification_speed: Speed::Fast,
+                notes:              "Smallest PQ signatures, variable length; signing needs floating point",
+            },
+            SignatureAlgorithm::Falcon1024 => AlgorithmInfo {
+                name:               "Falcon-1024",
+                security_level:     5,
+                public_key_size:    1793,
+                private_key_size:   2305,
+                signature_size:     1462,
+                signing_speed:      Speed::Moderate,
+                verification_speed: Speed::Fast,
+                notes:              "Falcon at category 5, variable length signatures",
+            },
+            SignatureAlgorithm::MlDsa44 => AlgorithmInfo {
+                name:               "ML-DSA-44",
+                security_level:     2,
+                public_key_size:    1312,
+                private_key_size:   32,
+                signature_size:     2420,
+                signing_speed:      Speed::Fast,
+                verification_speed: Speed::Fast,
+                notes:              "FIPS 204 lattice scheme, private key stored as its 32-byte seed",
+            },
+            SignatureAlgorithm::MlDsa65 => AlgorithmInfo {
+                name:               "ML-DSA-65",
+                security_level:     3,
+                public_key_size:    1952,
+                private_key_size:   32,
+                signature_size:     3309,
+                signing_speed:      Speed::Fast,
+                verification_speed: Speed::Fast,
+                notes:              "FIPS 204 lattice scheme, general purpose default",
+            },
+            SignatureAlgorithm::MlDsa87 => AlgorithmInfo {
+                name:               "ML-DSA-87",
+                security_level:     5,
+                public_key_size:    2592,
+                private_key_size:   32,
+                signature_size:     4627,
+                signing_speed:      Speed::Fast,
+                verification_speed: Speed::Fast,
+                notes:              "FIPS 204 lattice scheme at category 5",
+            },
+            SignatureAlgorithm::SlhDsaSha2_128s => AlgorithmInfo {
+                name:               "SLH-DSA-SHA2-128s",
+                security_level:     1,
+                public_key_size:    32,
+                private_key_size:   64,
+                signature_size:     7856,
+                signing_speed:      Speed::Slow,
+                verification_speed: Speed::Moderate,
+                notes:              "FIPS 205 stateless hash-based scheme, relies only on SHA-2; \
+                                     tiny keys, large signatures and slow signing, suited to long-lived roots",
+            },
+            SignatureAlgorithm::SlhDsaShake256s => AlgorithmInfo {
+                name:               "SLH-DSA-SHAKE-256s",
+                security_level:     5,
+                public_key_size:    64,
+                private_key_size:   128,
+                signature_size:     29792,
+                signing_speed:      Speed::Slow,
+                verification_speed: Speed::Moderate,
+                notes:              "FIPS 205 stateless hash-based scheme at category 5, relies only on SHAKE256; \
+                                     very large signatures, for roots that sign rarely",
+            },
+            SignatureAlgorithm::Ed25519Falcon512 => AlgorithmInfo {
+                name:               "Ed25519+Falcon-512",
+                security_level:     1,
+                public_key_size:    32 + 897,
+                private_key_size:   32 + 1281,
+                signature_size:     64 + 752,
+                signing_speed:      Speed::Moderate,
+                verification_speed: Speed::Fast,
+                notes:              "Composite for the PQ transition, secure while either component holds",
+            },
+        }
+    }
+}
+
+// Enum: KEM Algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KemAlgorithm {
+    X25519,
+    MlKem768,
+    /// Hybrid: the shared secret stays safe while either component holds
+    X25519MlKem768,
+}
+
+// Enum: Hash Algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha3_256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn all() -> &'static [HashAlgorithm] {
+        &[
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+            HashAlgorithm::Sha3_256,
+            HashAlgorithm::Blake3,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Sha384 => "SHA-384",
+            HashAlgorithm::Sha512 => "SHA-512",
+            HashAlgorithm::Sha3_256 => "SHA3-256",
+            HashAlgorithm::Blake3 => "BLAKE3",
+        }
+    }
+
+    /// Digest length in bytes
+    pub fn output_size(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+            HashAlgorithm::Sha3_256 => 32,
+            HashAlgorithm::Blake3 => 32,
+        }
+    }
+}
+
+// Enum: Speed (relative cost of an operation, compared across SignatureAlgorithm)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Speed {
+    /// Well under a millisecond
+    Fast,
+    /// Around a millisecond
+    Moderate,
+    /// Tens to hundreds of milliseconds
+    Slow,
+}
+
+
+// Structs:
+
+// AlgorithmInfo: static properties of a SignatureAlgorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlgorithmInfo {
+    pub name:               &'static str,
+    /// NIST PQC security category (1..=5), 0 for schemes without quantum resistance
+    pub security_level:     u8,
+    pub public_key_size:    usize,
+    pub private_key_size:   usize,
+    /// Upper bound in bytes, Falcon signatures are shorter on average
+    pub signature_size:     usize,
+    pub signing_speed:      Speed,
+    pub verification_speed: Speed,
+    pub notes:              &'static str,
+}
+
+impl AlgorithmInfo {
+    pub fn is_post_quantum(&self) -> bool {
+        self.security_level > 0
+    }
 }
 
 
@@ -97,6 +373,13 @@ pub trait SigningOperands {
     fn verify(message: &[u8], public_key: &PublicKey, signature: &Signature) -> Result<bool, StpcError>;
 }
 
+pub trait KeyEncapsulation {
+    fn keypair() -> Result<(PrivateKey, PublicKey), StpcError>;
+    /// Fresh shared secret for the holder of `public_key`, and the ciphertext that carries it
+    fn encapsulate(public_key: &PublicKey) -> Result<(SharedSecret, Ciphertext), StpcError>;
+    fn decapsulate(private_key: &PrivateKey, ciphertext: &Ciphertext) -> Result<SharedSecret, StpcError>;
+}
+
 
 
 
@@ -147,6 +430,34 @@ impl Signature {
     }
 }
 
+// Type: SharedSecret
+#[derive(Debug)]
+pub struct SharedSecret(Zeroizing<Vec<u8>>);
+
+impl SharedSecret {
+    pub fn from_bytes(slice: &[u8]) -> Self {
+        Self(Zeroizing::new(slice.to_vec()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+// Type: Ciphertext (KEM encapsulation, public)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ciphertext(Vec<u8>);
+
+impl Ciphertext {
+    pub fn from_bytes(slice: &[u8]) -> Self {
+        Self(slice.to_vec())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 //Type: PublicKey
 #[derive(Debug)]
 pub struct PublicKey(Zeroizing<Vec<u8>>);
@@ -173,6 +484,58 @@ impl Key for PublicKey {
 }
 
 
+// Type: Fingerprint (digest of a key or certificate, rendered for people)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub algorithm: HashAlgorithm,
+    bytes:         Vec<u8>,
+}
+
+impl Fingerprint {
+    pub fn new(algorithm: HashAlgorithm, bytes: Vec<u8>) -> Self {
+        Self { algorithm, bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Uppercase hex with colons between bytes, e.g. `3A:F0:...`
+    pub fn to_colon_hex(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// RFC 4648 base32 without padding, shorter than hex and case-insensitive
+    pub fn to_base32(&self) -> String {
+        const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+        let mut output = String::with_capacity(self.bytes.len().div_ceil(5) * 8);
+        let mut buffer: u16 = 0;
+        let mut bits = 0;
+        for byte in &self.bytes {
+            buffer = (buffer << 8) | *byte as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                output.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            output.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+        output
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.algorithm.name(), self.to_colon_hex())
+    }
+}
 
 
 // Drops:
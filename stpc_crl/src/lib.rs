@@ -1,14 +1,331 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use stpc_certs::{tlv_serializable, CertSerializable, Certificate, DistinguishedName};
+use stpc_core::{CertificateVersion, SignatureAlgorithm, StpcError};
+use stpc_crypto::{TypedPrivateKey, TypedPublicKey, TypedSignature};
+use stpc_encoding::{Framing, TlvDecode, TlvEncode};
+
+// === TYPES ===
+
+// RevocationReason (RFC 5280 reason codes)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+    CertificateHold,
+    RemoveFromCrl,
+    PrivilegeWithdrawn,
+    AaCompromise,
+}
+
+// RevokedCertificate
+#[derive(Debug, Clone, PartialEq, Eq, TlvEncode, TlvDecode)]
+pub struct RevokedCertificate {
+    #[tlv(tag = 1)]
+    pub serial_number:   [u8; 8],
+    #[tlv(tag = 2)]
+    pub revocation_time: u64,
+    #[tlv(tag = 3)]
+    pub reason:          RevocationReason,
+}
+
+// TbsCertList
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct TbsCertList {
+    #[tlv(tag = 1, with = "stpc_certs::tlv::version")]
+    pub version:              CertificateVersion,
+    #[tlv(tag = 2, with = "stpc_certs::tlv::signature_algorithm")]
+    pub signature_algorithm:  SignatureAlgorithm,
+    #[tlv(tag = 3)]
+    pub issuer:               DistinguishedName,
+    #[tlv(tag = 4)]
+    pub this_update:          u64,
+    #[tlv(tag = 5)]
+    pub next_update:          u64,
+    #[tlv(tag = 6, repeated)]
+    pub revoked_certificates: Vec<RevokedCertificate>,
+    /// Monotonically increasing per issuer
+    #[tlv(tag = 7)]
+    pub crl_number:           u64,
+    /// For delta CRLs: number of the full CRL the changes are relative to
+    #[tlv(tag = 8, optional)]
+    pub base_crl_number:      Option<u64>,
+    /// Framing the list is signed in; set from the packet when decoded
+    #[tlv(framing)]
+    pub framing:              Framing,
+}
+
+// CertificateRevocationList
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct CertificateRevocationList {
+    /// Signed part, accepted only in canonical encoding
+    #[tlv(tag = 1, with = "stpc_encoding::schema::canonical")]
+    pub tbs_cert_list:       TbsCertList,
+    #[tlv(tag = 2, with = "stpc_certs::tlv::signature_algorithm")]
+    pub signature_algorithm: SignatureAlgorithm,
+    #[tlv(tag = 3)]
+    pub signature_value:     Vec<u8>,
+}
+
+// RevocationView: a full CRL with deltas applied on top
+#[derive(Debug, Clone)]
+pub struct RevocationView {
+    pub issuer:          DistinguishedName,
+    pub base_crl_number: u64,
+    pub crl_number:      u64,
+    pub this_update:     u64,
+    pub next_update:     u64,
+    pub revoked:         Vec<RevokedCertificate>,
+}
+
+// === REVOCATION REASON ===
+
+impl RevocationReason {
+    pub fn code(&self) -> u8 {
+        match self {
+            RevocationReason::Unspecified          => 0,
+            RevocationReason::KeyCompromise        => 1,
+            RevocationReason::CaCompromise         => 2,
+            RevocationReason::AffiliationChanged   => 3,
+            RevocationReason::Superseded           => 4,
+            RevocationReason::CessationOfOperation => 5,
+            RevocationReason::CertificateHold      => 6,
+            RevocationReason::RemoveFromCrl        => 8,
+            RevocationReason::PrivilegeWithdrawn   => 9,
+            RevocationReason::AaCompromise         => 10,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Result<Self, StpcError> {
+        match code {
+            0  => Ok(RevocationReason::Unspecified),
+            1  => Ok(RevocationReason::KeyCompromise),
+            2  => Ok(RevocationReason::CaCompromise),
+            3  => Ok(RevocationReason::AffiliationChanged),
+            4  => Ok(RevocationReason::Superseded),
+            5  => Ok(RevocationReason::CessationOfOperation),
+            6  => Ok(RevocationReason::CertificateHold),
+            8  => Ok(RevocationReason::RemoveFromCrl),
+            9  => Ok(RevocationReason::PrivilegeWithdrawn),
+            10 => Ok(RevocationReason::AaCompromise),
+            _  => Err(StpcError::DeserilizateError(format!("Unknown revocation reason {}", code))),
+        }
+    }
+}
+
+impl TlvEncode for RevocationReason {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(vec![self.code()])
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl TlvDecode for RevocationReason {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        match value {
+            [code] => RevocationReason::from_code(*code),
+            _ => Err(StpcError::DeserilizateError("Invalid revocation reason length".into())),
+        }
+    }
+}
+
+// === REVOKED CERTIFICATE ===
+
+impl RevokedCertificate {
+    pub fn new(serial_number: [u8; 8], revocation_time: u64, reason: RevocationReason) -> Self {
+        Self { serial_number, revocation_time, reason }
+    }
+}
+
+tlv_serializable!(RevokedCertificate);
+
+// === TBS CERT LIST ===
+
+impl TbsCertList {
+    pub fn new(
+        signature_algorithm:  SignatureAlgorithm,
+        issuer:               DistinguishedName,
+        this_update:          u64,
+        next_update:          u64,
+        revoked_certificates: Vec<RevokedCertificate>,
+        crl_number:           u64,
+    ) -> Self {
+        Self {
+            version: CertificateVersion::V1,
+            signature_algorithm,
+            issuer,
+            this_update,
+            next_update,
+            revoked_certificates,
+            crl_number,
+            base_crl_number: None,
+            framing: Framing::V1,
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Turn this list into a delta CRL listing only changes since `base_crl_number`
+    pub fn with_delta_base(mut self, base_crl_number: u64) -> Self {
+        self.base_crl_number = Some(base_crl_number);
+        self
+    }
+
+    /// Sign the serialized list with the issuer's private key, which must be of `signature_algorithm`
+    pub fn sign(self, private_key: &TypedPrivateKey) -> Result<CertificateRevocationList, StpcError> {
+        let message = self.serialize()?;
+        let signature = private_key.sign_as(self.signature_algorithm, &message)?;
+        let signature_algorithm = self.signature_algorithm;
+
+        Ok(CertificateRevocationList {
+            tbs_cert_list: self,
+            signature_algorithm,
+            signature_value: signature.signature().as_bytes().to_vec(),
+        })
+    }
+}
+
+tlv_serializable!(TbsCertList);
+
+// === CERTIFICATE REVOCATION LIST ===
+
+impl CertificateRevocationList {
+    /// Verify the signature over the serialized list with the issuer's public key,
+    /// which must be of `signature_algorithm`
+    pub fn verify(&self, public_key: &TypedPublicKey) -> Result<bool, StpcError> {
+        if self.tbs_cert_list.signature_algorithm != self.signature_algorithm {
+            return Err(StpcError::AlgorithmMismatchError(format!(
+                "TBS declares {:?}, CRL declares {:?}",
+                self.tbs_cert_list.signature_algorithm, self.signature_algorithm
+            )));
+        }
+
+        let message = self.tbs_cert_list.serialize()?;
+        let signature = TypedSignature::from_bytes(self.signature_algorithm, &self.signature_value)?;
+
+        public_key.verify(&message, &signature)
+    }
+
+    /// Whether `now` lies between thisUpdate and nextUpdate
+    pub fn is_current(&self, now: u64) -> bool {
+        self.tbs_cert_list.this_update <= now && now <= self.tbs_cert_list.next_update
+    }
+
+    /// Revocation entry for `cert`, if this CRL was issued by its issuer and lists its serial
+    pub fn revoked_entry(&self, cert: &Certificate) -> Option<&RevokedCertificate> {
+        if self.tbs_cert_list.issuer != cert.tbs_certificate.issuser {
+            return None;
+        }
+
+        self.tbs_cert_list
+            .revoked_certificates
+            .iter()
+            .find(|entry| entry.serial_number == cert.tbs_certificate.serial_number)
+    }
+
+    pub fn is_revoked(&self, cert: &Certificate) -> bool {
+        self.revoked_entry(cert).is_some()
+    }
+
+    pub fn is_delta(&self) -> bool {
+        self.tbs_cert_list.base_crl_number.is_some()
+    }
+
+    /// Effective revocation state from this full CRL and `delta`
+    pub fn merge(&self, delta: &CertificateRevocationList) -> Result<RevocationView, StpcError> {
+        let mut view = RevocationView::from_base(self)?;
+        view.apply_delta(delta)?;
+        Ok(view)
+    }
+}
+
+// === REVOCATION VIEW ===
+
+impl RevocationView {
+    pub fn from_base(base: &CertificateRevocationList) -> Result<Self, StpcError> {
+        if base.is_delta() {
+            return Err(StpcError::CrlError("Base must be a full CRL, got a delta".into()));
+        }
+
+        let tbs = &base.tbs_cert_list;
+        Ok(Self {
+            issuer:          tbs.issuer.clone(),
+            base_crl_number: tbs.crl_number,
+            crl_number:      tbs.crl_number,
+            this_update:     tbs.this_update,
+            next_update:     tbs.next_update,
+            revoked:         tbs.revoked_certificates.clone(),
+        })
+    }
+
+    /// Apply a delta CRL. The delta must come from the same issuer, be newer than
+    /// anything applied so far and reference a base no newer than the one in this view
+    pub fn apply_delta(&mut self, delta: &CertificateRevocationList) -> Result<(), StpcError> {
+        let tbs = &delta.tbs_cert_list;
+        let delta_base = tbs
+            .base_crl_number
+            .ok_or_else(|| StpcError::CrlError("Expected a delta CRL, got a full CRL".into()))?;
+
+        if tbs.issuer != self.issuer {
+            return Err(StpcError::CrlError("Delta CRL issuer does not match base CRL issuer".into()));
+        }
+        if delta_base > self.base_crl_number {
+            return Err(StpcError::CrlError(format!(
+                "Base CRL {} is stale, delta requires base {} or newer",
+                self.base_crl_number, delta_base
+            )));
+        }
+        if tbs.crl_number <= self.crl_number {
+            return Err(StpcError::CrlError(format!(
+                "Delta CRL number {} is not newer than {}",
+                tbs.crl_number, self.crl_number
+            )));
+        }
+
+        for entry in &tbs.revoked_certificates {
+            self.revoked.retain(|e| e.serial_number != entry.serial_number);
+            if entry.reason != RevocationReason::RemoveFromCrl {
+                self.revoked.push(entry.clone());
+            }
+        }
+
+        self.crl_number = tbs.crl_number;
+        self.this_update = tbs.this_update;
+        self.next_update = tbs.next_update;
+
+        Ok(())
+    }
+
+    pub fn revoked_entry(&self, cert: &Certificate) -> Option<&RevokedCertificate> {
+        if self.issuer != cert.tbs_certificate.issuser {
+            return None;
+        }
+
+        self.revoked
+            .iter()
+            .find(|entry| entry.serial_number == cert.tbs_certificate.serial_number)
+    }
+
+    pub fn is_revoked(&self, cert: &Certificate) -> bool {
+        self.revoked_entry(cert).is_some()
+    }
+}
+
+impl CertSerializable for CertificateRevocationList {
+    /// In the framing of the signed list
+    fn serialize(&self) -> Result<Vec<u8>, StpcError> {
+        self.tlv_encode_as(self.tbs_cert_list.framing)
+    }
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
+        let crl = Self::tlv_decode(data)?;
+        if Framing::detect(data)? != crl.tbs_cert_list.framing {
+            return Err(StpcError::NonCanonicalError("CRL and its signed list use different framings".into()));
+        }
+        Ok(crl)
     }
 }
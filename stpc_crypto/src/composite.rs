@@ -0,0 +1,141 @@
+// Composite Ed25519 + Falcon-512 signatures for the post-quantum transition.
+
+use stpc_core::Key;
+use stpc_core::PrivateKey;
+use stpc_core::PublicKey;
+use stpc_core::Signature;
+use stpc_core::SigningOperands;
+use stpc_core::StpcError;
+
+use zeroize::Zeroizing;
+
+use crate::{Ed25519, Falcon512};
+
+/// Prefix of the message signed by both components, so neither component verifies
+/// on its own as a plain signature over the same message
+pub const DOMAIN: &[u8] = b"STPC-COMPOSITE-ED25519-FALCON512";
+
+const ED25519_PUBLIC_KEY_SIZE: usize = 32;
+const ED25519_PRIVATE_KEY_SIZE: usize = 32;
+const ED25519_SIGNATURE_SIZE: usize = 64;
+
+/// Keys and signatures are the Ed25519 component (fixed size) followed by the
+/// Falcon-512 component
+pub struct Ed25519Falcon512 {}
+
+// CompositePublicKey
+#[derive(Debug)]
+pub struct CompositePublicKey {
+    pub classical:    PublicKey,
+    pub post_quantum: PublicKey,
+}
+
+impl CompositePublicKey {
+    pub fn new(classical: PublicKey, post_quantum: PublicKey) -> Self {
+        Self { classical, post_quantum }
+    }
+
+    pub fn encode(&self) -> PublicKey {
+        PublicKey::from_bytes(&[self.classical.as_bytes(), self.post_quantum.as_bytes()].concat())
+    }
+
+    pub fn decode(public_key: &PublicKey) -> Result<Self, StpcError> {
+        let (classical, post_quantum) = split(public_key.as_bytes(), ED25519_PUBLIC_KEY_SIZE)
+            .ok_or_else(|| StpcError::InvalidKeyError("Invalid composite public key".into()))?;
+
+        Ok(Self::new(PublicKey::from_bytes(classical), PublicKey::from_bytes(post_quantum)))
+    }
+}
+
+// CompositePrivateKey
+#[derive(Debug)]
+pub struct CompositePrivateKey {
+    pub classical:    PrivateKey,
+    pub post_quantum: PrivateKey,
+}
+
+impl CompositePrivateKey {
+    pub fn new(classical: PrivateKey, post_quantum: PrivateKey) -> Self {
+        Self { classical, post_quantum }
+    }
+
+    pub fn encode(&self) -> PrivateKey {
+        let mut bytes = Zeroizing::new(self.classical.as_bytes().to_vec());
+        bytes.extend_from_slice(self.post_quantum.as_bytes());
+
+        PrivateKey::from_bytes(&bytes)
+    }
+
+    pub fn decode(private_key: &PrivateKey) -> Result<Self, StpcError> {
+        let (classical, post_quantum) = split(private_key.as_bytes(), ED25519_PRIVATE_KEY_SIZE)
+            .ok_or_else(|| StpcError::InvalidKeyError("Invalid composite private key".into()))?;
+
+        Ok(Self::new(PrivateKey::from_bytes(classical), PrivateKey::from_bytes(post_quantum)))
+    }
+}
+
+// CompositeSignature
+#[derive(Debug)]
+pub struct CompositeSignature {
+    pub classical:    Signature,
+    pub post_quantum: Signature,
+}
+
+impl CompositeSignature {
+    pub fn new(classical: Signature, post_quantum: Signature) -> Self {
+        Self { classical, post_quantum }
+    }
+
+    pub fn encode(&self) -> Signature {
+        Signature::from_bytes(&[self.classical.as_bytes(), self.post_quantum.as_bytes()].concat())
+    }
+
+    pub fn decode(signature: &Signature) -> Result<Self, StpcError> {
+        let (classical, post_quantum) =
+            split(signature.as_bytes(), ED25519_SIGNATURE_SIZE).ok_or(StpcError::SignatureVerifyError)?;
+
+        Ok(Self::new(Signature::from_bytes(classical), Signature::from_bytes(post_quantum)))
+    }
+}
+
+impl SigningOperands for Ed25519Falcon512 {
+    fn keypair() -> Result<(PrivateKey, PublicKey), StpcError> {
+        let (classical_private, classical_public) = Ed25519::keypair()?;
+        let (post_quantum_private, post_quantum_public) = Falcon512::keypair()?;
+
+        let private = CompositePrivateKey::new(classical_private, post_quantum_private);
+        let public = CompositePublicKey::new(classical_public, post_quantum_public);
+        Ok((private.encode(), public.encode()))
+    }
+
+    fn sign(message: &[u8], private_key: &PrivateKey) -> Result<Signature, StpcError> {
+        let private_key = CompositePrivateKey::decode(private_key)?;
+        let message = [DOMAIN, message].concat();
+
+        let classical = Ed25519::sign(&message, &private_key.classical)?;
+        let post_quantum = Falcon512::sign(&message, &private_key.post_quantum)?;
+
+        Ok(CompositeSignature::new(classical, post_quantum).encode())
+    }
+
+    /// Valid only if both the Ed25519 and the Falcon-512 component verify
+    fn verify(message: &[u8], public_key: &PublicKey, signature: &Signature) -> Result<bool, StpcError> {
+        let public_key = CompositePublicKey::decode(public_key)?;
+        let signature = CompositeSignature::decode(signature)?;
+        let message = [DOMAIN, message].concat();
+
+        let classical = Ed25519::verify(&message, &public_key.classical, &signature.classical)?;
+        let post_quantum = Falcon512::verify(&message, &public_key.post_quantum, &signature.post_quantum)?;
+
+        Ok(classical && post_quantum)
+    }
+}
+
+/// Split after the fixed-size classical component; both parts must be non-empty
+fn split(bytes: &[u8], classical_size: usize) -> Option<(&[u8], &[u8])> {
+    if bytes.len() <= classical_size {
+        return None;
+    }
+
+    Some(bytes.split_at(classical_size))
+}
@@ -0,0 +1,172 @@
+// Hashing, HMAC and HKDF over every HashAlgorithm.
+
+use blake3::Hasher as Blake3;
+use hkdf::SimpleHkdf;
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::digest::Digest;
+use hmac::{Mac, SimpleHmac};
+use sha2::{Sha256, Sha384, Sha512};
+use sha3::Sha3_256;
+use zeroize::Zeroizing;
+
+use stpc_core::{Fingerprint, HashAlgorithm, Key, PublicKey};
+use stpc_core::StpcError;
+
+/// Incremental hash: fed in pieces, then finalized
+pub trait Hasher {
+    fn algorithm(&self) -> HashAlgorithm;
+
+    fn update(&mut self, data: &[u8]);
+
+    /// Digest of everything fed so far; the hasher starts over empty
+    fn finalize_reset(&mut self) -> Vec<u8>;
+}
+
+macro_rules! hasher {
+    ($type:ty, $algorithm:expr) => {
+        impl Hasher for $type {
+            fn algorithm(&self) -> HashAlgorithm {
+                $algorithm
+            }
+
+            fn update(&mut self, data: &[u8]) {
+                Digest::update(self, data);
+            }
+
+            fn finalize_reset(&mut self) -> Vec<u8> {
+                Digest::finalize_reset(self).to_vec()
+            }
+        }
+    };
+}
+
+hasher!(Sha256, HashAlgorithm::Sha256);
+hasher!(Sha384, HashAlgorithm::Sha384);
+hasher!(Sha512, HashAlgorithm::Sha512);
+hasher!(Sha3_256, HashAlgorithm::Sha3_256);
+hasher!(Blake3, HashAlgorithm::Blake3);
+
+/// Call a generic function with the digest type of a HashAlgorithm
+macro_rules! with_digest {
+    ($algorithm:expr, $function:ident($($arg:expr),*)) => {
+        match $algorithm {
+            HashAlgorithm::Sha256 => $function::<Sha256>($($arg),*),
+            HashAlgorithm::Sha384 => $function::<Sha384>($($arg),*),
+            HashAlgorithm::Sha512 => $function::<Sha512>($($arg),*),
+            HashAlgorithm::Sha3_256 => $function::<Sha3_256>($($arg),*),
+            HashAlgorithm::Blake3 => $function::<Blake3>($($arg),*),
+        }
+    };
+}
+
+// Runtime dispatch over HashAlgorithm:
+
+/// Empty streaming hasher for `algorithm`
+pub fn hasher(algorithm: HashAlgorithm) -> Box<dyn Hasher + Send> {
+    match algorithm {
+        HashAlgorithm::Sha256 => Box::new(Sha256::new()),
+        HashAlgorithm::Sha384 => Box::new(Sha384::new()),
+        HashAlgorithm::Sha512 => Box::new(Sha512::new()),
+        HashAlgorithm::Sha3_256 => Box::new(Sha3_256::new()),
+        HashAlgorithm::Blake3 => Box::new(Blake3::new()),
+    }
+}
+
+/// One-shot digest of `data`
+pub fn digest(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    with_digest!(algorithm, digest_with(data))
+}
+
+/// SHA-256 over the raw key bytes, the key's identifier in allow-lists and logs
+pub fn key_id(public_key: &PublicKey) -> Fingerprint {
+    Fingerprint::new(HashAlgorithm::Sha256, digest(HashAlgorithm::Sha256, public_key.as_bytes()))
+}
+
+/// Identifier methods for keys defined in stpc_core
+pub trait PublicKeyExt {
+    /// See [`key_id`]
+    fn key_id(&self) -> Fingerprint;
+}
+
+impl PublicKeyExt for PublicKey {
+    fn key_id(&self) -> Fingerprint {
+        key_id(self)
+    }
+}
+
+/// HMAC tag of `data` under `key`
+pub fn hmac(algorithm: HashAlgorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    with_digest!(algorithm, hmac_tag_with(key, data))
+}
+
+/// Constant-time check of an HMAC tag
+pub fn hmac_verify(algorithm: HashAlgorithm, key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    with_digest!(algorithm, hmac_verify_with(key, data, tag))
+}
+
+/// HKDF-Extract: pseudorandom key from input keying material
+pub fn hkdf_extract(algorithm: HashAlgorithm, salt: &[u8], ikm: &[u8]) -> Zeroizing<Vec<u8>> {
+    with_digest!(algorithm, hkdf_extract_with(salt, ikm))
+}
+
+/// HKDF-Expand: `length` bytes of output keying material from a pseudorandom key
+pub fn hkdf_expand(
+    algorithm: HashAlgorithm,
+    prk: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Zeroizing<Vec<u8>>, StpcError> {
+    with_digest!(algorithm, hkdf_expand_with(prk, info, length))
+}
+
+/// HKDF: Extract followed by Expand
+pub fn hkdf(
+    algorithm: HashAlgorithm,
+    salt: &[u8],
+    ikm: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Zeroizing<Vec<u8>>, StpcError> {
+    let prk = hkdf_extract(algorithm, salt, ikm);
+    hkdf_expand(algorithm, &prk, info, length)
+}
+
+// Generic implementations:
+
+fn digest_with<D: Digest>(data: &[u8]) -> Vec<u8> {
+    D::digest(data).to_vec()
+}
+
+/// SimpleHmac needs nothing beyond Digest + BlockSizeUser, which BLAKE3 provides too
+fn hmac_with<D: Digest + BlockSizeUser + Clone>(key: &[u8], data: &[u8]) -> SimpleHmac<D> {
+    let mut mac = <SimpleHmac<D> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac
+}
+
+fn hmac_tag_with<D: Digest + BlockSizeUser + Clone>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac_with::<D>(key, data).finalize().into_bytes().to_vec()
+}
+
+fn hmac_verify_with<D: Digest + BlockSizeUser + Clone>(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    hmac_with::<D>(key, data).verify_slice(tag).is_ok()
+}
+
+fn hkdf_extract_with<D: Digest + BlockSizeUser + Clone>(salt: &[u8], ikm: &[u8]) -> Zeroizing<Vec<u8>> {
+    let (prk, _) = SimpleHkdf::<D>::extract(Some(salt), ikm);
+    Zeroizing::new(prk.to_vec())
+}
+
+fn hkdf_expand_with<D: Digest + BlockSizeUser + Clone>(
+    prk: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Zeroizing<Vec<u8>>, StpcError> {
+    let hkdf = SimpleHkdf::<D>::from_prk(prk)
+        .map_err(|_| StpcError::KeyDerivationError("Pseudorandom key shorter than the hash output".into()))?;
+
+    let mut output = Zeroizing::new(vec![0u8; length]);
+    hkdf.expand(info, &mut output)
+        .map_err(|_| StpcError::KeyDerivationError(format!("Cannot expand to {} bytes", length)))?;
+    Ok(output)
+}
@@ -0,0 +1,278 @@
+// Key encapsulation for establishing shared secrets.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use sha3::{Digest, Sha3_256};
+use zeroize::Zeroizing;
+
+use ml_kem::{KeyExport, MlKem768 as MlKem768Params};
+
+use stpc_core::Ciphertext;
+use stpc_core::Key;
+use stpc_core::KemAlgorithm;
+use stpc_core::KeyEncapsulation;
+use stpc_core::PrivateKey;
+use stpc_core::PublicKey;
+use stpc_core::SharedSecret;
+use stpc_core::StpcError;
+
+const X25519_KEY_SIZE: usize = 32;
+const ML_KEM_768_PUBLIC_KEY_SIZE: usize = 1184;
+const ML_KEM_768_PRIVATE_KEY_SIZE: usize = 64;
+const ML_KEM_768_CIPHERTEXT_SIZE: usize = 1088;
+
+const X25519_LABEL: &[u8] = b"STPC-X25519-KEM";
+/// X-Wing combiner label
+const HYBRID_LABEL: &[u8] = br"\.//^\";
+
+// KEM classes (обязательное наследование от trait KeyEncapsulation):
+pub struct X25519 {}
+pub struct MlKem768 {}
+/// Keys and ciphertexts are the ML-KEM-768 component followed by the X25519 one
+pub struct X25519MlKem768 {}
+
+
+// Runtime dispatch over KemAlgorithm:
+
+/// Generate a keypair for `algorithm`
+pub fn keypair(algorithm: KemAlgorithm) -> Result<(PrivateKey, PublicKey), StpcError> {
+    match algorithm {
+        KemAlgorithm::X25519 => X25519::keypair(),
+        KemAlgorithm::MlKem768 => MlKem768::keypair(),
+        KemAlgorithm::X25519MlKem768 => X25519MlKem768::keypair(),
+    }
+}
+
+pub fn encapsulate(algorithm: KemAlgorithm, public_key: &PublicKey) -> Result<(SharedSecret, Ciphertext), StpcError> {
+    match algorithm {
+        KemAlgorithm::X25519 => X25519::encapsulate(public_key),
+        KemAlgorithm::MlKem768 => MlKem768::encapsulate(public_key),
+        KemAlgorithm::X25519MlKem768 => X25519MlKem768::encapsulate(public_key),
+    }
+}
+
+pub fn decapsulate(
+    algorithm: KemAlgorithm,
+    private_key: &PrivateKey,
+    ciphertext: &Ciphertext,
+) -> Result<SharedSecret, StpcError> {
+    match algorithm {
+        KemAlgorithm::X25519 => X25519::decapsulate(private_key, ciphertext),
+        KemAlgorithm::MlKem768 => MlKem768::decapsulate(private_key, ciphertext),
+        KemAlgorithm::X25519MlKem768 => X25519MlKem768::decapsulate(private_key, ciphertext),
+    }
+}
+
+
+// X25519 as a KEM: the ciphertext is an ephemeral public key, the shared secret
+// is SHA3-256 over the Diffie-Hellman output and both public keys.
+
+impl X25519 {
+    /// Keypair from a fixed 32-byte secret (RFC 7748 clamping applies)
+    pub fn keypair_from_secret(secret: &[u8; 32]) -> (PrivateKey, PublicKey) {
+        let secret = x25519_dalek::StaticSecret::from(*secret);
+        let public = x25519_dalek::PublicKey::from(&secret);
+
+        (PrivateKey::from_bytes(secret.as_bytes()), PublicKey::from_bytes(public.as_bytes()))
+    }
+}
+
+impl KeyEncapsulation for X25519 {
+    fn keypair() -> Result<(PrivateKey, PublicKey), StpcError> {
+        let mut secret = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(secret.as_mut());
+
+        Ok(Self::keypair_from_secret(&secret))
+    }
+
+    fn encapsulate(public_key: &PublicKey) -> Result<(SharedSecret, Ciphertext), StpcError> {
+        let (shared, ephemeral) = x25519_encapsulate(public_key.as_bytes())?;
+        let secret = x25519_shared_secret(shared.as_slice(), &ephemeral, public_key.as_bytes());
+
+        Ok((secret, Ciphertext::from_bytes(&ephemeral)))
+    }
+
+    fn decapsulate(private_key: &PrivateKey, ciphertext: &Ciphertext) -> Result<SharedSecret, StpcError> {
+        let (shared, public) = x25519_decapsulate(private_key.as_bytes(), ciphertext.as_bytes())?;
+
+        Ok(x25519_shared_secret(shared.as_slice(), ciphertext.as_bytes(), &public))
+    }
+}
+
+
+// ML-KEM-768 (FIPS 203). The private key is the 64-byte seed d || z.
+
+impl MlKem768 {
+    /// Deterministic ML-KEM.KeyGen_internal from a 64-byte seed
+    pub fn keypair_from_seed(seed: &[u8; 64]) -> (PrivateKey, PublicKey) {
+        let decapsulation_key = ml_kem::DecapsulationKey::<MlKem768Params>::from_seed((*seed).into());
+        let public = decapsulation_key.encapsulation_key().to_bytes();
+
+        (PrivateKey::from_bytes(seed), PublicKey::from_bytes(&public))
+    }
+}
+
+impl KeyEncapsulation for MlKem768 {
+    fn keypair() -> Result<(PrivateKey, PublicKey), StpcError> {
+        let mut seed = Zeroizing::new([0u8; 64]);
+        OsRng.fill_bytes(seed.as_mut());
+
+        Ok(Self::keypair_from_seed(&seed))
+    }
+
+    fn encapsulate(public_key: &PublicKey) -> Result<(SharedSecret, Ciphertext), StpcError> {
+        let (shared, ciphertext) = ml_kem_encapsulate(public_key.as_bytes())?;
+
+        Ok((SharedSecret::from_bytes(shared.as_slice()), Ciphertext::from_bytes(&ciphertext)))
+    }
+
+    fn decapsulate(private_key: &PrivateKey, ciphertext: &Ciphertext) -> Result<SharedSecret, StpcError> {
+        let shared = ml_kem_decapsulate(private_key.as_bytes(), ciphertext.as_bytes())?;
+
+        Ok(SharedSecret::from_bytes(shared.as_slice()))
+    }
+}
+
+
+// Hybrid X25519 + ML-KEM-768
+
+impl KeyEncapsulation for X25519MlKem768 {
+    fn keypair() -> Result<(PrivateKey, PublicKey), StpcError> {
+        let (ml_kem_private, ml_kem_public) = MlKem768::keypair()?;
+        let (x25519_private, x25519_public) = X25519::keypair()?;
+
+        let mut private = Zeroizing::new(ml_kem_private.as_bytes().to_vec());
+        private.extend_from_slice(x25519_private.as_bytes());
+        let public = [ml_kem_public.as_bytes(), x25519_public.as_bytes()].concat();
+
+        Ok((PrivateKey::from_bytes(&private), PublicKey::from_bytes(&public)))
+    }
+
+    fn encapsulate(public_key: &PublicKey) -> Result<(SharedSecret, Ciphertext), StpcError> {
+        let (ml_kem_public, x25519_public) = split(public_key.as_bytes(), ML_KEM_768_PUBLIC_KEY_SIZE, "public key")?;
+
+        let (ml_kem_shared, ml_kem_ciphertext) = ml_kem_encapsulate(ml_kem_public)?;
+        let (x25519_shared, x25519_ciphertext) = x25519_encapsulate(x25519_public)?;
+
+        let secret = hybrid_shared_secret(ml_kem_shared.as_slice(), x25519_shared.as_slice(), &x25519_ciphertext, x25519_public);
+        Ok((secret, Ciphertext::from_bytes(&[ml_kem_ciphertext.as_slice(), &x25519_ciphertext].concat())))
+    }
+
+    fn decapsulate(private_key: &PrivateKey, ciphertext: &Ciphertext) -> Result<SharedSecret, StpcError> {
+        let (ml_kem_private, x25519_private) = split(private_key.as_bytes(), ML_KEM_768_PRIVATE_KEY_SIZE, "private key")?;
+        let (ml_kem_ciphertext, x25519_ciphertext) = split(ciphertext.as_bytes(), ML_KEM_768_CIPHERTEXT_SIZE, "ciphertext")?;
+
+        let ml_kem_shared = ml_kem_decapsulate(ml_kem_private, ml_kem_ciphertext)?;
+        let (x25519_shared, x25519_public) = x25519_decapsulate(x25519_private, x25519_ciphertext)?;
+
+        Ok(hybrid_shared_secret(ml_kem_shared.as_slice(), x25519_shared.as_slice(), x25519_ciphertext, &x25519_public))
+    }
+}
+
+
+// Helpers:
+
+/// Ephemeral Diffie-Hellman with `public_key`: (raw shared output, ephemeral public key)
+fn x25519_encapsulate(public_key: &[u8]) -> Result<(Zeroizing<[u8; 32]>, [u8; 32]), StpcError> {
+    let public_key: [u8; 32] = public_key
+        .try_into()
+        .map_err(|_| StpcError::KeyEncapsulationError("X25519 public key must be 32 bytes".into()))?;
+
+    let mut secret = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(secret.as_mut());
+    let ephemeral = x25519_dalek::StaticSecret::from(*secret);
+
+    let shared = ephemeral.diffie_hellman(&x25519_dalek::PublicKey::from(public_key));
+    if !shared.was_contributory() {
+        return Err(StpcError::KeyEncapsulationError("X25519 public key has low order".into()));
+    }
+
+    Ok((Zeroizing::new(shared.to_bytes()), *x25519_dalek::PublicKey::from(&ephemeral).as_bytes()))
+}
+
+/// Diffie-Hellman with the peer's ephemeral key: (raw shared output, own public key)
+fn x25519_decapsulate(private_key: &[u8], ciphertext: &[u8]) -> Result<(Zeroizing<[u8; 32]>, [u8; 32]), StpcError> {
+    let private_key: [u8; 32] = private_key
+        .try_into()
+        .map_err(|_| StpcError::KeyEncapsulationError("X25519 private key must be 32 bytes".into()))?;
+    let ephemeral: [u8; 32] = ciphertext
+        .try_into()
+        .map_err(|_| StpcError::KeyEncapsulationError("X25519 ciphertext must be 32 bytes".into()))?;
+
+    let secret = x25519_dalek::StaticSecret::from(private_key);
+    let shared = secret.diffie_hellman(&x25519_dalek::PublicKey::from(ephemeral));
+    if !shared.was_contributory() {
+        return Err(StpcError::KeyEncapsulationError("X25519 ciphertext has low order".into()));
+    }
+
+    Ok((Zeroizing::new(shared.to_bytes()), *x25519_dalek::PublicKey::from(&secret).as_bytes()))
+}
+
+fn x25519_shared_secret(shared: &[u8], ciphertext: &[u8], public_key: &[u8]) -> SharedSecret {
+    let digest: Zeroizing<[u8; 32]> = Zeroizing::new(
+        Sha3_256::new()
+            .chain_update(shared)
+            .chain_update(ciphertext)
+            .chain_update(public_key)
+            .chain_update(X25519_LABEL)
+            .finalize()
+            .into(),
+    );
+
+    SharedSecret::from_bytes(digest.as_slice())
+}
+
+fn ml_kem_encapsulate(public_key: &[u8]) -> Result<(Zeroizing<[u8; 32]>, Vec<u8>), StpcError> {
+    let encoded = public_key
+        .try_into()
+        .map_err(|_| StpcError::KeyEncapsulationError("Invalid ML-KEM-768 public key".into()))?;
+    let encapsulation_key = ml_kem::EncapsulationKey::<MlKem768Params>::new(encoded)
+        .map_err(|_| StpcError::KeyEncapsulationError("Invalid ML-KEM-768 public key".into()))?;
+
+    let mut randomness = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(randomness.as_mut());
+
+    let (ciphertext, shared) = encapsulation_key.encapsulate_deterministic(&(*randomness).into());
+    Ok((Zeroizing::new(shared.into()), ciphertext.to_vec()))
+}
+
+fn ml_kem_decapsulate(private_key: &[u8], ciphertext: &[u8]) -> Result<Zeroizing<[u8; 32]>, StpcError> {
+    let seed: [u8; 64] = private_key
+        .try_into()
+        .map_err(|_| StpcError::KeyEncapsulationError("ML-KEM-768 private key must be 64 bytes".into()))?;
+    let seed = Zeroizing::new(seed);
+    let ciphertext = ciphertext
+        .try_into()
+        .map_err(|_| StpcError::KeyEncapsulationError("Invalid ML-KEM-768 ciphertext".into()))?;
+
+    let decapsulation_key = ml_kem::DecapsulationKey::<MlKem768Params>::from_seed((*seed).into());
+    let shared = ml_kem::Decapsulate::decapsulate(&decapsulation_key, ciphertext);
+
+    Ok(Zeroizing::new(shared.into()))
+}
+
+/// X-Wing combiner: both shared secrets, bound to the X25519 ciphertext and public key
+fn hybrid_shared_secret(ml_kem: &[u8], x25519: &[u8], x25519_ciphertext: &[u8], x25519_public: &[u8]) -> SharedSecret {
+    let digest: Zeroizing<[u8; 32]> = Zeroizing::new(
+        Sha3_256::new()
+            .chain_update(ml_kem)
+            .chain_update(x25519)
+            .chain_update(x25519_ciphertext)
+            .chain_update(x25519_public)
+            .chain_update(HYBRID_LABEL)
+            .finalize()
+            .into(),
+    );
+
+    SharedSecret::from_bytes(digest.as_slice())
+}
+
+/// Split a hybrid value after its ML-KEM component; the rest must be an X25519 value
+fn split<'a>(bytes: &'a [u8], ml_kem_size: usize, what: &str) -> Result<(&'a [u8], &'a [u8]), StpcError> {
+    if bytes.len() != ml_kem_size + X25519_KEY_SIZE {
+        return Err(StpcError::KeyEncapsulationError(format!("Invalid X25519+ML-KEM-768 {}", what)));
+    }
+
+    Ok(bytes.split_at(ml_kem_size))
+}
@@ -0,0 +1,310 @@
+// Keys and signatures bound to their SignatureAlgorithm.
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use ml_dsa::{MlDsa44, MlDsa65, MlDsa87, MlDsaParams};
+use pqcrypto_falcon::{falcon1024, falcon512};
+use pqcrypto_traits::sign::{DetachedSignature as _, PublicKey as _, SecretKey as _};
+use slh_dsa::{Sha2_128s, Shake256s};
+use zeroize::Zeroizing;
+
+use stpc_core::Fingerprint;
+use stpc_core::Key;
+use stpc_core::PrivateKey;
+use stpc_core::PublicKey;
+use stpc_core::Signature;
+use stpc_core::SignatureAlgorithm;
+use stpc_core::StpcError;
+
+use crate::composite::{CompositePrivateKey, CompositePublicKey, CompositeSignature};
+use crate::hash::PublicKeyExt;
+
+// TypedPrivateKey
+/// Private key that passed validate_private_key for its algorithm. Views as
+/// another algorithm's key type fail with AlgorithmMismatchError
+#[derive(Debug)]
+pub struct TypedPrivateKey {
+    algorithm: SignatureAlgorithm,
+    key:       PrivateKey,
+}
+
+// TypedPublicKey
+/// Public key that passed validate_public_key for its algorithm. Verifying a
+/// signature of another algorithm fails with AlgorithmMismatchError
+#[derive(Debug)]
+pub struct TypedPublicKey {
+    algorithm: SignatureAlgorithm,
+    key:       PublicKey,
+}
+
+// TypedSignature
+/// Signature that passed validate_signature for its algorithm
+#[derive(Debug)]
+pub struct TypedSignature {
+    algorithm: SignatureAlgorithm,
+    signature: Signature,
+}
+
+// === PRIVATE KEY ===
+
+impl TypedPrivateKey {
+    pub fn new(algorithm: SignatureAlgorithm, key: PrivateKey) -> Result<Self, StpcError> {
+        crate::validate_private_key(algorithm, key.as_bytes())?;
+        Ok(Self { algorithm, key })
+    }
+
+    pub fn from_bytes(algorithm: SignatureAlgorithm, bytes: &[u8]) -> Result<Self, StpcError> {
+        Self::new(algorithm, PrivateKey::from_bytes(bytes))
+    }
+
+    /// Fresh keypair of `algorithm`
+    pub fn generate(algorithm: SignatureAlgorithm) -> Result<(TypedPrivateKey, TypedPublicKey), StpcError> {
+        let (private, public) = crate::keypair(algorithm)?;
+        Ok((Self { algorithm, key: private }, TypedPublicKey { algorithm, key: public }))
+    }
+
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    pub fn key(&self) -> &PrivateKey {
+        &self.key
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<TypedSignature, StpcError> {
+        let signature = crate::sign(self.algorithm, message, &self.key)?;
+        Ok(TypedSignature { algorithm: self.algorithm, signature })
+    }
+
+    /// Sign for a structure that declares `algorithm`, which must be this key's
+    pub fn sign_as(&self, algorithm: SignatureAlgorithm, message: &[u8]) -> Result<TypedSignature, StpcError> {
+        expect(algorithm, self.algorithm)?;
+        self.sign(message)
+    }
+
+    pub fn as_ed25519(&self) -> Result<SigningKey, StpcError> {
+        expect(SignatureAlgorithm::Ed25519, self.algorithm)?;
+        let bytes: [u8; 32] = self.key.as_bytes().try_into().expect("validated on construction");
+        Ok(SigningKey::from_bytes(&bytes))
+    }
+
+    pub fn as_falcon512(&self) -> Result<falcon512::SecretKey, StpcError> {
+        expect(SignatureAlgorithm::Falcon512, self.algorithm)?;
+        Ok(falcon512::SecretKey::from_bytes(self.key.as_bytes()).expect("validated on construction"))
+    }
+
+    pub fn as_falcon1024(&self) -> Result<falcon1024::SecretKey, StpcError> {
+        expect(SignatureAlgorithm::Falcon1024, self.algorithm)?;
+        Ok(falcon1024::SecretKey::from_bytes(self.key.as_bytes()).expect("validated on construction"))
+    }
+
+    pub fn as_ml_dsa44(&self) -> Result<ml_dsa::SigningKey<MlDsa44>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa44, self.algorithm)?;
+        Ok(ml_dsa_signing_key(&self.key))
+    }
+
+    pub fn as_ml_dsa65(&self) -> Result<ml_dsa::SigningKey<MlDsa65>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa65, self.algorithm)?;
+        Ok(ml_dsa_signing_key(&self.key))
+    }
+
+    pub fn as_ml_dsa87(&self) -> Result<ml_dsa::SigningKey<MlDsa87>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa87, self.algorithm)?;
+        Ok(ml_dsa_signing_key(&self.key))
+    }
+
+    pub fn as_slh_dsa_sha2_128s(&self) -> Result<slh_dsa::SigningKey<Sha2_128s>, StpcError> {
+        expect(SignatureAlgorithm::SlhDsaSha2_128s, self.algorithm)?;
+        slh_dsa_decode(self.algorithm, "private key", self.key.as_bytes())
+    }
+
+    pub fn as_slh_dsa_shake256s(&self) -> Result<slh_dsa::SigningKey<Shake256s>, StpcError> {
+        expect(SignatureAlgorithm::SlhDsaShake256s, self.algorithm)?;
+        slh_dsa_decode(self.algorithm, "private key", self.key.as_bytes())
+    }
+
+    pub fn as_composite(&self) -> Result<CompositePrivateKey, StpcError> {
+        expect(SignatureAlgorithm::Ed25519Falcon512, self.algorithm)?;
+        CompositePrivateKey::decode(&self.key)
+    }
+}
+
+// === PUBLIC KEY ===
+
+impl TypedPublicKey {
+    pub fn new(algorithm: SignatureAlgorithm, key: PublicKey) -> Result<Self, StpcError> {
+        crate::validate_public_key(algorithm, key.as_bytes())?;
+        Ok(Self { algorithm, key })
+    }
+
+    pub fn from_bytes(algorithm: SignatureAlgorithm, bytes: &[u8]) -> Result<Self, StpcError> {
+        Self::new(algorithm, PublicKey::from_bytes(bytes))
+    }
+
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    pub fn key_id(&self) -> Fingerprint {
+        self.key.key_id()
+    }
+
+    /// Verify a signature made with the same algorithm as this key
+    pub fn verify(&self, message: &[u8], signature: &TypedSignature) -> Result<bool, StpcError> {
+        expect(self.algorithm, signature.algorithm)?;
+        crate::verify(self.algorithm, message, &self.key, &signature.signature)
+    }
+
+    pub fn as_ed25519(&self) -> Result<VerifyingKey, StpcError> {
+        expect(SignatureAlgorithm::Ed25519, self.algorithm)?;
+        let bytes: [u8; 32] = self.key.as_bytes().try_into().expect("validated on construction");
+        VerifyingKey::from_bytes(&bytes)
+            .map_err(|_| StpcError::InvalidKeyError("Ed25519 public key is not a curve point".into()))
+    }
+
+    pub fn as_falcon512(&self) -> Result<falcon512::PublicKey, StpcError> {
+        expect(SignatureAlgorithm::Falcon512, self.algorithm)?;
+        Ok(falcon512::PublicKey::from_bytes(self.key.as_bytes()).expect("validated on construction"))
+    }
+
+    pub fn as_falcon1024(&self) -> Result<falcon1024::PublicKey, StpcError> {
+        expect(SignatureAlgorithm::Falcon1024, self.algorithm)?;
+        Ok(falcon1024::PublicKey::from_bytes(self.key.as_bytes()).expect("validated on construction"))
+    }
+
+    pub fn as_ml_dsa44(&self) -> Result<ml_dsa::VerifyingKey<MlDsa44>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa44, self.algorithm)?;
+        Ok(ml_dsa_verifying_key(&self.key))
+    }
+
+    pub fn as_ml_dsa65(&self) -> Result<ml_dsa::VerifyingKey<MlDsa65>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa65, self.algorithm)?;
+        Ok(ml_dsa_verifying_key(&self.key))
+    }
+
+    pub fn as_ml_dsa87(&self) -> Result<ml_dsa::VerifyingKey<MlDsa87>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa87, self.algorithm)?;
+        Ok(ml_dsa_verifying_key(&self.key))
+    }
+
+    pub fn as_slh_dsa_sha2_128s(&self) -> Result<slh_dsa::VerifyingKey<Sha2_128s>, StpcError> {
+        expect(SignatureAlgorithm::SlhDsaSha2_128s, self.algorithm)?;
+        slh_dsa_decode(self.algorithm, "public key", self.key.as_bytes())
+    }
+
+    pub fn as_slh_dsa_shake256s(&self) -> Result<slh_dsa::VerifyingKey<Shake256s>, StpcError> {
+        expect(SignatureAlgorithm::SlhDsaShake256s, self.algorithm)?;
+        slh_dsa_decode(self.algorithm, "public key", self.key.as_bytes())
+    }
+
+    pub fn as_composite(&self) -> Result<CompositePublicKey, StpcError> {
+        expect(SignatureAlgorithm::Ed25519Falcon512, self.algorithm)?;
+        CompositePublicKey::decode(&self.key)
+    }
+}
+
+// === SIGNATURE ===
+
+impl TypedSignature {
+    pub fn new(algorithm: SignatureAlgorithm, signature: Signature) -> Result<Self, StpcError> {
+        crate::validate_signature(algorithm, signature.as_bytes())?;
+        Ok(Self { algorithm, signature })
+    }
+
+    pub fn from_bytes(algorithm: SignatureAlgorithm, bytes: &[u8]) -> Result<Self, StpcError> {
+        Self::new(algorithm, Signature::from_bytes(bytes))
+    }
+
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn as_ed25519(&self) -> Result<ed25519_dalek::Signature, StpcError> {
+        expect(SignatureAlgorithm::Ed25519, self.algorithm)?;
+        let bytes: [u8; 64] = self.signature.as_bytes().try_into().expect("validated on construction");
+        Ok(ed25519_dalek::Signature::from_bytes(&bytes))
+    }
+
+    pub fn as_falcon512(&self) -> Result<falcon512::DetachedSignature, StpcError> {
+        expect(SignatureAlgorithm::Falcon512, self.algorithm)?;
+        Ok(falcon512::DetachedSignature::from_bytes(self.signature.as_bytes()).expect("validated on construction"))
+    }
+
+    pub fn as_falcon1024(&self) -> Result<falcon1024::DetachedSignature, StpcError> {
+        expect(SignatureAlgorithm::Falcon1024, self.algorithm)?;
+        Ok(falcon1024::DetachedSignature::from_bytes(self.signature.as_bytes()).expect("validated on construction"))
+    }
+
+    pub fn as_ml_dsa44(&self) -> Result<ml_dsa::Signature<MlDsa44>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa44, self.algorithm)?;
+        ml_dsa_signature(self.algorithm, &self.signature)
+    }
+
+    pub fn as_ml_dsa65(&self) -> Result<ml_dsa::Signature<MlDsa65>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa65, self.algorithm)?;
+        ml_dsa_signature(self.algorithm, &self.signature)
+    }
+
+    pub fn as_ml_dsa87(&self) -> Result<ml_dsa::Signature<MlDsa87>, StpcError> {
+        expect(SignatureAlgorithm::MlDsa87, self.algorithm)?;
+        ml_dsa_signature(self.algorithm, &self.signature)
+    }
+
+    pub fn as_slh_dsa_sha2_128s(&self) -> Result<slh_dsa::Signature<Sha2_128s>, StpcError> {
+        expect(SignatureAlgorithm::SlhDsaSha2_128s, self.algorithm)?;
+        slh_dsa_decode(self.algorithm, "signature", self.signature.as_bytes())
+    }
+
+    pub fn as_slh_dsa_shake256s(&self) -> Result<slh_dsa::Signature<Shake256s>, StpcError> {
+        expect(SignatureAlgorithm::SlhDsaShake256s, self.algorithm)?;
+        slh_dsa_decode(self.algorithm, "signature", self.signature.as_bytes())
+    }
+
+    pub fn as_composite(&self) -> Result<CompositeSignature, StpcError> {
+        expect(SignatureAlgorithm::Ed25519Falcon512, self.algorithm)?;
+        CompositeSignature::decode(&self.signature)
+    }
+}
+
+// === HELPERS ===
+
+/// The private key is the seed, the signing key is expanded from it
+fn ml_dsa_signing_key<P: MlDsaParams>(key: &PrivateKey) -> ml_dsa::SigningKey<P> {
+    let seed: Zeroizing<[u8; 32]> = Zeroizing::new(key.as_bytes().try_into().expect("validated on construction"));
+    ml_dsa::SigningKey::<P>::from_seed(&(*seed).into())
+}
+
+fn ml_dsa_verifying_key<P: MlDsaParams>(key: &PublicKey) -> ml_dsa::VerifyingKey<P> {
+    ml_dsa::VerifyingKey::<P>::decode(key.as_bytes().try_into().expect("validated on construction"))
+}
+
+/// Only the size is validated, the encoded hints may still be malformed
+fn ml_dsa_signature<P: MlDsaParams>(
+    algorithm: SignatureAlgorithm,
+    signature: &Signature,
+) -> Result<ml_dsa::Signature<P>, StpcError> {
+    ml_dsa::Signature::<P>::try_from(signature.as_bytes())
+        .map_err(|_| StpcError::InvalidKeyError(format!("{} signature is malformed", algorithm.info().name)))
+}
+
+fn slh_dsa_decode<'a, T: TryFrom<&'a [u8]>>(
+    algorithm: SignatureAlgorithm,
+    what: &str,
+    bytes: &'a [u8],
+) -> Result<T, StpcError> {
+    T::try_from(bytes).map_err(|_| StpcError::InvalidKeyError(format!("{} {} is malformed", algorithm.info().name, what)))
+}
+
+fn expect(expected: SignatureAlgorithm, actual: SignatureAlgorithm) -> Result<(), StpcError> {
+    if expected != actual {
+        return Err(StpcError::AlgorithmMismatchError(format!("Expected {:?}, got {:?}", expected, actual)));
+    }
+    Ok(())
+}
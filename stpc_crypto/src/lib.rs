@@ -1,4 +1,14 @@
+pub mod composite;
+pub mod hash;
+pub mod kem;
+pub mod keys;
+
+pub use composite::Ed25519Falcon512;
+pub use hash::PublicKeyExt;
+pub use keys::{TypedPrivateKey, TypedPublicKey, TypedSignature};
+
 use rand::rngs::OsRng;
+use rand::RngCore;
 
 use ed25519_dalek::SigningKey;
 use ed25519_dalek::VerifyingKey;
@@ -13,6 +23,8 @@ use stpc_core::Signature;
 use stpc_core::SigningOperands;
 use stpc_core::PrivateKey;
 use stpc_core::PublicKey;
+use stpc_core::AlgorithmInfo;
+use stpc_core::SignatureAlgorithm;
 use stpc_core::StpcError;
 
 use pqcrypto_falcon::falcon512;
@@ -23,12 +35,178 @@ use pqcrypto_traits::sign::SecretKey;
 use pqcrypto_traits::sign::PublicKey as FalconPublicKey;
 use pqcrypto_traits::sign::DetachedSignature as FalconDetachedSignature;
 
+use ml_dsa::Keypair;
+use ml_dsa::MlDsaParams;
+use ml_dsa::Signer as MlDsaSigner;
+use zeroize::Zeroizing;
+
+use slh_dsa::signature::RandomizedSigner;
+
 
 
 // Sign classes (обязательное наследование от trait SigningOperands):
 pub struct Ed25519 {}
 pub struct Falcon512 {}
 pub struct Falcon1024 {}
+pub struct MlDsa44 {}
+pub struct MlDsa65 {}
+pub struct MlDsa87 {}
+pub struct SlhDsaSha2_128s {}
+pub struct SlhDsaShake256s {}
+
+
+// Runtime dispatch over SignatureAlgorithm (e.g. the tag parsed from the wire):
+
+/// Generate a keypair for `algorithm`
+pub fn keypair(algorithm: SignatureAlgorithm) -> Result<(PrivateKey, PublicKey), StpcError> {
+    match algorithm {
+        SignatureAlgorithm::Ed25519 => Ed25519::keypair(),
+        SignatureAlgorithm::Falcon512 => Falcon512::keypair(),
+        SignatureAlgorithm::Falcon1024 => Falcon1024::keypair(),
+        SignatureAlgorithm::MlDsa44 => MlDsa44::keypair(),
+        SignatureAlgorithm::MlDsa65 => MlDsa65::keypair(),
+        SignatureAlgorithm::MlDsa87 => MlDsa87::keypair(),
+        SignatureAlgorithm::SlhDsaSha2_128s => SlhDsaSha2_128s::keypair(),
+        SignatureAlgorithm::SlhDsaShake256s => SlhDsaShake256s::keypair(),
+        SignatureAlgorithm::Ed25519Falcon512 => Ed25519Falcon512::keypair(),
+    }
+}
+
+/// Sign `message` with the implementation selected by `algorithm`
+pub fn sign(algorithm: SignatureAlgorithm, message: &[u8], private_key: &PrivateKey) -> Result<Signature, StpcError> {
+    validate_private_key(algorithm, private_key.as_bytes())?;
+
+    match algorithm {
+        SignatureAlgorithm::Ed25519 => Ed25519::sign(message, private_key),
+        SignatureAlgorithm::Falcon512 => Falcon512::sign(message, private_key),
+        SignatureAlgorithm::Falcon1024 => Falcon1024::sign(message, private_key),
+        SignatureAlgorithm::MlDsa44 => MlDsa44::sign(message, private_key),
+        SignatureAlgorithm::MlDsa65 => MlDsa65::sign(message, private_key),
+        SignatureAlgorithm::MlDsa87 => MlDsa87::sign(message, private_key),
+        SignatureAlgorithm::SlhDsaSha2_128s => SlhDsaSha2_128s::sign(message, private_key),
+        SignatureAlgorithm::SlhDsaShake256s => SlhDsaShake256s::sign(message, private_key),
+        SignatureAlgorithm::Ed25519Falcon512 => Ed25519Falcon512::sign(message, private_key),
+    }
+}
+
+/// Verify `signature` over `message` with the implementation selected by `algorithm`
+pub fn verify(
+    algorithm: SignatureAlgorithm,
+    message: &[u8],
+    public_key: &PublicKey,
+    signature: &Signature,
+) -> Result<bool, StpcError> {
+    validate_public_key(algorithm, public_key.as_bytes())?;
+
+    match algorithm {
+        SignatureAlgorithm::Ed25519 => Ed25519::verify(message, public_key, signature),
+        SignatureAlgorithm::Falcon512 => Falcon512::verify(message, public_key, signature),
+        SignatureAlgorithm::Falcon1024 => Falcon1024::verify(message, public_key, signature),
+        SignatureAlgorithm::MlDsa44 => MlDsa44::verify(message, public_key, signature),
+        SignatureAlgorithm::MlDsa65 => MlDsa65::verify(message, public_key, signature),
+        SignatureAlgorithm::MlDsa87 => MlDsa87::verify(message, public_key, signature),
+        SignatureAlgorithm::SlhDsaSha2_128s => SlhDsaSha2_128s::verify(message, public_key, signature),
+        SignatureAlgorithm::SlhDsaShake256s => SlhDsaShake256s::verify(message, public_key, signature),
+        SignatureAlgorithm::Ed25519Falcon512 => Ed25519Falcon512::verify(message, public_key, signature),
+    }
+}
+
+
+// Validation of raw encodings against a SignatureAlgorithm: sizes from
+// SignatureAlgorithm::info(), plus the structure checks each format allows.
+
+/// Check that `public_key` is a well-formed public key of `algorithm`
+pub fn validate_public_key(algorithm: SignatureAlgorithm, public_key: &[u8]) -> Result<(), StpcError> {
+    let info = algorithm.info();
+    check_size(&info, "public key", public_key.len(), info.public_key_size, true)?;
+
+    match algorithm {
+        SignatureAlgorithm::Ed25519 => {
+            let bytes: [u8; 32] = public_key.try_into().expect("size checked above");
+            VerifyingKey::from_bytes(&bytes)
+                .map_err(|_| StpcError::InvalidKeyError("Ed25519 public key is not a curve point".into()))?;
+        }
+        SignatureAlgorithm::Falcon512 => check_header(&info, "public key", public_key, FALCON512_LOGN)?,
+        SignatureAlgorithm::Falcon1024 => check_header(&info, "public key", public_key, FALCON1024_LOGN)?,
+        SignatureAlgorithm::Ed25519Falcon512 => {
+            let (classical, post_quantum) = public_key.split_at(32);
+            validate_public_key(SignatureAlgorithm::Ed25519, classical)?;
+            validate_public_key(SignatureAlgorithm::Falcon512, post_quantum)?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Check that `private_key` is a well-formed private key of `algorithm`
+pub fn validate_private_key(algorithm: SignatureAlgorithm, private_key: &[u8]) -> Result<(), StpcError> {
+    let info = algorithm.info();
+    check_size(&info, "private key", private_key.len(), info.private_key_size, true)?;
+
+    match algorithm {
+        SignatureAlgorithm::Falcon512 => check_header(&info, "private key", private_key, 0x50 | FALCON512_LOGN)?,
+        SignatureAlgorithm::Falcon1024 => check_header(&info, "private key", private_key, 0x50 | FALCON1024_LOGN)?,
+        SignatureAlgorithm::Ed25519Falcon512 => {
+            validate_private_key(SignatureAlgorithm::Falcon512, &private_key[32..])?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Check that `signature` is a well-formed signature of `algorithm`.
+/// Falcon signatures are compressed, their size is only bounded
+pub fn validate_signature(algorithm: SignatureAlgorithm, signature: &[u8]) -> Result<(), StpcError> {
+    let info = algorithm.info();
+    let variable = matches!(
+        algorithm,
+        SignatureAlgorithm::Falcon512 | SignatureAlgorithm::Falcon1024 | SignatureAlgorithm::Ed25519Falcon512
+    );
+    check_size(&info, "signature", signature.len(), info.signature_size, !variable)?;
+
+    match algorithm {
+        SignatureAlgorithm::Falcon512 => check_header(&info, "signature", signature, 0x30 | FALCON512_LOGN)?,
+        SignatureAlgorithm::Falcon1024 => check_header(&info, "signature", signature, 0x30 | FALCON1024_LOGN)?,
+        SignatureAlgorithm::Ed25519Falcon512 => {
+            if signature.len() <= 64 {
+                return Err(StpcError::InvalidKeyError(format!("{} signature is truncated", info.name)));
+            }
+            validate_signature(SignatureAlgorithm::Falcon512, &signature[64..])?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Falcon encodings start with a header byte holding log2 of the ring degree
+const FALCON512_LOGN: u8 = 9;
+const FALCON1024_LOGN: u8 = 10;
+
+fn check_size(info: &AlgorithmInfo, what: &str, size: usize, expected: usize, exact: bool) -> Result<(), StpcError> {
+    let valid = if exact { size == expected } else { size > 0 && size <= expected };
+    if valid {
+        return Ok(());
+    }
+
+    Err(StpcError::InvalidKeyError(format!(
+        "{} {} must be {}{} bytes, got {}",
+        info.name,
+        what,
+        if exact { "" } else { "at most " },
+        expected,
+        size
+    )))
+}
+
+fn check_header(info: &AlgorithmInfo, what: &str, bytes: &[u8], header: u8) -> Result<(), StpcError> {
+    match bytes.first() {
+        Some(first) if *first == header => Ok(()),
+        _ => Err(StpcError::InvalidKeyError(format!("{} {} has an invalid header byte", info.name, what))),
+    }
+}
 
 
 impl SigningOperands for Ed25519 {
@@ -49,7 +227,7 @@ impl SigningOperands for Ed25519 {
 
         let key_bytes: [u8; 32] = private_key.as_bytes()
             .try_into()
-            .map_err(|_| StpcError::KeyGenerationError("Private key must be 32 bytes".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Ed25519 private key must be 32 bytes".into()))?;
 
         let signing_key = SigningKey::from_bytes(&key_bytes);
 
@@ -65,10 +243,10 @@ impl SigningOperands for Ed25519 {
         // Преобразуем public key в [u8; 32]
         let key_bytes: [u8; 32] = public_key.as_bytes()
             .try_into()
-            .map_err(|_| StpcError::KeyGenerationError("Public key must be 32 bytes".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Ed25519 public key must be 32 bytes".into()))?;
 
         let verifying_key = VerifyingKey::from_bytes(&key_bytes)
-            .map_err(|_| StpcError::KeyGenerationError("Invalid public key bytes".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Ed25519 public key is not a curve point".into()))?;
 
         // Преобразуем signature в [u8; 64] и потом в EdSignature
         let sig_bytes: [u8; 64] = signature.as_bytes()
@@ -101,7 +279,7 @@ impl SigningOperands for Falcon512 {
     fn sign(message: &[u8], private_key: &PrivateKey) -> Result<Signature, StpcError> {
 
         let private_key = SecretKey::from_bytes(private_key.as_bytes())
-            .map_err(|_| StpcError::KeyGenerationError("Invalid Falcon512 private key".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Invalid Falcon512 private key".into()))?;
 
         let sig = falcon512::detached_sign(message, &private_key);
 
@@ -112,7 +290,7 @@ impl SigningOperands for Falcon512 {
     fn verify(message: &[u8], public_key: &PublicKey, signature: &Signature) -> Result<bool, StpcError> {
         
         let public_key = FalconPublicKey::from_bytes(public_key.as_bytes())
-            .map_err(|_| StpcError::KeyGenerationError("Invalid Falcon512 public key".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Invalid Falcon512 public key".into()))?;
 
         let signature = DetachedSignature512::from_bytes(signature.as_bytes())
             .map_err(|_| StpcError::SignatureVerifyError)?;
@@ -140,7 +318,7 @@ impl SigningOperands for Falcon1024 {
     fn sign(message: &[u8], private_key: &PrivateKey) -> Result<Signature, StpcError> {
 
         let private_key = SecretKey::from_bytes(private_key.as_bytes())
-            .map_err(|_| StpcError::KeyGenerationError("Invalid Falcon1024 private key".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Invalid Falcon1024 private key".into()))?;
 
         let sig = falcon1024::detached_sign(message, &private_key);
 
@@ -151,7 +329,7 @@ impl SigningOperands for Falcon1024 {
     fn verify(message: &[u8], public_key: &PublicKey, signature: &Signature) -> Result<bool, StpcError> {
         
         let public_key = FalconPublicKey::from_bytes(public_key.as_bytes())
-            .map_err(|_| StpcError::KeyGenerationError("Invalid Falcon1024 public key".into()))?;
+            .map_err(|_| StpcError::InvalidKeyError("Invalid Falcon1024 public key".into()))?;
 
         let signature = DetachedSignature1024::from_bytes(signature.as_bytes())
             .map_err(|_| StpcError::SignatureVerifyError)?;
@@ -164,3 +342,140 @@ impl SigningOperands for Falcon1024 {
     }
 }
 
+
+
+// ML-DSA (FIPS 204). The private key is the 32-byte seed ξ, the signing key is
+// re-expanded from it on every call; signatures use the deterministic variant
+// with an empty context string.
+
+macro_rules! ml_dsa_operands {
+    ($name:ident, $params:ty, $label:literal) => {
+        impl $name {
+            /// Deterministic ML-DSA.KeyGen_internal from a 32-byte seed
+            pub fn keypair_from_seed(seed: &[u8; 32]) -> (PrivateKey, PublicKey) {
+                let signing_key = ml_dsa::SigningKey::<$params>::from_seed(&(*seed).into());
+                let public = signing_key.verifying_key().encode();
+
+                (PrivateKey::from_bytes(seed), PublicKey::from_bytes(&public))
+            }
+        }
+
+        impl SigningOperands for $name {
+            fn keypair() -> Result<(PrivateKey, PublicKey), StpcError> {
+                let mut seed = Zeroizing::new([0u8; 32]);
+                OsRng.fill_bytes(seed.as_mut());
+
+                Ok(Self::keypair_from_seed(&seed))
+            }
+
+            fn sign(message: &[u8], private_key: &PrivateKey) -> Result<Signature, StpcError> {
+                let seed: [u8; 32] = private_key.as_bytes()
+                    .try_into()
+                    .map_err(|_| StpcError::InvalidKeyError(concat!("Invalid ", $label, " private key").into()))?;
+                let seed = Zeroizing::new(seed);
+
+                let signing_key = ml_dsa::SigningKey::<$params>::from_seed(&(*seed).into());
+                let sig = signing_key.try_sign(message)
+                    .map_err(|e| StpcError::SignatureComputingError(e.to_string()))?;
+
+                Ok(Signature::from_bytes(&sig.encode()))
+            }
+
+            fn verify(message: &[u8], public_key: &PublicKey, signature: &Signature) -> Result<bool, StpcError> {
+                ml_dsa_verify::<$params>(message, public_key, signature, $label)
+            }
+        }
+    };
+}
+
+fn ml_dsa_verify<P: MlDsaParams>(
+    message: &[u8],
+    public_key: &PublicKey,
+    signature: &Signature,
+    label: &str,
+) -> Result<bool, StpcError> {
+
+    let encoded = public_key.as_bytes()
+        .try_into()
+        .map_err(|_| StpcError::InvalidKeyError(format!("Invalid {} public key", label)))?;
+    let verifying_key = ml_dsa::VerifyingKey::<P>::decode(encoded);
+
+    let signature = ml_dsa::Signature::<P>::try_from(signature.as_bytes())
+        .map_err(|_| StpcError::SignatureVerifyError)?;
+
+    match verifying_key.verify_with_context(message, &[], &signature) {
+        true => Ok(true),
+        false => Err(StpcError::SignatureVerifyError),
+    }
+}
+
+ml_dsa_operands!(MlDsa44, ml_dsa::MlDsa44, "ML-DSA-44");
+ml_dsa_operands!(MlDsa65, ml_dsa::MlDsa65, "ML-DSA-65");
+ml_dsa_operands!(MlDsa87, ml_dsa::MlDsa87, "ML-DSA-87");
+
+
+
+// SLH-DSA (FIPS 205). Keys are the raw FIPS 205 encodings: the private key is
+// SK.seed || SK.prf || PK.seed || PK.root, the public key PK.seed || PK.root.
+// Signatures are hedged with fresh randomness and an empty context string.
+
+macro_rules! slh_dsa_operands {
+    ($name:ident, $params:ty, $n:literal, $label:literal) => {
+        impl $name {
+            /// Deterministic slh_keygen_internal; each seed must be n bytes long
+            pub fn keypair_from_seed(
+                sk_seed: &[u8],
+                sk_prf: &[u8],
+                pk_seed: &[u8],
+            ) -> Result<(PrivateKey, PublicKey), StpcError> {
+                if sk_seed.len() != $n || sk_prf.len() != $n || pk_seed.len() != $n {
+                    return Err(StpcError::KeyGenerationError(concat!($label, " seeds must be ", $n, " bytes").into()));
+                }
+
+                let signing_key = slh_dsa::SigningKey::<$params>::slh_keygen_internal(sk_seed, sk_prf, pk_seed);
+                Ok(slh_dsa_keys(&signing_key))
+            }
+        }
+
+        impl SigningOperands for $name {
+            fn keypair() -> Result<(PrivateKey, PublicKey), StpcError> {
+                let signing_key = slh_dsa::SigningKey::<$params>::new(&mut OsRng);
+
+                Ok(slh_dsa_keys(&signing_key))
+            }
+
+            fn sign(message: &[u8], private_key: &PrivateKey) -> Result<Signature, StpcError> {
+                let signing_key = slh_dsa::SigningKey::<$params>::try_from(private_key.as_bytes())
+                    .map_err(|_| StpcError::InvalidKeyError(concat!("Invalid ", $label, " private key").into()))?;
+
+                let sig = signing_key.try_sign_with_rng(&mut OsRng, message)
+                    .map_err(|e| StpcError::SignatureComputingError(e.to_string()))?;
+
+                Ok(Signature::from_bytes(&sig.to_bytes()))
+            }
+
+            fn verify(message: &[u8], public_key: &PublicKey, signature: &Signature) -> Result<bool, StpcError> {
+                let verifying_key = slh_dsa::VerifyingKey::<$params>::try_from(public_key.as_bytes())
+                    .map_err(|_| StpcError::InvalidKeyError(concat!("Invalid ", $label, " public key").into()))?;
+
+                let signature = slh_dsa::Signature::<$params>::try_from(signature.as_bytes())
+                    .map_err(|_| StpcError::SignatureVerifyError)?;
+
+                match verifying_key.try_verify_with_context(message, &[], &signature) {
+                    Ok(()) => Ok(true),
+                    Err(_) => Err(StpcError::SignatureVerifyError),
+                }
+            }
+        }
+    };
+}
+
+fn slh_dsa_keys<P: slh_dsa::ParameterSet>(signing_key: &slh_dsa::SigningKey<P>) -> (PrivateKey, PublicKey) {
+    let private = Zeroizing::new(signing_key.to_vec());
+    let public: &slh_dsa::VerifyingKey<P> = signing_key.as_ref();
+
+    (PrivateKey::from_bytes(&private), PublicKey::from_bytes(&public.to_bytes()))
+}
+
+slh_dsa_operands!(SlhDsaSha2_128s, slh_dsa::Sha2_128s, 16, "SLH-DSA-SHA2-128s");
+slh_dsa_operands!(SlhDsaShake256s, slh_dsa::Shake256s, 32, "SLH-DSA-SHAKE-256s");
@@ -0,0 +1,170 @@
+// Packet framings.
+
+use stpc_core::StpcError;
+
+/// First byte of every v2 packet
+pub const V2_MARKER: u8 = 2;
+
+/// Longest varint, enough for a u64
+pub const MAX_VARINT_SIZE: usize = 10;
+
+const V1_HEADER_SIZE:       usize = 8;
+const V1_BLOCK_HEADER_SIZE: usize = 5;
+
+// Framing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Framing {
+    /// Payload length (8 bytes, big endian) || blocks of
+    /// tag (1 byte) || value length (4 bytes, big endian) || value.
+    /// The first byte is the top byte of the length, 0 for any payload under 2^56 bytes
+    #[default]
+    V1,
+    /// 2 || payload length (varint) || blocks of
+    /// tag (varint) || value length (varint) || value.
+    /// Tags go up to u32::MAX
+    V2,
+}
+
+impl Framing {
+    /// Framing of a packet, from its first byte
+    pub fn detect(message: &[u8]) -> Result<Self, StpcError> {
+        match message.first() {
+            Some(0) => Ok(Framing::V1),
+            Some(&V2_MARKER) => Ok(Framing::V2),
+            Some(byte) => Err(StpcError::InvalidPacketError(format!("Unknown packet framing {:#04x}", byte))),
+            None => Err(StpcError::InvalidPacketError("Empty packet".into())),
+        }
+    }
+
+    /// Bytes taken by the header of a packet with `payload_len` payload bytes
+    pub fn header_size(self, payload_len: u64) -> usize {
+        match self {
+            Framing::V1 => V1_HEADER_SIZE,
+            Framing::V2 => 1 + varint_size(payload_len),
+        }
+    }
+
+    /// Payload bytes taken by a block, header included
+    pub fn block_size(self, tag: u32, value_len: usize) -> u64 {
+        let header = match self {
+            Framing::V1 => V1_BLOCK_HEADER_SIZE,
+            Framing::V2 => varint_size(tag as u64) + varint_size(value_len as u64),
+        };
+        (header + value_len) as u64
+    }
+
+    /// Shortest possible block header
+    pub(crate) fn min_block_header_size(self) -> usize {
+        match self {
+            Framing::V1 => V1_BLOCK_HEADER_SIZE,
+            Framing::V2 => 2,
+        }
+    }
+
+    pub(crate) fn write_header(self, payload_len: u64, out: &mut Vec<u8>) {
+        match self {
+            Framing::V1 => out.extend_from_slice(&payload_len.to_be_bytes()),
+            Framing::V2 => {
+                out.push(V2_MARKER);
+                write_varint(payload_len, out);
+            }
+        }
+    }
+
+    pub(crate) fn write_block_header(self, tag: u32, value_len: usize, out: &mut Vec<u8>) -> Result<(), StpcError> {
+        match self {
+            Framing::V1 => {
+                let tag = u8::try_from(tag)
+                    .map_err(|_| StpcError::SerilizateError(format!("Tag {} needs v2 framing", tag)))?;
+                let length = u32::try_from(value_len)
+                    .map_err(|_| StpcError::SerilizateError(format!("Block value of {} bytes is too long", value_len)))?;
+                out.push(tag);
+                out.extend_from_slice(&length.to_be_bytes());
+            }
+            Framing::V2 => {
+                write_varint(tag as u64, out);
+                write_varint(value_len as u64, out);
+            }
+        }
+        Ok(())
+    }
+
+    /// Payload length and header size, `None` while `bytes` is too short to tell
+    pub(crate) fn read_header(self, bytes: &[u8]) -> Result<Option<(u64, usize)>, StpcError> {
+        match self {
+            Framing::V1 => Ok(bytes
+                .get(..V1_HEADER_SIZE)
+                .map(|header| (u64::from_be_bytes(header.try_into().expect("8 bytes")), V1_HEADER_SIZE))),
+            Framing::V2 => {
+                Ok(read_varint(bytes.get(1..).unwrap_or_default())?.map(|(payload_len, size)| (payload_len, 1 + size)))
+            }
+        }
+    }
+
+    /// Tag, value length and header size, `None` while `bytes` is too short to tell
+    pub(crate) fn read_block_header(self, bytes: &[u8]) -> Result<Option<(u32, usize, usize)>, StpcError> {
+        match self {
+            Framing::V1 => Ok(bytes.get(..V1_BLOCK_HEADER_SIZE).map(|header| {
+                let length = u32::from_be_bytes(header[1..].try_into().expect("4 bytes"));
+                (header[0] as u32, length as usize, V1_BLOCK_HEADER_SIZE)
+            })),
+            Framing::V2 => {
+                let Some((tag, tag_size)) = read_varint(bytes)? else {
+                    return Ok(None);
+                };
+                let tag = u32::try_from(tag)
+                    .map_err(|_| StpcError::InvalidPacketError(format!("Tag {} is beyond u32", tag)))?;
+                let Some((length, length_size)) = read_varint(&bytes[tag_size..])? else {
+                    return Ok(None);
+                };
+                let length = usize::try_from(length)
+                    .map_err(|_| StpcError::InvalidPacketError(format!("Block length {} is too long", length)))?;
+
+                Ok(Some((tag, length, tag_size + length_size)))
+            }
+        }
+    }
+}
+
+// === VARINTS ===
+
+/// Bytes `value` takes as a varint
+pub fn varint_size(value: u64) -> usize {
+    (64 - value.max(1).leading_zeros() as usize).div_ceil(7)
+}
+
+/// Unsigned LEB128: 7 bits per byte, low bits first, the high bit set on every
+/// byte but the last. Only this shortest form is read back
+pub fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Value and size of the varint `bytes` starts with, `None` while it is cut short.
+/// Overlong forms and values beyond u64 are errors
+pub fn read_varint(bytes: &[u8]) -> Result<Option<(u64, usize)>, StpcError> {
+    let mut value: u64 = 0;
+
+    for (index, byte) in bytes.iter().take(MAX_VARINT_SIZE).enumerate() {
+        let bits = (byte & 0x7f) as u64;
+        if index == MAX_VARINT_SIZE - 1 && bits > 1 {
+            return Err(StpcError::InvalidPacketError("Varint overflows u64".into()));
+        }
+        value |= bits << (7 * index);
+
+        if byte & 0x80 == 0 {
+            if index > 0 && bits == 0 {
+                return Err(StpcError::InvalidPacketError("Varint is not in its shortest form".into()));
+            }
+            return Ok(Some((value, index + 1)));
+        }
+    }
+
+    if bytes.len() >= MAX_VARINT_SIZE {
+        return Err(StpcError::InvalidPacketError("Varint longer than 10 bytes".into()));
+    }
+    Ok(None)
+}
@@ -1,6 +1,18 @@
-use std::convert::TryInto;
+// Signatures cover serialized bytes, so every value must have exactly one encoding.
+
+use std::collections::HashSet;
+
 use stpc_core::StpcError;
 
+pub mod framing;
+pub mod schema;
+pub mod stream;
+
+pub use framing::Framing;
+pub use schema::{TlvDecode, TlvEncode};
+pub use stream::{TlvDecoder, TlvIter, TlvReader, TlvWriter};
+pub use stpc_encoding_derive::{TlvDecode, TlvEncode};
+
 
 
 pub trait TLV {
@@ -12,56 +24,187 @@ pub trait TLV {
 #[derive(Debug)]
 pub struct TLVParser {}
 
+/// Blocks of one packet, looked up by tag in any order
+#[derive(Debug)]
+pub struct TlvFields {
+    /// Value is taken out once a lookup has consumed the block
+    blocks:  Vec<(u32, Option<Vec<u8>>)>,
+    strict:  bool,
+    framing: Framing,
+}
+
 
 impl TLV for TLVParser {
+    /// v1 packet
     fn pack(blocks: &[(u8, &[u8])]) -> Result<Vec<u8>, StpcError> {
-        let mut packet = Vec::new();
+        Self::pack_as(Framing::V1, blocks)
+    }
+
+    /// Packet in either framing, failing on tags beyond u8
+    fn unpack(message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, StpcError> {
+        TlvIter::new(message)?
+            .map(|block| {
+                let (tag, value) = block?;
+                let tag = u8::try_from(tag)
+                    .map_err(|_| StpcError::InvalidPacketError(format!("Tag {} does not fit in one byte", tag)))?;
+                Ok((tag, value.to_vec()))
+            })
+            .collect()
+    }
 
-        for (tag, data) in blocks {
-            packet.push(*tag);
-            let len = data.len() as u32;
-            packet.extend(&len.to_be_bytes());
-            packet.extend(*data);
+}
+
+
+impl TLVParser {
+    pub fn pack_as(framing: Framing, blocks: &[(u8, &[u8])]) -> Result<Vec<u8>, StpcError> {
+        let blocks: Vec<(u32, &[u8])> = blocks.iter().map(|(tag, value)| (*tag as u32, *value)).collect();
+        Self::pack_wide(framing, &blocks)
+    }
+
+    /// `pack_as` for blocks that own their values
+    pub fn pack_owned(framing: Framing, blocks: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, StpcError> {
+        let blocks: Vec<(u8, &[u8])> = blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        Self::pack_as(framing, &blocks)
+    }
+
+    /// Packet with tags beyond u8, which only v2 framing can carry
+    pub fn pack_wide(framing: Framing, blocks: &[(u32, &[u8])]) -> Result<Vec<u8>, StpcError> {
+        let mut payload = Vec::new();
+        for (tag, value) in blocks {
+            framing.write_block_header(*tag, value.len(), &mut payload)?;
+            payload.extend_from_slice(value);
         }
 
-        let total_len = packet.len() as u64;
-        let mut result = total_len.to_be_bytes().to_vec();
-        result.extend(packet);
+        let mut packet = Vec::with_capacity(framing.header_size(payload.len() as u64) + payload.len());
+        framing.write_header(payload.len() as u64, &mut packet);
+        packet.extend(payload);
+        Ok(packet)
+    }
 
-        Ok(result)
+    /// Blocks with their full tags, for packets using tags beyond u8
+    pub fn unpack_wide(message: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, StpcError> {
+        TlvIter::new(message)?
+            .map(|block| block.map(|(tag, value)| (tag, value.to_vec())))
+            .collect()
     }
 
-    fn unpack(message: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, StpcError> {
-        if message.len() < 8 {
-            return Err(StpcError::InvalidPacketError(format!("Message must be greater than 8 bytes, received: {}", message.len())));
+    /// Blocks of `message` for lookup by tag. Blocks nobody asks for, whatever
+    /// their tag, and bytes after the packet are ignored
+    pub fn fields(message: &[u8]) -> Result<TlvFields, StpcError> {
+        Ok(TlvFields::new(Self::unpack_wide(message)?, false, Framing::detect(message)?))
+    }
+
+    /// Like `fields`, but bytes after the packet are an error, and `finish`
+    /// rejects blocks that were not looked up
+    pub fn fields_strict(message: &[u8]) -> Result<TlvFields, StpcError> {
+        let blocks = TlvIter::new(message)?;
+        if message.len() > blocks.packet_len() {
+            return Err(StpcError::TrailingBytesError(message.len() - blocks.packet_len()));
         }
 
-        let total_len = u64::from_be_bytes(message[0..8].try_into().unwrap()) as usize;
-        if message.len() - 8 < total_len {
-            return Err(StpcError::InvalidPacketError(format!("Message must be greater than 8 bytes, received: {}", message.len())));
+        Ok(TlvFields::new(Self::unpack_wide(message)?, true, blocks.framing()))
+    }
+
+    /// Blocks of `message` borrowed in place, without copying the values
+    pub fn iter(message: &[u8]) -> Result<TlvIter<'_>, StpcError> {
+        TlvIter::new(message)
+    }
+
+    /// Whether `message` is a well-formed packet following the canonical rules:
+    ///   1. the header declares exactly the bytes that follow, nothing trails
+    ///   2. block tags are strictly ascending, except in a list, where every
+    ///      block carries schema::LIST_TAG
+    ///   3. every length is exact, and v2 varints take their shortest form
+    ///
+    /// The rules depending on the schema are checked by schema::require_canonical
+    pub fn is_canonical(message: &[u8]) -> bool {
+        Self::check_canonical(message).is_ok()
+    }
+
+    /// Like `is_canonical`, with the first rule broken as the error
+    pub fn check_canonical(message: &[u8]) -> Result<(), StpcError> {
+        let blocks = TlvIter::new(message)?;
+        if message.len() > blocks.packet_len() {
+            return Err(StpcError::TrailingBytesError(message.len() - blocks.packet_len()));
         }
 
-        let payload = &message[8..8 + total_len];
-        let mut offset = 0;
-        let mut blocks = Vec::new();
+        let mut seen = HashSet::new();
+        let mut previous: Option<u32> = None;
+        for block in blocks {
+            let (tag, _) = block?;
+            // A list from its first block on
+            if previous.unwrap_or(tag) == schema::LIST_TAG {
+                if tag != schema::LIST_TAG {
+                    return Err(StpcError::NonCanonicalError(format!("Tag {} inside a list", tag)));
+                }
+                previous = Some(tag);
+                continue;
+            }
 
-        while offset < payload.len() {
-            if offset + 5 > payload.len() {
-                return Err(StpcError::InvalidPacketError(format!("Block must be greater than 5 bytes, received: {}", message.len())));
+            if !seen.insert(tag) {
+                return Err(StpcError::DuplicateTagError(tag));
             }
+            if let Some(previous) = previous.filter(|previous| *previous > tag) {
+                return Err(StpcError::NonCanonicalError(format!("Tag {} follows tag {}", tag, previous)));
+            }
+            previous = Some(tag);
+        }
+        Ok(())
+    }
+}
 
-            let tag = payload[offset];
-            let length = u32::from_be_bytes(payload[offset + 1..offset + 5].try_into().unwrap()) as usize;
-            offset += 5;
+impl TlvFields {
+    fn new(blocks: Vec<(u32, Vec<u8>)>, strict: bool, framing: Framing) -> Self {
+        Self {
+            blocks: blocks.into_iter().map(|(tag, value)| (tag, Some(value))).collect(),
+            strict,
+            framing,
+        }
+    }
 
-            if offset + length > payload.len() {
-                return Err(StpcError::InvalidPacketError(format!("Block at offset {} claims length {}, but only {} bytes remain", offset, length, payload.len() - offset)));         }
+    /// Framing of the packet the blocks came from
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Value of the only block with `tag`
+    pub fn required(&mut self, tag: u32, field: &str) -> Result<Vec<u8>, StpcError> {
+        self.optional(tag)?.ok_or_else(|| StpcError::MissingTagError { tag, field: field.into() })
+    }
 
-            blocks.push((tag, payload[offset..offset + length].to_vec()));
-            offset += length;
+    /// Value of the only block with `tag`, if there is one
+    pub fn optional(&mut self, tag: u32) -> Result<Option<Vec<u8>>, StpcError> {
+        let mut matching = self.blocks.iter_mut().filter(|(block_tag, _)| *block_tag == tag);
+
+        let value = match matching.next() {
+            Some((_, value)) => value,
+            None => return Ok(None),
+        };
+        if matching.next().is_some() {
+            return Err(StpcError::DuplicateTagError(tag));
         }
 
-        Ok(blocks)
+        value.take().map(Some).ok_or(StpcError::DuplicateTagError(tag))
+    }
+
+    /// Values of every block with `tag`, in packet order
+    pub fn repeated(&mut self, tag: u32) -> Vec<Vec<u8>> {
+        self.blocks
+            .iter_mut()
+            .filter(|(block_tag, _)| *block_tag == tag)
+            .filter_map(|(_, value)| value.take())
+            .collect()
     }
 
-}
\ No newline at end of file
+    /// In strict mode, fails on the first block no lookup has taken
+    pub fn finish(self) -> Result<(), StpcError> {
+        if !self.strict {
+            return Ok(());
+        }
+
+        match self.blocks.iter().find(|(_, value)| value.is_some()) {
+            Some((tag, _)) => Err(StpcError::UnexpectedTagError(*tag)),
+            None => Ok(()),
+        }
+    }
+}
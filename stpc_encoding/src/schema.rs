@@ -0,0 +1,213 @@
+// Schema-driven TLV: the traits behind #[derive(TlvEncode, TlvDecode)].
+
+use std::convert::TryInto;
+
+use stpc_core::StpcError;
+
+use crate::{Framing, TLVParser};
+
+/// Value that can be stored in a TLV block
+pub trait TlvEncode {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError>;
+
+    /// Encoding in `framing`, for values that are packets themselves. Structs and
+    /// lists hand it down to their fields, plain values ignore it
+    fn tlv_encode_as(&self, framing: Framing) -> Result<Vec<u8>, StpcError> {
+        let _ = framing;
+        self.tlv_encode()
+    }
+}
+
+/// Value that can be read back from a TLV block
+pub trait TlvDecode: Sized {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError>;
+}
+
+// === PRIMITIVES ===
+
+macro_rules! integer {
+    ($($type:ty),*) => {$(
+        /// Fixed width, big endian
+        impl TlvEncode for $type {
+            fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+                Ok(self.to_be_bytes().to_vec())
+            }
+        }
+
+        impl TlvDecode for $type {
+            fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+                let bytes = value.try_into().map_err(|_| {
+                    StpcError::DeserilizateError(format!(
+                        "{} must be {} bytes, got {}",
+                        stringify!($type),
+                        size_of::<$type>(),
+                        value.len()
+                    ))
+                })?;
+                Ok(<$type>::from_be_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+integer!(u8, u16, u32, u64);
+
+impl TlvEncode for bool {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(vec![*self as u8])
+    }
+}
+
+impl TlvDecode for bool {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        match value {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(StpcError::DeserilizateError("Invalid bool".into())),
+        }
+    }
+}
+
+impl TlvEncode for String {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(self.as_bytes().to_vec())
+    }
+}
+
+impl TlvDecode for String {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        String::from_utf8(value.to_vec()).map_err(|_| StpcError::DeserilizateError("Invalid UTF-8".into()))
+    }
+}
+
+/// Raw bytes, any length
+impl TlvEncode for Vec<u8> {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(self.clone())
+    }
+}
+
+impl TlvDecode for Vec<u8> {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        Ok(value.to_vec())
+    }
+}
+
+/// Raw bytes, exactly N
+impl<const N: usize> TlvEncode for [u8; N] {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(self.to_vec())
+    }
+}
+
+impl<const N: usize> TlvDecode for [u8; N] {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        value
+            .try_into()
+            .map_err(|_| StpcError::DeserilizateError(format!("Expected {} bytes, got {}", N, value.len())))
+    }
+}
+
+// === REPEATED FIELDS ===
+
+/// Tag of every list element. Structs never use it, so a packet starting with
+/// it is a list and may repeat it
+pub const LIST_TAG: u32 = 0;
+
+/// List packet with each element of `items` under `LIST_TAG`
+pub fn encode_repeated<T>(
+    framing: Framing,
+    items: &[T],
+    encode: impl Fn(&T) -> Result<Vec<u8>, StpcError>,
+) -> Result<Vec<u8>, StpcError> {
+    let mut blocks = Blocks::with_framing(framing);
+    for item in items {
+        blocks.push(LIST_TAG, encode(item)?);
+    }
+    blocks.finish()
+}
+
+/// Elements of a packet written by `encode_repeated`
+pub fn decode_repeated<T>(
+    value: &[u8],
+    decode: impl Fn(&[u8]) -> Result<T, StpcError>,
+) -> Result<Vec<T>, StpcError> {
+    TLVParser::iter(value)?
+        .map(|block| match block? {
+            (LIST_TAG, item) => decode(item),
+            (tag, _) => Err(StpcError::DeserilizateError(format!("Tag {} inside a list", tag))),
+        })
+        .collect()
+}
+
+// === CANONICAL ENCODING ===
+
+/// Check that `data`, decoded and encoded again as `reencoded`, was canonical: on
+/// top of TLVParser::is_canonical, nested values are canonical for their own type,
+/// optional fields are left out rather than empty, and nested packets use the
+/// framing around them unless they are signed structures of their own
+pub fn require_canonical(data: &[u8], reencoded: &[u8]) -> Result<(), StpcError> {
+    TLVParser::check_canonical(data)?;
+
+    if data != reencoded {
+        let offset = data.iter().zip(reencoded).take_while(|(a, b)| a == b).count();
+        return Err(StpcError::NonCanonicalError(format!(
+            "Re-encoding differs at byte {} ({} bytes received, {} expected)",
+            offset,
+            data.len(),
+            reencoded.len()
+        )));
+    }
+    Ok(())
+}
+
+/// For `#[tlv(with = "stpc_encoding::schema::canonical")]`: the field is
+/// decoded only from its canonical encoding and encoded in its own framing
+/// rather than its parent's, as signed parts need
+pub mod canonical {
+    use super::*;
+
+    pub fn encode<T: TlvEncode>(value: &T) -> Result<Vec<u8>, StpcError> {
+        value.tlv_encode()
+    }
+
+    pub fn decode<T: TlvEncode + TlvDecode>(data: &[u8]) -> Result<T, StpcError> {
+        let value = T::tlv_decode(data)?;
+        require_canonical(data, &value.tlv_encode_as(Framing::detect(data)?)?)?;
+        Ok(value)
+    }
+}
+
+// === STRUCTS ===
+
+/// Blocks of a struct being encoded
+#[derive(Debug, Default)]
+pub struct Blocks {
+    framing: Framing,
+    blocks:  Vec<(u32, Vec<u8>)>,
+}
+
+impl Blocks {
+    /// v1 packet
+    pub fn new() -> Self {
+        Self::with_framing(Framing::V1)
+    }
+
+    pub fn with_framing(framing: Framing) -> Self {
+        Self { framing, blocks: Vec::new() }
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    pub fn push(&mut self, tag: u32, value: Vec<u8>) {
+        self.blocks.push((tag, value));
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, StpcError> {
+        let blocks: Vec<(u32, &[u8])> = self.blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+
+        TLVParser::pack_wide(self.framing, &blocks)
+    }
+}
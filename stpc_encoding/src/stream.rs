@@ -0,0 +1,392 @@
+// Streaming access to TLVParser packets, in either framing (see framing.rs).
+
+use std::io::{self, Read, Write};
+
+use stpc_core::StpcError;
+
+use crate::framing::{read_varint, Framing, MAX_VARINT_SIZE};
+
+/// v1 packet header: payload length
+pub const HEADER_SIZE: usize = 8;
+
+/// v1 block header: tag and value length
+pub const BLOCK_HEADER_SIZE: usize = 5;
+
+/// Largest block value TlvDecoder and TlvReader accept unless told otherwise
+pub const DEFAULT_MAX_BLOCK_SIZE: usize = 16 * 1024 * 1024;
+
+// === ITERATOR ===
+
+/// Blocks of a packet in memory, borrowed from it
+#[derive(Debug, Clone)]
+pub struct TlvIter<'a> {
+    framing:     Framing,
+    header_size: usize,
+    payload:     &'a [u8],
+    offset:      usize,
+    failed:      bool,
+}
+
+impl<'a> TlvIter<'a> {
+    /// Check the packet header; bytes after the declared payload are not looked at
+    pub fn new(message: &'a [u8]) -> Result<Self, StpcError> {
+        let framing = Framing::detect(message)?;
+        let (payload_len, header_size) = framing.read_header(message)?.ok_or_else(|| {
+            StpcError::InvalidPacketError(format!("Packet header is cut short, received: {} bytes", message.len()))
+        })?;
+
+        let available = (message.len() - header_size) as u64;
+        if payload_len > available {
+            return Err(StpcError::InvalidPacketError(format!(
+                "Packet declares {} payload bytes, only {} follow",
+                payload_len, available
+            )));
+        }
+
+        Ok(Self {
+            framing,
+            header_size,
+            payload: &message[header_size..header_size + payload_len as usize],
+            offset: 0,
+            failed: false,
+        })
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Bytes of the packet, header included
+    pub fn packet_len(&self) -> usize {
+        self.header_size + self.payload.len()
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Result<(u32, &'a [u8]), StpcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset == self.payload.len() {
+            return None;
+        }
+
+        let rest = &self.payload[self.offset..];
+        let result = match self.framing.read_block_header(rest) {
+            Ok(Some((tag, length, header_size))) if rest.len() - header_size >= length => {
+                self.offset += header_size + length;
+                Ok((tag, &rest[header_size..header_size + length]))
+            }
+            Ok(Some((_, length, header_size))) => Err(StpcError::InvalidPacketError(format!(
+                "Block at offset {} claims length {}, but only {} bytes remain",
+                self.offset + header_size,
+                length,
+                rest.len() - header_size
+            ))),
+            Ok(None) => Err(StpcError::InvalidPacketError(format!(
+                "Block header at offset {} is cut short, {} bytes remain",
+                self.offset,
+                rest.len()
+            ))),
+            Err(e) => Err(e),
+        };
+
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+// === INCREMENTAL DECODER ===
+
+/// Decoder fed with a packet in chunks of any size
+#[derive(Debug)]
+pub struct TlvDecoder {
+    buffer:         Vec<u8>,
+    /// Framing and payload bytes not yet returned as blocks, None until the header is in
+    remaining:      Option<(Framing, u64)>,
+    max_block_size: usize,
+}
+
+impl Default for TlvDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TlvDecoder {
+    pub fn new() -> Self {
+        Self::with_max_block_size(DEFAULT_MAX_BLOCK_SIZE)
+    }
+
+    /// Blocks declaring a longer value are rejected before anything is buffered for them
+    pub fn with_max_block_size(max_block_size: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            remaining: None,
+            max_block_size,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Next complete block, `None` until enough bytes have been fed
+    pub fn next_block(&mut self) -> Result<Option<(u32, Vec<u8>)>, StpcError> {
+        let (framing, remaining) = match self.remaining {
+            Some(remaining) => remaining,
+            None if self.buffer.is_empty() => return Ok(None),
+            None => {
+                let framing = Framing::detect(&self.buffer)?;
+                let Some((payload_len, header_size)) = framing.read_header(&self.buffer)? else {
+                    return Ok(None);
+                };
+                self.buffer.drain(..header_size);
+                self.remaining = Some((framing, payload_len));
+                (framing, payload_len)
+            }
+        };
+        if remaining == 0 {
+            return Ok(None);
+        }
+        check_header_fits(framing, remaining)?;
+
+        let (tag, length, header_size) = match framing.read_block_header(&self.buffer)? {
+            Some(header) => header,
+            None if self.buffer.len() as u64 >= remaining => {
+                return Err(StpcError::InvalidPacketError("Block header runs past the payload".into()));
+            }
+            None => return Ok(None),
+        };
+        check_block(remaining, header_size, length, self.max_block_size)?;
+        if self.buffer.len() < header_size + length {
+            return Ok(None);
+        }
+
+        let value = self.buffer[header_size..header_size + length].to_vec();
+        self.buffer.drain(..header_size + length);
+        self.remaining = Some((framing, remaining - (header_size + length) as u64));
+
+        Ok(Some((tag, value)))
+    }
+
+    /// Whether the whole payload has been returned
+    pub fn is_finished(&self) -> bool {
+        matches!(self.remaining, Some((_, 0)))
+    }
+
+    /// Bytes fed after the end of the packet
+    pub fn trailing(&self) -> &[u8] {
+        if self.is_finished() { &self.buffer } else { &[] }
+    }
+}
+
+// === READER ===
+
+/// Packet pulled from a `Read` one block at a time. v2 headers are read a byte
+/// at a time, so an unbuffered source is best wrapped in a BufReader
+#[derive(Debug)]
+pub struct TlvReader<R> {
+    reader:         R,
+    framing:        Framing,
+    remaining:      u64,
+    value:          Vec<u8>,
+    max_block_size: usize,
+}
+
+impl<R: Read> TlvReader<R> {
+    /// Read the packet header
+    pub fn new(reader: R) -> Result<Self, StpcError> {
+        Self::with_max_block_size(reader, DEFAULT_MAX_BLOCK_SIZE)
+    }
+
+    pub fn with_max_block_size(mut reader: R, max_block_size: usize) -> Result<Self, StpcError> {
+        let mut first = [0u8; 1];
+        read_exact(&mut reader, &mut first)?;
+
+        let framing = Framing::detect(&first)?;
+        let remaining = match framing {
+            Framing::V1 => {
+                let mut header = [0u8; HEADER_SIZE];
+                read_exact(&mut reader, &mut header[1..])?;
+                u64::from_be_bytes(header)
+            }
+            Framing::V2 => read_varint_from(&mut reader)?.0,
+        };
+
+        Ok(Self {
+            reader,
+            framing,
+            remaining,
+            value: Vec::new(),
+            max_block_size,
+        })
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Next block, `None` at the end of the packet. The value is only
+    /// borrowed until the next call, its buffer is reused
+    pub fn next_block(&mut self) -> Result<Option<(u32, &[u8])>, StpcError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        check_header_fits(self.framing, self.remaining)?;
+
+        let (tag, length, header_size) = match self.framing {
+            Framing::V1 => {
+                let mut header = [0u8; BLOCK_HEADER_SIZE];
+                read_exact(&mut self.reader, &mut header)?;
+                self.framing.read_block_header(&header)?.expect("full block header")
+            }
+            Framing::V2 => {
+                let (tag, tag_size) = read_varint_from(&mut self.reader)?;
+                let (length, length_size) = read_varint_from(&mut self.reader)?;
+                let tag = u32::try_from(tag)
+                    .map_err(|_| StpcError::InvalidPacketError(format!("Tag {} is beyond u32", tag)))?;
+                let length = usize::try_from(length)
+                    .map_err(|_| StpcError::InvalidPacketError(format!("Block length {} is too long", length)))?;
+                (tag, length, tag_size + length_size)
+            }
+        };
+        check_block(self.remaining, header_size, length, self.max_block_size)?;
+
+        self.value.resize(length, 0);
+        read_exact(&mut self.reader, &mut self.value)?;
+        self.remaining -= (header_size + length) as u64;
+
+        Ok(Some((tag, &self.value)))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+// === WRITER ===
+
+/// Packet written to a `Write` block by block. The payload length goes first,
+/// so it is declared up front; `block_size` (or `Framing::block_size` for v2)
+/// adds it up
+#[derive(Debug)]
+pub struct TlvWriter<W> {
+    writer:    W,
+    framing:   Framing,
+    remaining: u64,
+}
+
+impl<W: Write> TlvWriter<W> {
+    /// v1 packet
+    pub fn new(writer: W, payload_len: u64) -> Result<Self, StpcError> {
+        Self::with_framing(writer, Framing::V1, payload_len)
+    }
+
+    pub fn with_framing(mut writer: W, framing: Framing, payload_len: u64) -> Result<Self, StpcError> {
+        let mut header = Vec::with_capacity(framing.header_size(payload_len));
+        framing.write_header(payload_len, &mut header);
+        writer.write_all(&header).map_err(io_error)?;
+
+        Ok(Self { writer, framing, remaining: payload_len })
+    }
+
+    /// Payload bytes taken by a v1 block with a `value_len`-byte value
+    pub fn block_size(value_len: usize) -> u64 {
+        Framing::V1.block_size(0, value_len)
+    }
+
+    pub fn write_block(&mut self, tag: u32, value: &[u8]) -> Result<(), StpcError> {
+        self.write_block_header(tag, value.len())?;
+        self.writer.write_all(value).map_err(io_error)
+    }
+
+    /// Block whose `length`-byte value is copied straight from `source`
+    pub fn write_block_from(&mut self, tag: u32, length: usize, source: &mut impl Read) -> Result<(), StpcError> {
+        self.write_block_header(tag, length)?;
+
+        let copied = io::copy(&mut source.take(length as u64), &mut self.writer).map_err(io_error)?;
+        if copied != length as u64 {
+            return Err(StpcError::IoError(format!("Source ended after {} of {} bytes", copied, length)));
+        }
+        Ok(())
+    }
+
+    /// Check that the declared payload was written in full and hand back the writer
+    pub fn finish(mut self) -> Result<W, StpcError> {
+        if self.remaining != 0 {
+            return Err(StpcError::SerilizateError(format!("{} declared payload bytes not written", self.remaining)));
+        }
+
+        self.writer.flush().map_err(io_error)?;
+        Ok(self.writer)
+    }
+
+    fn write_block_header(&mut self, tag: u32, length: usize) -> Result<(), StpcError> {
+        let size = self.framing.block_size(tag, length);
+        if size > self.remaining {
+            return Err(StpcError::SerilizateError(format!(
+                "Block of {} bytes exceeds the {} declared payload bytes left",
+                size, self.remaining
+            )));
+        }
+
+        let mut header = Vec::with_capacity(MAX_VARINT_SIZE);
+        self.framing.write_block_header(tag, length, &mut header)?;
+        self.writer.write_all(&header).map_err(io_error)?;
+        self.remaining -= size;
+        Ok(())
+    }
+}
+
+// === HELPERS ===
+
+fn check_header_fits(framing: Framing, remaining: u64) -> Result<(), StpcError> {
+    if remaining < framing.min_block_header_size() as u64 {
+        return Err(StpcError::InvalidPacketError(format!(
+            "Block header does not fit in the {} payload bytes left",
+            remaining
+        )));
+    }
+    Ok(())
+}
+
+fn check_block(remaining: u64, header_size: usize, length: usize, max_block_size: usize) -> Result<(), StpcError> {
+    if length > max_block_size {
+        return Err(StpcError::InvalidPacketError(format!(
+            "Block of {} bytes exceeds the {} byte limit",
+            length, max_block_size
+        )));
+    }
+    if (header_size as u64).saturating_add(length as u64) > remaining {
+        return Err(StpcError::InvalidPacketError(format!(
+            "Block claims length {}, but the packet has {} payload bytes left",
+            length, remaining
+        )));
+    }
+    Ok(())
+}
+
+/// Varint and its size, read a byte at a time
+fn read_varint_from(reader: &mut impl Read) -> Result<(u64, usize), StpcError> {
+    let mut bytes = Vec::with_capacity(MAX_VARINT_SIZE);
+    loop {
+        let mut byte = [0u8; 1];
+        read_exact(reader, &mut byte)?;
+        bytes.push(byte[0]);
+
+        if let Some(varint) = read_varint(&bytes)? {
+            return Ok(varint);
+        }
+    }
+}
+
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8]) -> Result<(), StpcError> {
+    reader.read_exact(buffer).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => StpcError::InvalidPacketError("Packet truncated".into()),
+        _ => io_error(e),
+    })
+}
+
+fn io_error(error: io::Error) -> StpcError {
+    StpcError::IoError(error.to_string())
+}
@@ -0,0 +1,254 @@
+// #[derive(TlvEncode, TlvDecode)] for structs with named fields.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitInt, LitStr, Path};
+
+/// Encode a struct as one packet with a block per field, in ascending tag order
+/// whatever the field order in the source. Every field carries #[tlv(tag = N)],
+/// N > 0, and may add:
+///
+/// - `optional`: `Option<T>`, the block is left out for None
+/// - `repeated`: `Vec<T>`, one block holding the list (see stpc_encoding::schema);
+///   with `optional` as well, an empty list is left out
+/// - `with = "path"`: encode and decode through `path::encode(&T)` and
+///   `path::decode(&[u8])` instead of the TlvEncode/TlvDecode impls
+/// - `check = "path"`: once decoded, the field must pass `path(&T) -> Result<(), StpcError>`
+///
+/// One field may instead carry #[tlv(framing)]: a stpc_encoding::Framing that is
+/// not written, set to the framing of the decoded packet and used by tlv_encode,
+/// so a decoded value encodes back the way it came in. Nested values are encoded
+/// in the framing of the struct around them
+#[proc_macro_derive(TlvEncode, attributes(tlv))]
+pub fn derive_tlv_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, encode).unwrap_or_else(|error| error.to_compile_error()).into()
+}
+
+/// Decode the packet written by the TlvEncode derive. Fields are looked up by tag,
+/// so blocks may come in any order; duplicate and undeclared tags are errors
+#[proc_macro_derive(TlvDecode, attributes(tlv))]
+pub fn derive_tlv_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, decode).unwrap_or_else(|error| error.to_compile_error()).into()
+}
+
+// === FIELDS ===
+
+struct Field {
+    ident:    Ident,
+    tag:      u32,
+    optional: bool,
+    repeated: bool,
+    with:     Option<Path>,
+    check:    Option<Path>,
+}
+
+/// Tagged fields in ascending tag order, and the framing field if there is one
+struct Layout {
+    fields:  Vec<Field>,
+    framing: Option<Ident>,
+}
+
+/// A field is either written under a tag or holds the framing
+enum Parsed {
+    Tagged(Field),
+    Framing(Ident),
+}
+
+impl Field {
+    fn parse(field: &syn::Field) -> syn::Result<Parsed> {
+        let ident = field.ident.clone().expect("named field");
+        let mut tag = None;
+        let mut optional = false;
+        let mut repeated = false;
+        let mut with = None;
+        let mut check = None;
+        let mut framing = false;
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("tlv")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    tag = Some(meta.value()?.parse::<LitInt>()?.base10_parse::<u32>()?);
+                } else if meta.path.is_ident("optional") {
+                    optional = true;
+                } else if meta.path.is_ident("repeated") {
+                    repeated = true;
+                } else if meta.path.is_ident("with") {
+                    with = Some(meta.value()?.parse::<LitStr>()?.parse::<Path>()?);
+                } else if meta.path.is_ident("check") {
+                    check = Some(meta.value()?.parse::<LitStr>()?.parse::<Path>()?);
+                } else if meta.path.is_ident("framing") {
+                    framing = true;
+                } else {
+                    return Err(meta.error("expected `tag`, `optional`, `repeated`, `with`, `check` or `framing`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if framing {
+            if tag.is_some() || optional || repeated || with.is_some() || check.is_some() {
+                return Err(syn::Error::new_spanned(&ident, "#[tlv(framing)] takes no other options"));
+            }
+            return Ok(Parsed::Framing(ident));
+        }
+
+        let tag = tag.ok_or_else(|| syn::Error::new_spanned(&ident, "missing #[tlv(tag = N)]"))?;
+        if tag == 0 {
+            return Err(syn::Error::new_spanned(&ident, "tag 0 is reserved for list elements"));
+        }
+        Ok(Parsed::Tagged(Self { ident, tag, optional, repeated, with, check }))
+    }
+
+    /// Expression encoding `value` (a reference to one element) into Vec<u8>
+    fn encode_value(&self, value: TokenStream2) -> TokenStream2 {
+        match &self.with {
+            Some(path) => quote!(#path::encode(#value)),
+            None => quote!(::stpc_encoding::TlvEncode::tlv_encode_as(#value, framing)),
+        }
+    }
+
+    /// Function decoding one element from &[u8]
+    fn decoder(&self) -> TokenStream2 {
+        match &self.with {
+            Some(path) => quote!(#path::decode),
+            None => quote!(::stpc_encoding::TlvDecode::tlv_decode),
+        }
+    }
+}
+
+fn expand(input: &DeriveInput, generate: fn(&DeriveInput, &Layout) -> TokenStream2) -> syn::Result<TokenStream2> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "TLV derives need named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "TLV derives support structs only")),
+    };
+
+    let mut layout = Layout { fields: Vec::new(), framing: None };
+    for field in named {
+        match Field::parse(field)? {
+            Parsed::Tagged(field) => layout.fields.push(field),
+            Parsed::Framing(ident) if layout.framing.is_some() => {
+                return Err(syn::Error::new_spanned(ident, "only one field may hold the framing"));
+            }
+            Parsed::Framing(ident) => layout.framing = Some(ident),
+        }
+    }
+
+    layout.fields.sort_by_key(|field| field.tag);
+    for pair in layout.fields.windows(2) {
+        if pair[0].tag == pair[1].tag {
+            return Err(syn::Error::new_spanned(&pair[1].ident, format!("tag {} is used twice", pair[1].tag)));
+        }
+    }
+
+    Ok(generate(input, &layout))
+}
+
+// === ENCODE ===
+
+fn encode(input: &DeriveInput, layout: &Layout) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let pushes = layout.fields.iter().map(|field| {
+        let ident = &field.ident;
+        let tag = field.tag;
+
+        if field.repeated {
+            let element = field.encode_value(quote!(item));
+            let list = quote!(::stpc_encoding::schema::encode_repeated(framing, &self.#ident, |item| #element)?);
+            if field.optional {
+                quote!(if !self.#ident.is_empty() { blocks.push(#tag, #list); })
+            } else {
+                quote!(blocks.push(#tag, #list);)
+            }
+        } else if field.optional {
+            let value = field.encode_value(quote!(value));
+            quote!(if let Some(value) = &self.#ident { blocks.push(#tag, #value?); })
+        } else {
+            let value = field.encode_value(quote!(&self.#ident));
+            quote!(blocks.push(#tag, #value?);)
+        }
+    });
+    let own_framing = match &layout.framing {
+        Some(ident) => quote!(self.#ident),
+        None => quote!(::stpc_encoding::Framing::V1),
+    };
+
+    quote! {
+        impl #impl_generics ::stpc_encoding::TlvEncode for #name #type_generics #where_clause {
+            fn tlv_encode(&self) -> ::core::result::Result<::std::vec::Vec<u8>, ::stpc_core::StpcError> {
+                ::stpc_encoding::TlvEncode::tlv_encode_as(self, #own_framing)
+            }
+
+            fn tlv_encode_as(
+                &self,
+                framing: ::stpc_encoding::Framing,
+            ) -> ::core::result::Result<::std::vec::Vec<u8>, ::stpc_core::StpcError> {
+                let mut blocks = ::stpc_encoding::schema::Blocks::with_framing(framing);
+                #(#pushes)*
+                blocks.finish()
+            }
+        }
+    }
+}
+
+// === DECODE ===
+
+fn decode(input: &DeriveInput, layout: &Layout) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let reads = layout.fields.iter().map(|field| {
+        let ident = &field.ident;
+        let field_name = ident.to_string();
+        let tag = field.tag;
+        let decoder = field.decoder();
+        let check = field.check.iter().map(|path| quote!(#path(&#ident)?;));
+
+        let element = if field.repeated {
+            quote!(::stpc_encoding::schema::decode_repeated(&value, #decoder)?)
+        } else {
+            quote!(#decoder(&value)?)
+        };
+
+        if field.optional {
+            let absent = if field.repeated { quote!(::std::vec::Vec::new()) } else { quote!(None) };
+            let present = if field.repeated { element } else { quote!(Some(#element)) };
+            quote! {
+                let #ident = match fields.optional(#tag)? {
+                    Some(value) => #present,
+                    None => #absent,
+                };
+                #(#check)*
+            }
+        } else {
+            quote! {
+                let #ident = {
+                    let value = fields.required(#tag, #field_name)?;
+                    #element
+                };
+                #(#check)*
+            }
+        }
+    });
+    let framing = layout.framing.iter().map(|ident| quote!(let #ident = fields.framing();));
+    let idents = layout.fields.iter().map(|field| &field.ident).chain(&layout.framing);
+
+    quote! {
+        impl #impl_generics ::stpc_encoding::TlvDecode for #name #type_generics #where_clause {
+            fn tlv_decode(data: &[u8]) -> ::core::result::Result<Self, ::stpc_core::StpcError> {
+                let mut fields = ::stpc_encoding::TLVParser::fields_strict(data)?;
+                #(#framing)*
+                #(#reads)*
+                fields.finish()?;
+                Ok(Self { #(#idents),* })
+            }
+        }
+    }
+}
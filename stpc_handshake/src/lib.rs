@@ -0,0 +1,347 @@
+// Mutually authenticated key exchange between two certificate holders.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use zeroize::Zeroizing;
+
+use stpc_certs::extensions::{ExtensionValue, KeyPurpose, EXTENDED_KEY_USAGE};
+use stpc_certs::path::{validate_path, TrustAnchor};
+use stpc_certs::{CertSerializable, Certificate};
+use stpc_core::{Ciphertext, HashAlgorithm, KemAlgorithm, Key, PublicKey, SharedSecret, StpcError};
+use stpc_crypto::hash;
+use stpc_crypto::{TypedPrivateKey, TypedSignature};
+use stpc_time::TimeManager;
+
+pub mod messages;
+pub mod record;
+
+use messages::{CertificateMessage, CertificateVerify, ClientHello, Finished, ServerHello};
+
+// === TYPES ===
+
+// HandshakeConfig
+pub struct HandshakeConfig {
+    /// Own certificate chain, leaf first; the trust anchor may be left out
+    pub chain:         Vec<Certificate>,
+    /// Key of the leaf's subject, under the algorithm the leaf declares
+    pub private_key:   TypedPrivateKey,
+    /// Roots the peer's chain must lead to
+    pub trust_anchors: Vec<TrustAnchor>,
+    /// Offered by a client, required from clients by a server
+    pub kem:           KemAlgorithm,
+}
+
+// Role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+// SessionKeys
+pub struct SessionKeys {
+    /// Traffic secret for client -> server records
+    pub client_write: Zeroizing<[u8; 32]>,
+    /// Traffic secret for server -> client records
+    pub server_write: Zeroizing<[u8; 32]>,
+}
+
+// Session
+pub struct Session {
+    pub role:            Role,
+    pub keys:            SessionKeys,
+    /// SHA-256 over every handshake message, identical on both ends
+    pub transcript_hash: [u8; 32],
+    /// Peer's validated chain, leaf first, without the trust anchor
+    pub peer_chain:      Vec<Certificate>,
+}
+
+/// Runs handshakes for one identity, as client or as server, over any Read + Write stream:
+///
+///   client -> server  ClientHello         random, KEM algorithm, ephemeral KEM public key
+///   server -> client  ServerHello         random, KEM ciphertext
+///                     Certificate         server chain and its key's signature algorithm
+///                     CertificateVerify   signature over the transcript hash
+///                     Finished            HMAC over the transcript hash
+///   client -> server  Certificate, CertificateVerify, Finished
+pub struct Handshake {
+    config: HandshakeConfig,
+    time:   Arc<TimeManager>,
+}
+
+// === ROLE ===
+
+impl Role {
+    /// ExtendedKeyUsage purpose required from this side's certificate
+    fn purpose(&self) -> KeyPurpose {
+        match self {
+            Role::Client => KeyPurpose::ClientAuth,
+            Role::Server => KeyPurpose::ServerAuth,
+        }
+    }
+
+    /// Prefix of the CertificateVerify content, keeps the two signatures apart
+    fn context(&self) -> &'static [u8] {
+        match self {
+            Role::Client => b"STPC handshake, client CertificateVerify",
+            Role::Server => b"STPC handshake, server CertificateVerify",
+        }
+    }
+
+    fn finished_label(&self) -> &'static [u8] {
+        match self {
+            Role::Client => b"stpc client finished",
+            Role::Server => b"stpc server finished",
+        }
+    }
+}
+
+// === SESSION ===
+
+impl Session {
+    /// Peer's leaf certificate
+    pub fn peer_certificate(&self) -> &Certificate {
+        &self.peer_chain[0]
+    }
+
+    /// Traffic secret for records this side sends
+    pub fn write_secret(&self) -> &[u8; 32] {
+        match self.role {
+            Role::Client => &self.keys.client_write,
+            Role::Server => &self.keys.server_write,
+        }
+    }
+
+    /// Traffic secret for records this side receives
+    pub fn read_secret(&self) -> &[u8; 32] {
+        match self.role {
+            Role::Client => &self.keys.server_write,
+            Role::Server => &self.keys.client_write,
+        }
+    }
+}
+
+// === HANDSHAKE ===
+
+impl Handshake {
+    pub fn new(config: HandshakeConfig, time: Arc<TimeManager>) -> Self {
+        Self { config, time }
+    }
+
+    /// Run the client side over `stream`
+    pub fn connect<S: Read + Write>(&self, stream: &mut S) -> Result<Session, StpcError> {
+        let mut transcript = Transcript::new();
+
+        let (kem_private, kem_public) = stpc_crypto::kem::keypair(self.config.kem)?;
+        let client_hello = ClientHello::new(self.config.kem, kem_public.as_bytes().to_vec());
+        transcript.send(stream, &client_hello)?;
+
+        let server_hello: ServerHello = transcript.receive(stream)?;
+        let ciphertext = Ciphertext::from_bytes(&server_hello.ciphertext);
+        let shared_secret = stpc_crypto::kem::decapsulate(self.config.kem, &kem_private, &ciphertext)?;
+        let schedule = KeySchedule::new(&shared_secret, &client_hello.random, &server_hello.random);
+
+        let peer_chain = self.verify_peer(stream, &mut transcript, Role::Server)?;
+        schedule.receive_finished(stream, &mut transcript, Role::Server)?;
+
+        self.authenticate(stream, &mut transcript, Role::Client)?;
+        schedule.send_finished(stream, &mut transcript, Role::Client)?;
+
+        Ok(schedule.session(Role::Client, transcript.hash(), peer_chain))
+    }
+
+    /// Run the server side over `stream`
+    pub fn accept<S: Read + Write>(&self, stream: &mut S) -> Result<Session, StpcError> {
+        let mut transcript = Transcript::new();
+
+        let client_hello: ClientHello = transcript.receive(stream)?;
+        if client_hello.kem != self.config.kem {
+            return Err(StpcError::HandshakeError(format!(
+                "Client offered {:?}, server requires {:?}",
+                client_hello.kem, self.config.kem
+            )));
+        }
+
+        let public_key = PublicKey::from_bytes(&client_hello.public_key);
+        let (shared_secret, ciphertext) = stpc_crypto::kem::encapsulate(self.config.kem, &public_key)?;
+        let server_hello = ServerHello::new(ciphertext.as_bytes().to_vec());
+        transcript.send(stream, &server_hello)?;
+        let schedule = KeySchedule::new(&shared_secret, &client_hello.random, &server_hello.random);
+
+        self.authenticate(stream, &mut transcript, Role::Server)?;
+        schedule.send_finished(stream, &mut transcript, Role::Server)?;
+
+        let peer_chain = self.verify_peer(stream, &mut transcript, Role::Client)?;
+        schedule.receive_finished(stream, &mut transcript, Role::Client)?;
+
+        Ok(schedule.session(Role::Server, transcript.hash(), peer_chain))
+    }
+
+    /// Send own chain and a signature over the transcript so far
+    fn authenticate<S: Write>(&self, stream: &mut S, transcript: &mut Transcript, role: Role) -> Result<(), StpcError> {
+        let certificate = CertificateMessage::new(self.config.chain.clone(), self.config.private_key.algorithm());
+        transcript.send(stream, &certificate)?;
+
+        let content = signed_content(role, &transcript.hash());
+        let signature = self.config.private_key.sign(&content)?;
+        transcript.send(stream, &CertificateVerify::new(signature.signature().as_bytes().to_vec()))
+    }
+
+    /// Receive the peer's chain, validate it against the trust anchors and check its signature
+    fn verify_peer<S: Read>(
+        &self,
+        stream: &mut S,
+        transcript: &mut Transcript,
+        peer: Role,
+    ) -> Result<Vec<Certificate>, StpcError> {
+        let certificate: CertificateMessage = transcript.receive(stream)?;
+        let content = signed_content(peer, &transcript.hash());
+        let verify: CertificateVerify = transcript.receive(stream)?;
+
+        let chain = self.validate_chain(&certificate.chain, peer)?;
+        // The algorithm comes from the validated leaf, the peer's claim only has to agree
+        let public_key = chain[0].tbs_certificate.subject_key()?;
+        if certificate.signature_algorithm != public_key.algorithm() {
+            return Err(StpcError::HandshakeError(format!(
+                "Peer claims {:?} for a {:?} key",
+                certificate.signature_algorithm,
+                public_key.algorithm()
+            )));
+        }
+        let signature = TypedSignature::from_bytes(public_key.algorithm(), &verify.signature)?;
+        if !public_key.verify(&content, &signature)? {
+            return Err(StpcError::SignatureVerifyError);
+        }
+
+        Ok(chain)
+    }
+
+    fn validate_chain(&self, chain: &[Certificate], peer: Role) -> Result<Vec<Certificate>, StpcError> {
+        let (leaf, intermediates) = chain
+            .split_first()
+            .ok_or_else(|| StpcError::HandshakeError("Peer sent an empty certificate chain".into()))?;
+
+        let report = validate_path(leaf, intermediates, &self.config.trust_anchors, self.time.get_time());
+        if let Some(failure) = report.failure {
+            return Err(StpcError::HandshakeError(format!(
+                "Peer certificate chain rejected at depth {}: {:?}",
+                failure.depth, failure.reason
+            )));
+        }
+        if !report.is_valid() {
+            return Err(StpcError::HandshakeError("Peer certificate chain is not trusted".into()));
+        }
+
+        // Like KeyUsage in path validation, a missing ExtendedKeyUsage allows any purpose
+        if let Some(ExtensionValue::ExtendedKeyUsage(purposes)) =
+            leaf.tbs_certificate.extension(EXTENDED_KEY_USAGE).map(|ext| &ext.value)
+            && !purposes.contains(&peer.purpose())
+        {
+            return Err(StpcError::HandshakeError(format!(
+                "Peer certificate lacks the {:?} purpose",
+                peer.purpose()
+            )));
+        }
+
+        Ok(report.chain)
+    }
+}
+
+// === TRANSCRIPT ===
+
+/// Transcript hash and the key schedule's HKDF and HMAC
+const HASH: HashAlgorithm = HashAlgorithm::Sha256;
+
+/// Every message sent or received, in wire form, hashed on demand
+struct Transcript {
+    packets: Vec<u8>,
+}
+
+impl Transcript {
+    fn new() -> Self {
+        Self { packets: Vec::new() }
+    }
+
+    fn hash(&self) -> [u8; 32] {
+        hash::digest(HASH, &self.packets).try_into().expect("SHA-256 digests are 32 bytes")
+    }
+
+    fn send<W: Write, M: CertSerializable>(&mut self, stream: &mut W, message: &M) -> Result<(), StpcError> {
+        let packet = message.serialize()?;
+        messages::write_packet(stream, &packet)?;
+        self.packets.extend_from_slice(&packet);
+        Ok(())
+    }
+
+    /// Only the canonical encoding of a message is accepted, so the transcript
+    /// hash covers the one form both ends agree on
+    fn receive<R: Read, M: CertSerializable>(&mut self, stream: &mut R) -> Result<M, StpcError> {
+        let packet = messages::read_packet(stream)?;
+        self.packets.extend_from_slice(&packet);
+        M::deserialize_canonical(&packet)
+    }
+}
+
+// === KEY SCHEDULE ===
+
+/// Handshake secret: the KEM shared secret salted with both randoms. Finished keys
+/// are expanded from it directly, traffic secrets over the complete transcript hash
+struct KeySchedule {
+    /// HKDF pseudorandom key
+    handshake_secret: Zeroizing<Vec<u8>>,
+}
+
+impl KeySchedule {
+    fn new(shared_secret: &SharedSecret, client_random: &[u8; 32], server_random: &[u8; 32]) -> Self {
+        let salt = [client_random.as_slice(), server_random.as_slice()].concat();
+
+        Self {
+            handshake_secret: hash::hkdf_extract(HASH, &salt, shared_secret.as_bytes()),
+        }
+    }
+
+    fn expand(&self, label: &[u8], context: &[u8]) -> Zeroizing<[u8; 32]> {
+        let okm = hash::hkdf_expand(HASH, &self.handshake_secret, &[label, context].concat(), 32)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        let mut output = Zeroizing::new([0u8; 32]);
+        output.copy_from_slice(&okm);
+        output
+    }
+
+    fn send_finished<W: Write>(&self, stream: &mut W, transcript: &mut Transcript, role: Role) -> Result<(), StpcError> {
+        let key = self.expand(role.finished_label(), &[]);
+        let verify_data = hash::hmac(HASH, key.as_slice(), &transcript.hash())
+            .try_into()
+            .expect("HMAC-SHA256 tags are 32 bytes");
+        transcript.send(stream, &Finished::new(verify_data))
+    }
+
+    fn receive_finished<R: Read>(&self, stream: &mut R, transcript: &mut Transcript, role: Role) -> Result<(), StpcError> {
+        let key = self.expand(role.finished_label(), &[]);
+        let transcript_hash = transcript.hash();
+        let finished: Finished = transcript.receive(stream)?;
+
+        if !hash::hmac_verify(HASH, key.as_slice(), &transcript_hash, &finished.verify_data) {
+            return Err(StpcError::HandshakeError(format!("Invalid {:?} Finished", role)));
+        }
+        Ok(())
+    }
+
+    fn session(&self, role: Role, transcript_hash: [u8; 32], peer_chain: Vec<Certificate>) -> Session {
+        Session {
+            role,
+            keys: SessionKeys {
+                client_write: self.expand(b"stpc client write", &transcript_hash),
+                server_write: self.expand(b"stpc server write", &transcript_hash),
+            },
+            transcript_hash,
+            peer_chain,
+        }
+    }
+}
+
+/// Content signed in CertificateVerify
+fn signed_content(role: Role, transcript_hash: &[u8; 32]) -> Vec<u8> {
+    [role.context(), &[0], transcript_hash].concat()
+}
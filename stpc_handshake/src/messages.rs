@@ -0,0 +1,242 @@
+// Handshake messages, one TLVParser packet each with the message type in tag 1.
+
+use std::io::{Read, Write};
+
+use rand::{rngs::OsRng, RngCore};
+
+use stpc_certs::{tlv_serializable, Certificate};
+use stpc_core::{KemAlgorithm, SignatureAlgorithm, StpcError};
+use stpc_encoding::{TlvDecode, TlvEncode};
+
+/// Only protocol version so far
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Upper bound for a single message, SLH-DSA chains stay well below it
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+const CLIENT_HELLO:       u8 = 1;
+const SERVER_HELLO:       u8 = 2;
+const CERTIFICATE:        u8 = 3;
+const CERTIFICATE_VERIFY: u8 = 4;
+const FINISHED:           u8 = 5;
+
+// === TYPES ===
+
+// ClientHello
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct ClientHello {
+    #[tlv(tag = 1)]
+    message_type:   MessageType<CLIENT_HELLO>,
+    #[tlv(tag = 2)]
+    version:        ProtocolVersion,
+    #[tlv(tag = 3)]
+    pub random:     [u8; 32],
+    #[tlv(tag = 4, with = "kem")]
+    pub kem:        KemAlgorithm,
+    /// Ephemeral KEM public key, used for this handshake only
+    #[tlv(tag = 5)]
+    pub public_key: Vec<u8>,
+}
+
+// ServerHello
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct ServerHello {
+    #[tlv(tag = 1)]
+    message_type:   MessageType<SERVER_HELLO>,
+    #[tlv(tag = 2)]
+    version:        ProtocolVersion,
+    #[tlv(tag = 3)]
+    pub random:     [u8; 32],
+    /// KEM encapsulation to the client's ephemeral key
+    #[tlv(tag = 4)]
+    pub ciphertext: Vec<u8>,
+}
+
+// CertificateMessage
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct CertificateMessage {
+    #[tlv(tag = 1)]
+    message_type:            MessageType<CERTIFICATE>,
+    /// Sender's chain, leaf first
+    #[tlv(tag = 2, repeated, with = "stpc_certs::tlv::certificate")]
+    pub chain:               Vec<Certificate>,
+    /// Algorithm of the leaf's subject key, used for CertificateVerify
+    #[tlv(tag = 3, with = "stpc_certs::tlv::signature_algorithm")]
+    pub signature_algorithm: SignatureAlgorithm,
+}
+
+// CertificateVerify
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct CertificateVerify {
+    #[tlv(tag = 1)]
+    message_type:  MessageType<CERTIFICATE_VERIFY>,
+    #[tlv(tag = 2)]
+    pub signature: Vec<u8>,
+}
+
+// Finished
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct Finished {
+    #[tlv(tag = 1)]
+    message_type:    MessageType<FINISHED>,
+    #[tlv(tag = 2)]
+    pub verify_data: [u8; 32],
+}
+
+tlv_serializable!(ClientHello, ServerHello, CertificateMessage, CertificateVerify, Finished);
+
+// MessageType
+/// Tag 1 of every message; decoding fails unless it holds `T`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageType<const T: u8>;
+
+// ProtocolVersion
+/// Tag 2 of the hellos; decoding fails unless it holds PROTOCOL_VERSION
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProtocolVersion;
+
+// === CONSTRUCTORS ===
+
+impl ClientHello {
+    pub fn new(kem: KemAlgorithm, public_key: Vec<u8>) -> Self {
+        Self {
+            message_type: MessageType,
+            version: ProtocolVersion,
+            random: random(),
+            kem,
+            public_key,
+        }
+    }
+}
+
+impl ServerHello {
+    pub fn new(ciphertext: Vec<u8>) -> Self {
+        Self {
+            message_type: MessageType,
+            version: ProtocolVersion,
+            random: random(),
+            ciphertext,
+        }
+    }
+}
+
+impl CertificateMessage {
+    pub fn new(chain: Vec<Certificate>, signature_algorithm: SignatureAlgorithm) -> Self {
+        Self { message_type: MessageType, chain, signature_algorithm }
+    }
+}
+
+impl CertificateVerify {
+    pub fn new(signature: Vec<u8>) -> Self {
+        Self { message_type: MessageType, signature }
+    }
+}
+
+impl Finished {
+    pub fn new(verify_data: [u8; 32]) -> Self {
+        Self { message_type: MessageType, verify_data }
+    }
+}
+
+// === HEADER FIELDS ===
+
+impl<const T: u8> TlvEncode for MessageType<T> {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(vec![T])
+    }
+}
+
+impl<const T: u8> TlvDecode for MessageType<T> {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        match value {
+            [message_type] if *message_type == T => Ok(Self),
+            _ => Err(StpcError::HandshakeError(format!(
+                "Unexpected message type {:?}, expected {}",
+                value, T
+            ))),
+        }
+    }
+}
+
+impl TlvEncode for ProtocolVersion {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        Ok(vec![PROTOCOL_VERSION])
+    }
+}
+
+impl TlvDecode for ProtocolVersion {
+    fn tlv_decode(value: &[u8]) -> Result<Self, StpcError> {
+        match value {
+            [PROTOCOL_VERSION] => Ok(Self),
+            _ => Err(StpcError::HandshakeError(format!("Unsupported protocol version {:?}", value))),
+        }
+    }
+}
+
+// === FRAMING ===
+
+/// Read one packet: the 8-byte length header followed by that many bytes
+pub fn read_packet<R: Read>(stream: &mut R) -> Result<Vec<u8>, StpcError> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).map_err(io_error)?;
+
+    let length = u64::from_be_bytes(header);
+    if length > MAX_MESSAGE_SIZE as u64 {
+        return Err(StpcError::HandshakeError(format!("Message too large: {} bytes", length)));
+    }
+
+    let mut packet = vec![0u8; 8 + length as usize];
+    packet[..8].copy_from_slice(&header);
+    stream.read_exact(&mut packet[8..]).map_err(io_error)?;
+
+    Ok(packet)
+}
+
+pub fn write_packet<W: Write>(stream: &mut W, packet: &[u8]) -> Result<(), StpcError> {
+    stream.write_all(packet).map_err(io_error)?;
+    stream.flush().map_err(io_error)
+}
+
+// === WIRE TAGS ===
+
+pub fn kem_tag(kem: KemAlgorithm) -> u8 {
+    match kem {
+        KemAlgorithm::X25519 => 1,
+        KemAlgorithm::MlKem768 => 2,
+        KemAlgorithm::X25519MlKem768 => 3,
+    }
+}
+
+pub fn kem_from_tag(value: &[u8]) -> Result<KemAlgorithm, StpcError> {
+    match value {
+        [1] => Ok(KemAlgorithm::X25519),
+        [2] => Ok(KemAlgorithm::MlKem768),
+        [3] => Ok(KemAlgorithm::X25519MlKem768),
+        _ => Err(StpcError::DeserilizateError("Unknown KEM algorithm".into())),
+    }
+}
+
+/// `#[tlv(with = ...)]` adapter for kem_tag
+mod kem {
+    use stpc_core::{KemAlgorithm, StpcError};
+
+    pub fn encode(kem: &KemAlgorithm) -> Result<Vec<u8>, StpcError> {
+        Ok(vec![super::kem_tag(*kem)])
+    }
+
+    pub fn decode(value: &[u8]) -> Result<KemAlgorithm, StpcError> {
+        super::kem_from_tag(value)
+    }
+}
+
+// === HELPERS ===
+
+fn random() -> [u8; 32] {
+    let mut random = [0u8; 32];
+    OsRng.fill_bytes(&mut random);
+    random
+}
+
+fn io_error(e: std::io::Error) -> StpcError {
+    StpcError::NetworkError(e.to_string())
+}
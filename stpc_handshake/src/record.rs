@@ -0,0 +1,325 @@
+// Record layer for established sessions.
+
+use std::io::{self, Read, Write};
+
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use zeroize::Zeroizing;
+
+use stpc_core::{HashAlgorithm, StpcError};
+use stpc_crypto::hash;
+use stpc_encoding::{TLVParser, TLV};
+
+use crate::messages;
+use crate::Session;
+
+/// Largest plaintext carried by one record
+pub const MAX_RECORD_SIZE: usize = 16 * 1024;
+
+/// Records sent under one key before the writer updates it on its own
+pub const KEY_UPDATE_INTERVAL: u64 = 1 << 24;
+
+const TAG_SIZE: usize = 16;
+
+const DATA:       u8 = 1;
+const KEY_UPDATE: u8 = 2;
+const CLOSE:      u8 = 3;
+
+// === TYPES ===
+
+// CipherSuite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    ChaCha20Poly1305,
+    Aes256Gcm,
+}
+
+enum Cipher {
+    ChaCha20Poly1305(ChaCha20Poly1305),
+    /// Boxed, the expanded AES key schedule is much larger than the ChaCha20 key
+    Aes256Gcm(Box<Aes256Gcm>),
+}
+
+/// Keys and sequence number of one direction. The AEAD key and IV are expanded
+/// from `secret`; a KeyUpdate moves to the next secret and restarts `sequence`
+struct Direction {
+    suite:    CipherSuite,
+    secret:   Zeroizing<[u8; 32]>,
+    cipher:   Cipher,
+    iv:       Zeroizing<[u8; 12]>,
+    sequence: u64,
+}
+
+/// Encrypted `Read + Write` adapter over a stream after a handshake. A record is a
+/// TLVParser packet: type (1), sequence number (2), ciphertext (3). Records are
+/// accepted strictly in order, so a sequence number below the expected one is a replay
+pub struct RecordStream<S> {
+    stream:   S,
+    writer:   Direction,
+    reader:   Direction,
+    /// Plaintext of the last data record not yet returned by `read`
+    buffer:   Zeroizing<Vec<u8>>,
+    position: usize,
+    closed:   bool,
+}
+
+// === CIPHER SUITE ===
+
+impl CipherSuite {
+    /// Binds the derived keys to the suite, both ends must use the same one
+    fn label(&self) -> &'static [u8] {
+        match self {
+            CipherSuite::ChaCha20Poly1305 => b"chacha20-poly1305",
+            CipherSuite::Aes256Gcm => b"aes-256-gcm",
+        }
+    }
+}
+
+// === DIRECTION ===
+
+impl Direction {
+    fn new(suite: CipherSuite, secret: &[u8; 32]) -> Self {
+        let mut key = Zeroizing::new([0u8; 32]);
+        let mut iv = Zeroizing::new([0u8; 12]);
+        expand(secret, &[b"stpc record key ", suite.label()].concat(), key.as_mut_slice());
+        expand(secret, &[b"stpc record iv ", suite.label()].concat(), iv.as_mut_slice());
+
+        let cipher = match suite {
+            CipherSuite::ChaCha20Poly1305 => Cipher::ChaCha20Poly1305(ChaCha20Poly1305::new(key.as_slice().into())),
+            CipherSuite::Aes256Gcm => Cipher::Aes256Gcm(Box::new(Aes256Gcm::new(key.as_slice().into()))),
+        };
+
+        Self {
+            suite,
+            secret: Zeroizing::new(*secret),
+            cipher,
+            iv,
+            sequence: 0,
+        }
+    }
+
+    /// Move on to the next traffic secret
+    fn update(&mut self) {
+        let mut next = Zeroizing::new([0u8; 32]);
+        expand(&self.secret, b"stpc record update", next.as_mut_slice());
+
+        *self = Direction::new(self.suite, &next);
+    }
+
+    fn nonce(&self, sequence: u64) -> [u8; 12] {
+        let mut nonce = *self.iv;
+        for (byte, counter) in nonce[4..].iter_mut().zip(sequence.to_be_bytes()) {
+            *byte ^= counter;
+        }
+        nonce
+    }
+
+    fn seal(&mut self, record_type: u8, plaintext: &[u8]) -> Result<(u64, Vec<u8>), StpcError> {
+        let sequence = self.sequence;
+        let nonce = self.nonce(sequence);
+        let aad = associated_data(record_type, sequence);
+        let payload = Payload { msg: plaintext, aad: &aad };
+
+        let ciphertext = match &self.cipher {
+            Cipher::ChaCha20Poly1305(cipher) => cipher.encrypt(&nonce.into(), payload),
+            Cipher::Aes256Gcm(cipher) => cipher.encrypt(&nonce.into(), payload),
+        }
+        .map_err(|_| StpcError::RecordTooLargeError(plaintext.len()))?;
+
+        self.sequence += 1;
+        Ok((sequence, ciphertext))
+    }
+
+    fn open(&mut self, record_type: u8, sequence: u64, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>, StpcError> {
+        if sequence < self.sequence {
+            return Err(StpcError::RecordReplayError { expected: self.sequence, received: sequence });
+        }
+        if sequence > self.sequence {
+            return Err(StpcError::RecordSequenceError { expected: self.sequence, received: sequence });
+        }
+
+        let nonce = self.nonce(sequence);
+        let aad = associated_data(record_type, sequence);
+        let payload = Payload { msg: ciphertext, aad: &aad };
+
+        let plaintext = match &self.cipher {
+            Cipher::ChaCha20Poly1305(cipher) => cipher.decrypt(&nonce.into(), payload),
+            Cipher::Aes256Gcm(cipher) => cipher.decrypt(&nonce.into(), payload),
+        }
+        .map_err(|_| StpcError::RecordAuthenticationError)?;
+
+        self.sequence += 1;
+        Ok(Zeroizing::new(plaintext))
+    }
+}
+
+// === RECORD STREAM ===
+
+impl<S: Read + Write> RecordStream<S> {
+    /// Records over `stream` with the traffic secrets of `session`
+    pub fn new(stream: S, session: &Session, suite: CipherSuite) -> Self {
+        Self::from_secrets(stream, suite, session.write_secret(), session.read_secret())
+    }
+
+    pub fn from_secrets(stream: S, suite: CipherSuite, write_secret: &[u8; 32], read_secret: &[u8; 32]) -> Self {
+        Self {
+            stream,
+            writer:   Direction::new(suite, write_secret),
+            reader:   Direction::new(suite, read_secret),
+            buffer:   Zeroizing::new(Vec::new()),
+            position: 0,
+            closed:   false,
+        }
+    }
+
+    /// Send `data`, split into records of at most `MAX_RECORD_SIZE` bytes
+    pub fn send(&mut self, data: &[u8]) -> Result<(), StpcError> {
+        for chunk in data.chunks(MAX_RECORD_SIZE) {
+            self.write_record(DATA, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Plaintext of the next data record, `None` once the peer has closed.
+    /// KeyUpdate records on the way are applied
+    pub fn receive(&mut self) -> Result<Option<Zeroizing<Vec<u8>>>, StpcError> {
+        if self.closed {
+            return Ok(None);
+        }
+
+        loop {
+            let (record_type, plaintext) = self.read_record()?;
+            match record_type {
+                DATA => return Ok(Some(plaintext)),
+                KEY_UPDATE => {
+                    self.reader.update();
+                    if plaintext.as_slice() == [1] {
+                        self.update_keys(false)?;
+                    }
+                }
+                CLOSE => {
+                    self.closed = true;
+                    return Ok(None);
+                }
+                other => return Err(StpcError::RecordTypeError(other)),
+            }
+        }
+    }
+
+    /// Switch to the next write key; with `request_peer` the peer answers by updating its own
+    pub fn update_keys(&mut self, request_peer: bool) -> Result<(), StpcError> {
+        self.write_record(KEY_UPDATE, &[request_peer as u8])?;
+        self.writer.update();
+        Ok(())
+    }
+
+    /// Tell the peer no more data follows. Without it, end of stream is an error
+    pub fn close(&mut self) -> Result<(), StpcError> {
+        self.write_record(CLOSE, &[])
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    fn write_record(&mut self, record_type: u8, plaintext: &[u8]) -> Result<(), StpcError> {
+        if plaintext.len() > MAX_RECORD_SIZE {
+            return Err(StpcError::RecordTooLargeError(plaintext.len()));
+        }
+        if record_type == DATA && self.writer.sequence >= KEY_UPDATE_INTERVAL {
+            self.update_keys(false)?;
+        }
+
+        let (sequence, ciphertext) = self.writer.seal(record_type, plaintext)?;
+        let sequence = sequence.to_be_bytes();
+        let packet = TLVParser::pack(&[(1, &[record_type]), (2, &sequence), (3, &ciphertext)])?;
+
+        messages::write_packet(&mut self.stream, &packet)
+    }
+
+    fn read_record(&mut self) -> Result<(u8, Zeroizing<Vec<u8>>), StpcError> {
+        let packet = messages::read_packet(&mut self.stream)?;
+        let blocks = TLVParser::unpack(&packet)?;
+        if blocks.len() != 3 {
+            return Err(StpcError::DeserilizateError("Record must have 3 fields".into()));
+        }
+        let mut blocks = blocks.into_iter();
+
+        let record_type = match blocks.next() {
+            Some((1, value)) if value.len() == 1 => value[0],
+            _ => return Err(StpcError::DeserilizateError("Tag record_type != 1".into())),
+        };
+        let sequence = match blocks.next() {
+            Some((2, value)) => u64::from_be_bytes(
+                value
+                    .try_into()
+                    .map_err(|_| StpcError::DeserilizateError("Invalid sequence length".into()))?,
+            ),
+            _ => return Err(StpcError::DeserilizateError("Tag sequence != 2".into())),
+        };
+        let ciphertext = match blocks.next() {
+            Some((3, value)) => value,
+            _ => return Err(StpcError::DeserilizateError("Tag ciphertext != 3".into())),
+        };
+        if ciphertext.len() > MAX_RECORD_SIZE + TAG_SIZE {
+            return Err(StpcError::RecordTooLargeError(ciphertext.len()));
+        }
+
+        let plaintext = self.reader.open(record_type, sequence, &ciphertext)?;
+        Ok((record_type, plaintext))
+    }
+}
+
+impl<S: Read + Write> Read for RecordStream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            match self.receive().map_err(io::Error::other)? {
+                Some(plaintext) => {
+                    self.buffer = plaintext;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let count = buf.len().min(self.buffer.len() - self.position);
+        buf[..count].copy_from_slice(&self.buffer[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+impl<S: Read + Write> Write for RecordStream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(MAX_RECORD_SIZE);
+        if count > 0 {
+            self.write_record(DATA, &buf[..count]).map_err(io::Error::other)?;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+// === HELPERS ===
+
+fn expand(secret: &[u8; 32], label: &[u8], output: &mut [u8]) {
+    let okm = hash::hkdf_expand(HashAlgorithm::Sha256, secret, label, output.len())
+        .expect("Record keys are a valid HKDF-SHA256 output length");
+    output.copy_from_slice(&okm);
+}
+
+/// Type and sequence number travel in clear and are authenticated instead
+fn associated_data(record_type: u8, sequence: u64) -> [u8; 9] {
+    let mut aad = [0u8; 9];
+    aad[0] = record_type;
+    aad[1..].copy_from_slice(&sequence.to_be_bytes());
+    aad
+}
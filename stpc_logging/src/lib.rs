@@ -17,7 +17,7 @@ pub enum LogLevel {
 }
 
 impl LogLevel {
-    fn to_color(&self) -> Color {
+    fn to_color(self) -> Color {
         match self {
             LogLevel::Debug => Color::Cyan,
             LogLevel::Info => Color::Green,
@@ -27,7 +27,7 @@ impl LogLevel {
         }
     }
 
-    fn to_string(&self) -> &'static str {
+    fn to_string(self) -> &'static str {
         match self {
             LogLevel::Debug => "DEBUG",
             LogLevel::Info => "INFO",
@@ -104,17 +104,16 @@ impl Logger {
     }
 
     fn rotate_file_if_needed(&mut self) {
-        if let Some(file) = &mut self.file_handle {
-            if let Ok(metadata) = file.metadata() {
-                if metadata.len() >= self.config.max_file_size {
-                    let rotated = self.config.file_path.with_extension("log.old");
-                    if let Err(e) = std::fs::rename(&self.config.file_path, rotated) {
-                        eprintln!("Failed to rotate log file: {}", e);
-                        return;
-                    }
-                    self.file_handle = Self::open_file(&self.config.file_path);
-                }
+        if let Some(file) = &mut self.file_handle
+            && let Ok(metadata) = file.metadata()
+            && metadata.len() >= self.config.max_file_size
+        {
+            let rotated = self.config.file_path.with_extension("log.old");
+            if let Err(e) = std::fs::rename(&self.config.file_path, rotated) {
+                eprintln!("Failed to rotate log file: {}", e);
+                return;
             }
+            self.file_handle = Self::open_file(&self.config.file_path);
         }
     }
 
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::{rngs::OsRng, RngCore};
+
+use stpc_certs::{CertSerializable, Certificate};
+use stpc_core::StpcError;
+use stpc_time::TimeManager;
+
+use crate::http;
+use crate::{CertId, CertStatus, OcspRequest, OcspResponse};
+
+// OcspClientConfig
+#[derive(Debug, Clone)]
+pub struct OcspClientConfig {
+    /// Deadline for a single attempt, from connecting to the last byte of the answer
+    pub timeout:        Duration,
+    /// Extra attempts after a network failure or a 5xx answer
+    pub retries:        u32,
+    pub retry_delay:    Duration,
+    /// Send a random nonce and require it to be echoed
+    pub use_nonce:      bool,
+    /// Tolerated clock difference to the responder, in seconds
+    pub max_clock_skew: u64,
+    /// Most responses kept in the cache, 0 disables caching
+    pub cache_size:     usize,
+}
+
+impl Default for OcspClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout:        Duration::from_secs(5),
+            retries:        2,
+            retry_delay:    Duration::from_millis(200),
+            use_nonce:      true,
+            max_clock_skew: 60,
+            cache_size:     1024,
+        }
+    }
+}
+
+/// Blocking OCSP client that queries the responder named in `TbsCertificate::ocsp_url`
+pub struct OcspClient {
+    config: OcspClientConfig,
+    time:   Arc<TimeManager>,
+    cache:  Mutex<HashMap<CertId, OcspResponse>>,
+}
+
+impl OcspClient {
+    pub fn new(config: OcspClientConfig, time: Arc<TimeManager>) -> Self {
+        Self {
+            config,
+            time,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Revocation status of `cert`, issued by `issuer`. Fresh cached responses are
+    /// reused, otherwise the responder is queried and its answer verified
+    pub fn check(&self, cert: &Certificate, issuer: &Certificate) -> Result<CertStatus, StpcError> {
+        let cert_id = CertId::new(cert, &issuer.tbs_certificate.subject_public_key)?;
+        let now = self.time.get_time();
+
+        if let Some(cached) = self.cache.lock().unwrap().get(&cert_id)
+            && self.is_fresh(cached, now)
+        {
+            return Ok(cached.response_data.cert_status);
+        }
+
+        let nonce = self.config.use_nonce.then(|| {
+            let mut nonce = vec![0u8; 16];
+            OsRng.fill_bytes(&mut nonce);
+            nonce
+        });
+        let request = OcspRequest::new(cert_id.clone(), nonce.clone());
+        let response = self.fetch(&cert.tbs_certificate.ocsp_url, &request)?;
+
+        let data = &response.response_data;
+        if !response.verify(issuer, now)? {
+            return Err(StpcError::SignatureVerifyError);
+        }
+        if data.cert_id != cert_id {
+            return Err(StpcError::OcspError("Response is for another certificate".into()));
+        }
+        if nonce.is_some() && data.nonce != nonce {
+            return Err(StpcError::OcspError("Response nonce does not match the request".into()));
+        }
+        if data.produced_at > now.saturating_add(self.config.max_clock_skew) {
+            return Err(StpcError::OcspError(format!(
+                "Response produced in the future: producedAt {}, now {}",
+                data.produced_at, now
+            )));
+        }
+        if data.produced_at < data.this_update {
+            return Err(StpcError::OcspError(format!(
+                "Response produced before its thisUpdate: producedAt {}, thisUpdate {}",
+                data.produced_at, data.this_update
+            )));
+        }
+        if !self.is_fresh(&response, now) {
+            return Err(StpcError::OcspError(format!(
+                "Response is not fresh: thisUpdate {}, nextUpdate {}, now {}",
+                data.this_update, data.next_update, now
+            )));
+        }
+
+        let status = data.cert_status;
+        self.remember(cert_id, response, now);
+        Ok(status)
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Cache `response`, dropping expired entries first and, if the cache is still
+    /// full, the entry that expires soonest
+    fn remember(&self, cert_id: CertId, response: OcspResponse, now: u64) {
+        if self.config.cache_size == 0 {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| self.is_fresh(cached, now));
+        if cache.len() >= self.config.cache_size
+            && !cache.contains_key(&cert_id)
+            && let Some(soonest) = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.response_data.next_update)
+                .map(|(id, _)| id.clone())
+        {
+            cache.remove(&soonest);
+        }
+        cache.insert(cert_id, response);
+    }
+
+    fn is_fresh(&self, response: &OcspResponse, now: u64) -> bool {
+        let data = &response.response_data;
+        data.this_update <= now.saturating_add(self.config.max_clock_skew) && now < data.next_update
+    }
+
+    fn fetch(&self, url: &str, request: &OcspRequest) -> Result<OcspResponse, StpcError> {
+        let (host, path) = parse_url(url)?;
+        let body = request.serialize()?;
+
+        let mut attempt = 0;
+        loop {
+            match self.post(host, path, &body) {
+                Ok(response) => return OcspResponse::deserialize(&response),
+                Err(StpcError::NetworkError(_)) if attempt < self.config.retries => {
+                    attempt += 1;
+                    thread::sleep(self.config.retry_delay);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn post(&self, host: &str, path: &str, body: &[u8]) -> Result<Vec<u8>, StpcError> {
+        let deadline = Instant::now() + self.config.timeout;
+        let address = socket_authority(host)
+            .to_socket_addrs()
+            .map_err(http::io_error)?
+            .next()
+            .ok_or_else(|| StpcError::NetworkError(format!("Cannot resolve {}", host)))?;
+
+        let remaining = remaining(deadline).map_err(http::io_error)?;
+        let stream = TcpStream::connect_timeout(&address, remaining).map_err(http::io_error)?;
+        let mut stream = DeadlineStream { stream, deadline };
+
+        http::write_request(&mut stream, host, path, body)?;
+        let response = http::read_message(&mut stream)?;
+
+        // A failing responder may recover, so server errors are retried like network ones
+        match response.start_line.split_whitespace().nth(1).unwrap_or("") {
+            "200" => {}
+            status if status.starts_with('5') => {
+                return Err(StpcError::NetworkError(format!("Responder answered {}", response.start_line)));
+            }
+            _ => return Err(StpcError::OcspError(format!("Responder answered {}", response.start_line))),
+        }
+
+        Ok(response.body)
+    }
+}
+
+// DeadlineStream
+/// TcpStream whose reads and writes share one deadline, so a responder that
+/// trickles bytes cannot keep an attempt alive past it
+struct DeadlineStream {
+    stream:   TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(remaining(self.deadline)?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(remaining(self.deadline)?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Time left until `deadline`, TimedOut once it has passed
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "OCSP request deadline exceeded"))
+}
+
+/// Split `http://host[:port][/path]` into `host[:port]` and `path`
+pub fn parse_url(url: &str) -> Result<(&str, &str), StpcError> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| StpcError::OcspError(format!("Unsupported OCSP URL: {}", url)))?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(StpcError::OcspError(format!("OCSP URL has no host: {}", url)));
+    }
+
+    Ok((authority, path))
+}
+
+/// `host[:port]` with port 80 filled in when there is none; the colons of a
+/// bracketed IPv6 literal such as `[::1]` are not a port
+pub fn socket_authority(authority: &str) -> String {
+    let after_host = match authority.rfind(']') {
+        Some(index) => &authority[index + 1..],
+        None => authority,
+    };
+    let has_port = after_host
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.bytes().all(|byte| byte.is_ascii_digit()));
+
+    if has_port {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    }
+}
@@ -0,0 +1,100 @@
+// Minimal HTTP/1.1 framing for OCSP over localhost, one POST per connection.
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use stpc_core::StpcError;
+
+pub(crate) const CONTENT_TYPE_REQUEST: &str = "application/ocsp-request";
+pub(crate) const CONTENT_TYPE_RESPONSE: &str = "application/ocsp-response";
+
+/// Upper bound for headers and bodies, OCSP messages are a few kilobytes
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+pub(crate) struct Message {
+    /// Request line or status line
+    pub start_line: String,
+    pub body:       Vec<u8>,
+}
+
+/// Read a start line, headers and a body whose length comes from Content-Length
+pub(crate) fn read_message<R: Read>(stream: R) -> Result<Message, StpcError> {
+    let mut reader = BufReader::new(stream);
+    let mut header_budget = MAX_MESSAGE_SIZE;
+    let mut content_length = 0;
+
+    let start_line = read_line(&mut reader, &mut header_budget)?;
+    loop {
+        let line = read_line(&mut reader, &mut header_budget)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value
+                .trim()
+                .parse()
+                .map_err(|_| StpcError::NetworkError("Invalid Content-Length".into()))?;
+        }
+    }
+
+    if content_length > MAX_MESSAGE_SIZE {
+        return Err(StpcError::NetworkError(format!("Body too large: {} bytes", content_length)));
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).map_err(io_error)?;
+
+    Ok(Message {
+        start_line: start_line.trim_end().to_string(),
+        body,
+    })
+}
+
+/// One line, newline included, of at most `budget` bytes; the bytes read come off the budget
+fn read_line<R: BufRead>(reader: &mut R, budget: &mut usize) -> Result<String, StpcError> {
+    let mut line = Vec::new();
+    let read = reader.by_ref().take(*budget as u64).read_until(b'\n', &mut line).map_err(io_error)?;
+    if !line.ends_with(b"\n") {
+        return Err(StpcError::NetworkError("Malformed HTTP headers".into()));
+    }
+    *budget -= read;
+
+    String::from_utf8(line).map_err(|_| StpcError::NetworkError("Malformed HTTP headers".into()))
+}
+
+pub(crate) fn write_request<W: Write>(
+    mut stream: W,
+    host: &str,
+    path: &str,
+    body: &[u8],
+) -> Result<(), StpcError> {
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        host,
+        CONTENT_TYPE_REQUEST,
+        body.len()
+    );
+
+    stream.write_all(head.as_bytes()).map_err(io_error)?;
+    stream.write_all(body).map_err(io_error)?;
+    stream.flush().map_err(io_error)
+}
+
+pub(crate) fn write_response<W: Write>(mut stream: W, status: &str, body: &[u8]) -> Result<(), StpcError> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        CONTENT_TYPE_RESPONSE,
+        body.len()
+    );
+
+    stream.write_all(head.as_bytes()).map_err(io_error)?;
+    stream.write_all(body).map_err(io_error)?;
+    stream.flush().map_err(io_error)
+}
+
+pub(crate) fn io_error(e: std::io::Error) -> StpcError {
+    StpcError::NetworkError(e.to_string())
+}
@@ -1,14 +1,372 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use stpc_certs::extensions::{ExtensionValue, KeyPurpose, EXTENDED_KEY_USAGE};
+use stpc_certs::{tlv_serializable, CertSerializable, Certificate, DistinguishedName};
+use stpc_core::{CertificateVersion, HashAlgorithm, PublicKey, SignatureAlgorithm, StpcError};
+use stpc_crypto::{PublicKeyExt, TypedPrivateKey, TypedSignature};
+use stpc_crl::RevocationReason;
+use stpc_encoding::{Framing, TLVParser, TLV, TlvDecode, TlvEncode};
+
+pub mod client;
+mod http;
+pub mod responder;
+
+// === TYPES ===
+
+// CertId
+#[derive(Debug, Clone, PartialEq, Eq, Hash, TlvEncode, TlvDecode)]
+pub struct CertId {
+    #[tlv(tag = 1)]
+    pub issuer_name_hash: [u8; 32],
+    #[tlv(tag = 2)]
+    pub issuer_key_hash:  [u8; 32],
+    #[tlv(tag = 3)]
+    pub serial_number:    [u8; 8],
+}
+
+// OcspRequest
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct OcspRequest {
+    #[tlv(tag = 1, with = "stpc_certs::tlv::version")]
+    pub version: CertificateVersion,
+    #[tlv(tag = 2)]
+    pub cert_id: CertId,
+    #[tlv(tag = 3, optional)]
+    pub nonce:   Option<Vec<u8>>,
+}
+
+// CertStatus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertStatus {
+    Good,
+    Revoked { revocation_time: u64, reason: RevocationReason },
+    Unknown,
+}
+
+// ResponderId
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponderId {
+    ByName(DistinguishedName),
+    ByKeyHash([u8; 32]),
+}
+
+// ResponseData
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct ResponseData {
+    #[tlv(tag = 1, with = "stpc_certs::tlv::version")]
+    pub version:             CertificateVersion,
+    #[tlv(tag = 2, with = "stpc_certs::tlv::signature_algorithm")]
+    pub signature_algorithm: SignatureAlgorithm,
+    #[tlv(tag = 3)]
+    pub responder_id:        ResponderId,
+    #[tlv(tag = 4)]
+    pub produced_at:         u64,
+    #[tlv(tag = 5)]
+    pub cert_id:             CertId,
+    #[tlv(tag = 6)]
+    pub cert_status:         CertStatus,
+    #[tlv(tag = 7)]
+    pub this_update:         u64,
+    #[tlv(tag = 8)]
+    pub next_update:         u64,
+    #[tlv(tag = 9, optional)]
+    pub nonce:               Option<Vec<u8>>,
+    /// Framing the data is signed in; set from the packet when decoded
+    #[tlv(framing)]
+    pub framing:             Framing,
+}
+
+// OcspResponse
+#[derive(Debug, Clone, TlvEncode, TlvDecode)]
+pub struct OcspResponse {
+    /// Signed part, accepted only in canonical encoding
+    #[tlv(tag = 1, with = "stpc_encoding::schema::canonical")]
+    pub response_data:         ResponseData,
+    #[tlv(tag = 2, with = "stpc_certs::tlv::signature_algorithm")]
+    pub signature_algorithm:   SignatureAlgorithm,
+    #[tlv(tag = 3)]
+    pub signature_value:       Vec<u8>,
+    /// Delegated responder certificate, issued by the CA with the OcspSigning purpose
+    #[tlv(tag = 4, optional, with = "stpc_certs::tlv::certificate")]
+    pub responder_certificate: Option<Certificate>,
+}
+
+// === HASHING ===
+
+/// SHA-256 over the serialized DistinguishedName
+pub fn name_hash(name: &DistinguishedName) -> Result<[u8; 32], StpcError> {
+    Ok(sha256(&name.serialize()?))
+}
+
+/// SHA-256 over the raw public key bytes, the key's `key_id`
+pub fn key_hash(public_key: &[u8]) -> [u8; 32] {
+    PublicKey::from_bytes(public_key)
+        .key_id()
+        .as_bytes()
+        .try_into()
+        .expect("SHA-256 digests are 32 bytes")
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    stpc_crypto::hash::digest(HashAlgorithm::Sha256, data)
+        .try_into()
+        .expect("SHA-256 digests are 32 bytes")
+}
+
+// === CERT ID ===
+
+impl CertId {
+    /// CertId of `cert`, issued by the holder of `issuer_public_key`
+    pub fn new(cert: &Certificate, issuer_public_key: &[u8]) -> Result<Self, StpcError> {
+        Ok(Self {
+            issuer_name_hash: name_hash(&cert.tbs_certificate.issuser)?,
+            issuer_key_hash:  key_hash(issuer_public_key),
+            serial_number:    cert.tbs_certificate.serial_number,
+        })
+    }
+
+    /// Whether this id names a certificate issued by `issuer`
+    pub fn is_issued_by(&self, issuer: &Certificate) -> Result<bool, StpcError> {
+        Ok(self.issuer_name_hash == name_hash(&issuer.tbs_certificate.subject)?
+            && self.issuer_key_hash == key_hash(&issuer.tbs_certificate.subject_public_key))
+    }
+}
+
+tlv_serializable!(CertId);
+
+// === OCSP REQUEST ===
+
+impl OcspRequest {
+    pub fn new(cert_id: CertId, nonce: Option<Vec<u8>>) -> Self {
+        Self {
+            version: CertificateVersion::V1,
+            cert_id,
+            nonce,
+        }
+    }
+}
+
+tlv_serializable!(OcspRequest);
+
+// === CERT STATUS ===
+
+impl TlvEncode for CertStatus {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        self.tlv_encode_as(Framing::V1)
+    }
+
+    fn tlv_encode_as(&self, framing: Framing) -> Result<Vec<u8>, StpcError> {
+        let temp: Vec<(u8, Vec<u8>)> = match self {
+            CertStatus::Good => vec![(1, vec![0])],
+            CertStatus::Revoked { revocation_time, reason } => vec![
+                (1, vec![1]),
+                (2, revocation_time.to_be_bytes().to_vec()),
+                (3, vec![reason.code()]),
+            ],
+            CertStatus::Unknown => vec![(1, vec![2])],
+        };
+
+        TLVParser::pack_owned(framing, &temp)
+    }
+}
+
+impl TlvDecode for CertStatus {
+    fn tlv_decode(data: &[u8]) -> Result<Self, StpcError> {
+        let blocks = TLVParser::unpack(data)?;
+
+        match blocks.as_slice() {
+            [(1, status)] if status == &[0] => Ok(CertStatus::Good),
+            [(1, status)] if status == &[2] => Ok(CertStatus::Unknown),
+            [(1, status), (2, time), (3, reason)] if status == &[1] && reason.len() == 1 => {
+                Ok(CertStatus::Revoked {
+                    revocation_time: u64::tlv_decode(time)?,
+                    reason: RevocationReason::from_code(reason[0])?,
+                })
+            }
+            _ => Err(StpcError::DeserilizateError("Invalid CertStatus".into())),
+        }
+    }
+}
+
+tlv_serializable!(CertStatus);
+
+// === RESPONDER ID ===
+
+impl ResponderId {
+    /// Whether `cert` is the responder this id names
+    pub fn matches(&self, cert: &Certificate) -> bool {
+        match self {
+            ResponderId::ByName(name) => name == &cert.tbs_certificate.subject,
+            ResponderId::ByKeyHash(hash) => hash == &key_hash(&cert.tbs_certificate.subject_public_key),
+        }
+    }
+}
+
+impl TlvEncode for ResponderId {
+    fn tlv_encode(&self) -> Result<Vec<u8>, StpcError> {
+        self.tlv_encode_as(Framing::V1)
+    }
+
+    fn tlv_encode_as(&self, framing: Framing) -> Result<Vec<u8>, StpcError> {
+        let temp: Vec<(u8, Vec<u8>)> = match self {
+            ResponderId::ByName(name) => vec![(1, name.serialize_as(framing)?)],
+            ResponderId::ByKeyHash(hash) => vec![(2, hash.to_vec())],
+        };
+
+        TLVParser::pack_owned(framing, &temp)
+    }
+}
+
+impl TlvDecode for ResponderId {
+    fn tlv_decode(data: &[u8]) -> Result<Self, StpcError> {
+        let [(tag, value)]: [(u8, Vec<u8>); 1] = TLVParser::unpack(data)?
+            .try_into()
+            .map_err(|_| StpcError::DeserilizateError("ResponderId must have 1 field".into()))?;
+
+        match tag {
+            1 => Ok(ResponderId::ByName(DistinguishedName::deserialize(&value)?)),
+            2 => Ok(ResponderId::ByKeyHash(<[u8; 32]>::tlv_decode(&value)?)),
+            _ => Err(StpcError::DeserilizateError("Unknown ResponderId tag".into())),
+        }
+    }
+}
+
+tlv_serializable!(ResponderId);
+
+// === RESPONSE DATA ===
+
+impl ResponseData {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        signature_algorithm: SignatureAlgorithm,
+        responder_id:        ResponderId,
+        produced_at:         u64,
+        cert_id:             CertId,
+        cert_status:         CertStatus,
+        this_update:         u64,
+        next_update:         u64,
+        nonce:               Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            version: CertificateVersion::V1,
+            signature_algorithm,
+            responder_id,
+            produced_at,
+            cert_id,
+            cert_status,
+            this_update,
+            next_update,
+            nonce,
+            framing: Framing::V1,
+        }
+    }
+
+    /// Framing to sign in; v2 gives a smaller response that v1-only clients cannot read
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Sign with the CA's key or a delegated responder's key, which must be of `signature_algorithm`
+    pub fn sign(self, private_key: &TypedPrivateKey) -> Result<OcspResponse, StpcError> {
+        let message = self.serialize()?;
+        let signature = private_key.sign_as(self.signature_algorithm, &message)?;
+        let signature_algorithm = self.signature_algorithm;
+
+        Ok(OcspResponse {
+            response_data: self,
+            signature_algorithm,
+            signature_value: signature.signature().as_bytes().to_vec(),
+            responder_certificate: None,
+        })
+    }
+}
+
+tlv_serializable!(ResponseData);
+
+// === OCSP RESPONSE ===
+
+impl OcspResponse {
+    /// Attach the delegated responder certificate that signed this response
+    pub fn with_responder_certificate(mut self, certificate: Certificate) -> Self {
+        self.responder_certificate = Some(certificate);
+        self
+    }
+
+    /// Verify the response for a certificate issued by `issuer`, at the verifier's time `now`.
+    /// Without a responder certificate the CA itself must have signed it; otherwise the
+    /// responder certificate must be issued by the CA, carry the OcspSigning purpose and
+    /// be valid both at `produced_at` and at `now`, since the signer picks `produced_at`
+    pub fn verify(&self, issuer: &Certificate, now: u64) -> Result<bool, StpcError> {
+        let data = &self.response_data;
+        if data.signature_algorithm != self.signature_algorithm {
+            return Err(StpcError::AlgorithmMismatchError(format!(
+                "Response data declares {:?}, response declares {:?}",
+                data.signature_algorithm, self.signature_algorithm
+            )));
+        }
+        if !data.cert_id.is_issued_by(issuer)? {
+            return Err(StpcError::OcspError("CertId does not belong to the issuer".into()));
+        }
+
+        let signer = match &self.responder_certificate {
+            None => issuer,
+            Some(responder) => {
+                check_delegated_responder(responder, issuer, data.produced_at, now)?;
+                responder
+            }
+        };
+        if !data.responder_id.matches(signer) {
+            return Err(StpcError::OcspError("ResponderId does not match the signer".into()));
+        }
+
+        let message = data.serialize()?;
+        let public_key = signer.tbs_certificate.subject_key_for(self.signature_algorithm)?;
+        let signature = TypedSignature::from_bytes(self.signature_algorithm, &self.signature_value)?;
+
+        public_key.verify(&message, &signature)
+    }
+}
+
+impl CertSerializable for OcspResponse {
+    /// In the framing of the response data
+    fn serialize(&self) -> Result<Vec<u8>, StpcError> {
+        self.tlv_encode_as(self.response_data.framing)
+    }
+
+    fn deserialize(data: &[u8]) -> Result<Self, StpcError> {
+        let response = Self::tlv_decode(data)?;
+        if Framing::detect(data)? != response.response_data.framing {
+            return Err(StpcError::NonCanonicalError("Response and its data use different framings".into()));
+        }
+        Ok(response)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// === HELPERS ===
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+fn check_delegated_responder(
+    responder: &Certificate,
+    issuer: &Certificate,
+    produced_at: u64,
+    now: u64,
+) -> Result<(), StpcError> {
+    if responder.tbs_certificate.issuser != issuer.tbs_certificate.subject {
+        return Err(StpcError::OcspError("Responder certificate is not issued by the CA".into()));
     }
+
+    let issuer_key = issuer.tbs_certificate.subject_key_for(responder.signature_algorithm)?;
+    if !responder.verify(&issuer_key)? {
+        return Err(StpcError::SignatureVerifyError);
+    }
+    responder.tbs_certificate.validity.check_validity(produced_at)?;
+    responder.tbs_certificate.validity.check_validity(now)?;
+
+    let ocsp_signing = matches!(
+        responder.tbs_certificate.extension(EXTENDED_KEY_USAGE).map(|ext| &ext.value),
+        Some(ExtensionValue::ExtendedKeyUsage(purposes)) if purposes.contains(&KeyPurpose::OcspSigning)
+    );
+    if !ocsp_signing {
+        return Err(StpcError::OcspError("Responder certificate lacks the OcspSigning purpose".into()));
+    }
+
+    Ok(())
 }
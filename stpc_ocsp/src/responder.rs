@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::fs;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use stpc_certs::{CertSerializable, Certificate};
+use stpc_core::StpcError;
+use stpc_crl::{CertificateRevocationList, RevocationReason, RevocationView, RevokedCertificate};
+use stpc_crypto::TypedPrivateKey;
+use stpc_encoding::Framing;
+use stpc_time::TimeManager;
+
+use crate::http;
+use crate::{key_hash, CertId, CertStatus, OcspRequest, OcspResponse, ResponderId, ResponseData};
+
+// === REVOCATION STORE ===
+
+/// Source of revocation state for a single CA
+pub trait RevocationStore: Send + Sync {
+    fn status(&self, serial_number: &[u8; 8]) -> Result<CertStatus, StpcError>;
+
+    /// Responses must not outlive the data they were built from
+    fn next_update(&self) -> Option<u64> {
+        None
+    }
+
+    /// Changes whenever the status of `serial_number` changes; cached responses
+    /// built under another revision are stale
+    fn revision(&self, _serial_number: &[u8; 8]) -> u64 {
+        0
+    }
+}
+
+fn status_from_entry(entry: Option<&RevokedCertificate>) -> CertStatus {
+    match entry {
+        Some(entry) => CertStatus::Revoked {
+            revocation_time: entry.revocation_time,
+            reason:          entry.reason,
+        },
+        None => CertStatus::Good,
+    }
+}
+
+impl RevocationStore for CertificateRevocationList {
+    fn status(&self, serial_number: &[u8; 8]) -> Result<CertStatus, StpcError> {
+        let entry = self
+            .tbs_cert_list
+            .revoked_certificates
+            .iter()
+            .find(|entry| &entry.serial_number == serial_number);
+
+        Ok(status_from_entry(entry))
+    }
+
+    fn next_update(&self) -> Option<u64> {
+        Some(self.tbs_cert_list.next_update)
+    }
+}
+
+impl RevocationStore for RevocationView {
+    fn status(&self, serial_number: &[u8; 8]) -> Result<CertStatus, StpcError> {
+        let entry = self.revoked.iter().find(|entry| &entry.serial_number == serial_number);
+
+        Ok(status_from_entry(entry))
+    }
+
+    fn next_update(&self) -> Option<u64> {
+        Some(self.next_update)
+    }
+}
+
+/// Shared store, e.g. a MemoryStore kept by the caller to revoke from while serving
+impl<S: RevocationStore + ?Sized> RevocationStore for Arc<S> {
+    fn status(&self, serial_number: &[u8; 8]) -> Result<CertStatus, StpcError> {
+        (**self).status(serial_number)
+    }
+
+    fn next_update(&self) -> Option<u64> {
+        (**self).next_update()
+    }
+
+    fn revision(&self, serial_number: &[u8; 8]) -> u64 {
+        (**self).revision(serial_number)
+    }
+}
+
+// MemoryStore: mutable revocation database
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    revoked:   RwLock<HashMap<[u8; 8], RevokedCertificate>>,
+    /// Number of revoke and unrevoke calls per serial number
+    revisions: RwLock<HashMap<[u8; 8], u64>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn revoke(&self, serial_number: [u8; 8], revocation_time: u64, reason: RevocationReason) {
+        let entry = RevokedCertificate::new(serial_number, revocation_time, reason);
+        self.revoked.write().unwrap().insert(serial_number, entry);
+        self.bump(&serial_number);
+    }
+
+    /// Lift a revocation, e.g. a released CertificateHold
+    pub fn unrevoke(&self, serial_number: &[u8; 8]) {
+        self.revoked.write().unwrap().remove(serial_number);
+        self.bump(serial_number);
+    }
+
+    fn bump(&self, serial_number: &[u8; 8]) {
+        *self.revisions.write().unwrap().entry(*serial_number).or_default() += 1;
+    }
+}
+
+impl RevocationStore for MemoryStore {
+    fn status(&self, serial_number: &[u8; 8]) -> Result<CertStatus, StpcError> {
+        Ok(status_from_entry(self.revoked.read().unwrap().get(serial_number)))
+    }
+
+    fn revision(&self, serial_number: &[u8; 8]) -> u64 {
+        self.revisions.read().unwrap().get(serial_number).copied().unwrap_or(0)
+    }
+}
+
+// CrlFileStore: serialized CRL on disk, reloaded when the file changes
+pub struct CrlFileStore {
+    path:   PathBuf,
+    issuer: Certificate,
+    loaded: Mutex<Option<(SystemTime, Arc<CertificateRevocationList>)>>,
+}
+
+impl CrlFileStore {
+    /// Open a CRL file whose signature must verify with `issuer`'s key
+    pub fn open(path: impl Into<PathBuf>, issuer: &Certificate) -> Result<Self, StpcError> {
+        let store = Self {
+            path:   path.into(),
+            issuer: issuer.clone(),
+            loaded: Mutex::new(None),
+        };
+
+        store.current()?;
+        Ok(store)
+    }
+
+    /// The verified CRL, read again only when the file's modification time changes
+    fn current(&self) -> Result<Arc<CertificateRevocationList>, StpcError> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .map_err(|e| StpcError::IoError(format!("{}: {}", self.path.display(), e)))?;
+
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some((time, crl)) = loaded.as_ref()
+            && *time == modified
+        {
+            return Ok(Arc::clone(crl));
+        }
+
+        let data = fs::read(&self.path)
+            .map_err(|e| StpcError::IoError(format!("{}: {}", self.path.display(), e)))?;
+        let crl = CertificateRevocationList::deserialize(&data)?;
+        if crl.is_delta() {
+            return Err(StpcError::CrlError("CRL file must hold a full CRL".into()));
+        }
+        let issuer_key = self.issuer.tbs_certificate.subject_key_for(crl.signature_algorithm)?;
+        if !crl.verify(&issuer_key)? {
+            return Err(StpcError::SignatureVerifyError);
+        }
+
+        let crl = Arc::new(crl);
+        *loaded = Some((modified, Arc::clone(&crl)));
+        Ok(crl)
+    }
+}
+
+impl RevocationStore for CrlFileStore {
+    fn status(&self, serial_number: &[u8; 8]) -> Result<CertStatus, StpcError> {
+        self.current()?.status(serial_number)
+    }
+
+    fn next_update(&self) -> Option<u64> {
+        self.current().ok().map(|crl| crl.tbs_cert_list.next_update)
+    }
+
+    /// The CRL number, so publishing a new CRL retires every cached response
+    fn revision(&self, _serial_number: &[u8; 8]) -> u64 {
+        self.current().map_or(0, |crl| crl.tbs_cert_list.crl_number)
+    }
+}
+
+// === RESPONDER ===
+
+pub struct OcspResponder {
+    issuer:                Certificate,
+    signing_key:           TypedPrivateKey,
+    signer_key_hash:       [u8; 32],
+    responder_certificate: Option<Certificate>,
+    store:                 Box<dyn RevocationStore>,
+    /// Seconds a response stays fresh when the store has no nextUpdate of its own
+    validity_period:       u64,
+    framing:               Framing,
+    /// Responses by the store revision they were built under
+    cache:                 Mutex<HashMap<CertId, (u64, OcspResponse)>>,
+}
+
+impl OcspResponder {
+    /// Responder that signs with the CA's own key
+    pub fn new(
+        issuer:          Certificate,
+        signing_key:     TypedPrivateKey,
+        store:           Box<dyn RevocationStore>,
+        validity_period: u64,
+    ) -> Self {
+        let signer_key_hash = key_hash(&issuer.tbs_certificate.subject_public_key);
+
+        Self {
+            issuer,
+            signing_key,
+            signer_key_hash,
+            responder_certificate: None,
+            store,
+            validity_period,
+            framing: Framing::V1,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sign with a delegated responder key; `signing_key` must belong to `certificate`
+    /// and is used with its own algorithm, which need not be the CA's
+    pub fn with_delegated_responder(mut self, certificate: Certificate, signing_key: TypedPrivateKey) -> Self {
+        self.signer_key_hash = key_hash(&certificate.tbs_certificate.subject_public_key);
+        self.responder_certificate = Some(certificate);
+        self.signing_key = signing_key;
+        self.cache.lock().unwrap().clear();
+        self
+    }
+
+    /// Sign responses in `framing`; v2 responses are smaller but need a v2-aware client
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self.cache.lock().unwrap().clear();
+        self
+    }
+
+    /// Answer `request` at time `now`. Requests without a nonce are served from the
+    /// cache until the cached response reaches its nextUpdate
+    pub fn respond(&self, request: &OcspRequest, now: u64) -> Result<OcspResponse, StpcError> {
+        if !request.cert_id.is_issued_by(&self.issuer)? {
+            return Err(StpcError::OcspError("Request is for a certificate of another issuer".into()));
+        }
+
+        let revision = self.store.revision(&request.cert_id.serial_number);
+        if request.nonce.is_none()
+            && let Some((cached_revision, cached)) = self.cache.lock().unwrap().get(&request.cert_id)
+            && *cached_revision == revision
+            && cached.response_data.this_update <= now
+            && now < cached.response_data.next_update
+        {
+            return Ok(cached.clone());
+        }
+
+        let response = self.sign_response(&request.cert_id, request.nonce.clone(), now)?;
+        if request.nonce.is_none() {
+            self.cache.lock().unwrap().insert(request.cert_id.clone(), (revision, response.clone()));
+        }
+
+        Ok(response)
+    }
+
+    /// Pre-sign responses for `cert_ids` so later requests are answered from the cache
+    pub fn presign(&self, cert_ids: &[CertId], now: u64) -> Result<(), StpcError> {
+        for cert_id in cert_ids {
+            let revision = self.store.revision(&cert_id.serial_number);
+            let response = self.sign_response(cert_id, None, now)?;
+            self.cache.lock().unwrap().insert(cert_id.clone(), (revision, response));
+        }
+
+        Ok(())
+    }
+
+    /// Drop cached responses, e.g. after a store without revisions has changed
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Byte-level interface: serialized `OcspRequest` in, serialized `OcspResponse` out
+    pub fn handle(&self, request: &[u8], now: u64) -> Result<Vec<u8>, StpcError> {
+        let request = OcspRequest::deserialize(request)?;
+        self.respond(&request, now)?.serialize()
+    }
+
+    fn sign_response(&self, cert_id: &CertId, nonce: Option<Vec<u8>>, now: u64) -> Result<OcspResponse, StpcError> {
+        let cert_status = self.store.status(&cert_id.serial_number)?;
+
+        let mut next_update = now.saturating_add(self.validity_period);
+        if let Some(store_next_update) = self.store.next_update() {
+            if store_next_update <= now {
+                return Err(StpcError::OcspError(format!(
+                    "Revocation data expired at {}, nothing to answer from",
+                    store_next_update
+                )));
+            }
+            next_update = next_update.min(store_next_update);
+        }
+
+        let response = ResponseData::new(
+            self.signing_key.algorithm(),
+            ResponderId::ByKeyHash(self.signer_key_hash),
+            now,
+            cert_id.clone(),
+            cert_status,
+            now,
+            next_update,
+            nonce,
+        )
+        .with_framing(self.framing)
+        .sign(&self.signing_key)?;
+
+        Ok(match &self.responder_certificate {
+            Some(certificate) => response.with_responder_certificate(certificate.clone()),
+            None => response,
+        })
+    }
+}
+
+// === HTTP SERVER ===
+
+/// Serves an `OcspResponder` over HTTP POST on a local socket
+pub struct OcspServer {
+    address:     SocketAddr,
+    stop_signal: Arc<AtomicBool>,
+    handle:      Option<JoinHandle<()>>,
+}
+
+impl OcspServer {
+    /// Bind to `address` (port 0 picks a free port) and serve on a background thread
+    pub fn start(
+        responder: Arc<OcspResponder>,
+        address:   impl ToSocketAddrs,
+        time:      Arc<TimeManager>,
+    ) -> Result<Self, StpcError> {
+        let listener = TcpListener::bind(address).map_err(http::io_error)?;
+        let address = listener.local_addr().map_err(http::io_error)?;
+        let stop_signal = Arc::new(AtomicBool::new(false));
+
+        let stop = Arc::clone(&stop_signal);
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = OcspServer::serve(&responder, stream, time.get_time());
+                }
+            }
+        });
+
+        Ok(Self {
+            address,
+            stop_signal,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// URL to put into `TbsCertificate::ocsp_url`
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.address)
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_signal.store(true, Ordering::SeqCst);
+
+        // wake the accept loop up so it sees the stop signal
+        let _ = TcpStream::connect_timeout(&self.address, Duration::from_secs(1));
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn serve(responder: &OcspResponder, stream: TcpStream, now: u64) -> Result<(), StpcError> {
+        stream.set_read_timeout(Some(Duration::from_secs(5))).map_err(http::io_error)?;
+
+        let request = match http::read_message(&stream) {
+            Ok(request) => request,
+            Err(_) => return http::write_response(&stream, "400 Bad Request", &[]),
+        };
+        if !request.start_line.starts_with("POST ") {
+            return http::write_response(&stream, "405 Method Not Allowed", &[]);
+        }
+
+        match responder.handle(&request.body, now) {
+            Ok(body) => http::write_response(&stream, "200 OK", &body),
+            Err(StpcError::DeserilizateError(_)) | Err(StpcError::InvalidPacketError(_)) => {
+                http::write_response(&stream, "400 Bad Request", &[])
+            }
+            Err(StpcError::OcspError(_)) => http::write_response(&stream, "403 Forbidden", &[]),
+            Err(_) => http::write_response(&stream, "500 Internal Server Error", &[]),
+        }
+    }
+}
+
+impl Drop for OcspServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -1,11 +1,17 @@
-use stpc_certs::{TbsCertificate, Validity, DistinguishedName, CertSerializable};
-use stpc_core::{CertificateVersion, SignatureAlgorithm, StpcError};
+use stpc_certs::{TbsCertificate, Certificate, Validity, DistinguishedName, CertSerializable};
+use stpc_core::{CertificateVersion, HashAlgorithm, SignatureAlgorithm, StpcError};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use stpc_crypto::{Ed25519, Falcon512, Falcon1024};
-    use stpc_core::SigningOperands;
+    use crate::fixtures::{cert, dn, keypair};
+    use stpc_crypto::{Ed25519, Falcon512, Falcon1024, PublicKeyExt, TypedPrivateKey, TypedPublicKey};
+    use stpc_core::{Key, SigningOperands};
+    use stpc_certs::path::{validate_path, LinkFailureReason, TrustAnchor};
+    use stpc_certs::extensions::{Extension, ExtensionValue, GeneralName, KeyPurpose, KeyUsage};
+    use stpc_certs::csr::{CertificationRequest, CertificationRequestInfo, IssuancePolicy};
+    use stpc_encoding::{TLVParser, TLV};
+    use std::net::IpAddr;
 
     fn test_algorithm<A: SigningOperands>() {
         // Генерация ключей
@@ -68,4 +74,667 @@ mod tests {
 
         Ok(())
     }
+
+    fn signed_certificate(algorithm: SignatureAlgorithm) -> (Certificate, TypedPublicKey) {
+        let (priv_key, pub_key) = keypair(algorithm);
+        let dn = DistinguishedName::new(
+            "CN".to_string(),
+            None, None, None, None, None, None
+        );
+        let tbs = TbsCertificate::new(
+            CertificateVersion::V1,
+            algorithm,
+            dn.clone(),
+            Validity::new(0, 1000),
+            dn,
+            pub_key.key().as_bytes().to_vec(),
+            "http://ocsp.example.com".to_string()
+        );
+
+        let cert = tbs.sign(&priv_key).expect("Certificate signing failed");
+        (cert, pub_key)
+    }
+
+    #[test]
+    fn test_cert_sign_verify() -> Result<(), StpcError> {
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519);
+        assert!(cert.verify(&pub_key)?);
+
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Falcon512);
+        assert!(cert.verify(&pub_key)?);
+
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Falcon1024);
+        assert!(cert.verify(&pub_key)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ml_dsa_certificates() -> Result<(), StpcError> {
+        let certificates = [
+            signed_certificate(SignatureAlgorithm::MlDsa44),
+            signed_certificate(SignatureAlgorithm::MlDsa65),
+            signed_certificate(SignatureAlgorithm::MlDsa87),
+        ];
+
+        for (cert, pub_key) in certificates {
+            let deserialized = Certificate::deserialize(&cert.serialize()?)?;
+            assert_eq!(deserialized.signature_algorithm, cert.signature_algorithm);
+            assert_eq!(deserialized.tbs_certificate.signature_algorithm, cert.tbs_certificate.signature_algorithm);
+            assert!(deserialized.verify(&pub_key)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slh_dsa_certificate() -> Result<(), StpcError> {
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::SlhDsaSha2_128s);
+
+        let deserialized = Certificate::deserialize(&cert.serialize()?)?;
+        assert_eq!(deserialized.signature_algorithm, SignatureAlgorithm::SlhDsaSha2_128s);
+        assert_eq!(deserialized.signature_value.len(), SignatureAlgorithm::SlhDsaSha2_128s.info().signature_size);
+        assert!(deserialized.verify(&pub_key)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_composite_certificate() -> Result<(), StpcError> {
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519Falcon512);
+
+        let deserialized = Certificate::deserialize(&cert.serialize()?)?;
+        assert_eq!(deserialized.signature_algorithm, SignatureAlgorithm::Ed25519Falcon512);
+        assert_eq!(deserialized.tbs_certificate.subject_public_key, pub_key.key().as_bytes());
+        assert!(deserialized.verify(&pub_key)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_fingerprint() -> Result<(), StpcError> {
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519);
+        let decoded = Certificate::deserialize(&cert.serialize()?)?;
+
+        for algorithm in HashAlgorithm::all() {
+            let fingerprint = cert.fingerprint(*algorithm)?;
+            assert_eq!(fingerprint.algorithm, *algorithm);
+            assert_eq!(fingerprint.as_bytes().len(), algorithm.output_size());
+            assert_eq!(fingerprint, decoded.fingerprint(*algorithm)?);
+        }
+
+        let fingerprint = cert.fingerprint(HashAlgorithm::Sha256)?;
+        assert_eq!(fingerprint.to_colon_hex().len(), 32 * 3 - 1);
+        assert_eq!(fingerprint.to_base32().len(), 52);
+
+        // Another certificate for the same key differs in serial number and signature
+        let (other, _) = signed_certificate(SignatureAlgorithm::Ed25519);
+        assert_ne!(fingerprint, other.fingerprint(HashAlgorithm::Sha256)?);
+
+        assert_eq!(cert.subject_key_id(), pub_key.key().key_id());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_verify_after_serialization() -> Result<(), StpcError> {
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519);
+
+        let deserialized = Certificate::deserialize(&cert.serialize()?)?;
+        assert!(deserialized.verify(&pub_key)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_tampered_tbs() {
+        let (mut cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519);
+        cert.tbs_certificate.ocsp_url = "http://evil.example.com".to_string();
+
+        assert!(cert.verify(&pub_key).is_err());
+    }
+
+    #[test]
+    fn test_cert_wrong_key() {
+        let (cert, _) = signed_certificate(SignatureAlgorithm::Ed25519);
+        let (_, other_key) = keypair(SignatureAlgorithm::Ed25519);
+
+        assert!(cert.verify(&other_key).is_err());
+    }
+
+    #[test]
+    fn test_cert_algorithm_mismatch() {
+        let (mut cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519);
+        cert.signature_algorithm = SignatureAlgorithm::Falcon512;
+
+        assert!(matches!(cert.verify(&pub_key), Err(StpcError::AlgorithmMismatchError(_))));
+
+        // Keys of another algorithm are refused before anything is signed or verified
+        let (_, falcon_pub) = keypair(SignatureAlgorithm::Falcon512);
+        let (cert, _) = signed_certificate(SignatureAlgorithm::Ed25519);
+        assert!(matches!(cert.verify(&falcon_pub), Err(StpcError::AlgorithmMismatchError(_))));
+
+        let (falcon_priv, _) = keypair(SignatureAlgorithm::Falcon512);
+        let tbs = cert.tbs_certificate.clone();
+        assert!(matches!(tbs.sign(&falcon_priv), Err(StpcError::AlgorithmMismatchError(_))));
+    }
+
+    /// Re-pack a TLV packet with its blocks in reverse order
+    fn reversed(packet: &[u8]) -> Vec<u8> {
+        let blocks = TLVParser::unpack(packet).expect("Unpack failed");
+        let blocks: Vec<(u8, &[u8])> = blocks.iter().rev().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        TLVParser::pack(&blocks).expect("Pack failed")
+    }
+
+    #[test]
+    fn test_cert_fields_any_order() -> Result<(), StpcError> {
+        let (cert, _) = signed_certificate(SignatureAlgorithm::Ed25519);
+        let tbs = reversed(&cert.tbs_certificate.serialize()?);
+
+        // Unsigned decoding looks fields up by tag
+        let decoded = TbsCertificate::deserialize(&tbs)?;
+        assert_eq!(decoded.serial_number, cert.tbs_certificate.serial_number);
+        assert_eq!(decoded.serialize()?, cert.tbs_certificate.serialize()?);
+
+        // A certificate only takes its canonical encoding
+        let encoded = TLVParser::pack(&[(1, &tbs), (2, &[1]), (3, &cert.signature_value)])?;
+        assert!(matches!(Certificate::deserialize(&encoded), Err(StpcError::NonCanonicalError(_))));
+        assert!(matches!(
+            Certificate::deserialize(&reversed(&cert.serialize()?)),
+            Err(StpcError::NonCanonicalError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_canonical_only() -> Result<(), StpcError> {
+        let (cert, pub_key) = signed_certificate(SignatureAlgorithm::Ed25519);
+        let encoded = cert.serialize()?;
+        assert!(TLVParser::is_canonical(&encoded));
+        assert!(Certificate::deserialize(&encoded)?.verify(&pub_key)?);
+
+        let tbs_blocks = TLVParser::unpack(&cert.tbs_certificate.serialize()?)?;
+        let with_tbs = |blocks: &[(u8, Vec<u8>)]| -> Result<Vec<u8>, StpcError> {
+            let blocks: Vec<(u8, &[u8])> = blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+            let tbs = TLVParser::pack(&blocks)?;
+            TLVParser::pack(&[(1, &tbs), (2, &[1]), (3, &cert.signature_value)])
+        };
+
+        // An empty extensions list decodes to the same certificate as no list at all
+        let mut empty_extensions = tbs_blocks.clone();
+        empty_extensions.push((9, TLVParser::pack(&[])?));
+        assert!(matches!(
+            Certificate::deserialize(&with_tbs(&empty_extensions)?),
+            Err(StpcError::NonCanonicalError(_))
+        ));
+
+        // Nested values count too
+        let issuer = DistinguishedName::new("CN".into(), Some("Org".into()), None, None, None, None, None);
+        let mut reordered_issuer = tbs_blocks.clone();
+        reordered_issuer[3].1 = reversed(&issuer.serialize()?);
+        assert!(matches!(
+            Certificate::deserialize(&with_tbs(&reordered_issuer)?),
+            Err(StpcError::NonCanonicalError(_))
+        ));
+
+        assert!(Certificate::deserialize(&with_tbs(&tbs_blocks)?).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_v2_framing() -> Result<(), StpcError> {
+        use stpc_encoding::Framing;
+
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let dn = DistinguishedName::new("CN".into(), Some("Org".into()), None, None, None, None, None);
+        let tbs = TbsCertificate::new(
+            CertificateVersion::V1,
+            SignatureAlgorithm::Ed25519,
+            dn.clone(),
+            Validity::new(0, 1000),
+            dn,
+            pub_key.key().as_bytes().to_vec(),
+            "http://ocsp.example.com".to_string(),
+        )
+        .with_extensions(vec![Extension::new(true, ExtensionValue::BasicConstraints { ca: true, path_len: Some(1) })]);
+
+        let v1 = tbs.clone().sign(&priv_key)?.serialize()?;
+        let v2 = tbs.with_framing(Framing::V2).sign(&priv_key)?.serialize()?;
+        assert!(v2.len() + 50 < v1.len());
+
+        // Both framings decode, keep their framing and verify
+        for encoded in [&v1, &v2] {
+            let cert = Certificate::deserialize(encoded)?;
+            assert_eq!(cert.tbs_certificate.framing, Framing::detect(encoded)?);
+            assert_eq!(&cert.serialize()?, encoded);
+            assert!(cert.verify(&pub_key)?);
+        }
+
+        // A v2 TBS inside a v1 certificate has another encoding than the one signed
+        let blocks = TLVParser::unpack(&v2)?;
+        let blocks: Vec<(u8, &[u8])> = blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        let mixed = TLVParser::pack(&blocks)?;
+        assert!(matches!(Certificate::deserialize(&mixed), Err(StpcError::NonCanonicalError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_strict_decoding() -> Result<(), StpcError> {
+        let (cert, _) = signed_certificate(SignatureAlgorithm::Ed25519);
+        let encoded = cert.serialize()?;
+        let blocks = TLVParser::unpack(&encoded)?;
+        let pack = |extra: &[(u8, &[u8])]| {
+            let mut all: Vec<(u8, &[u8])> = blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+            all.extend_from_slice(extra);
+            TLVParser::pack(&all)
+        };
+
+        assert!(matches!(Certificate::deserialize(&pack(&[(4, b"extra")])?), Err(StpcError::UnexpectedTagError(4))));
+        assert!(matches!(Certificate::deserialize(&pack(&[(2, &[1])])?), Err(StpcError::DuplicateTagError(2))));
+        assert!(matches!(
+            Certificate::deserialize(&TLVParser::pack(&[(1, &blocks[0].1), (2, &[1])])?),
+            Err(StpcError::MissingTagError { tag: 3, .. })
+        ));
+
+        let trailing = [encoded.as_slice(), &[0, 0]].concat();
+        assert!(matches!(Certificate::deserialize(&trailing), Err(StpcError::TrailingBytesError(2))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cert_malformed_input_never_panics() -> Result<(), StpcError> {
+        let (cert, _) = signed_certificate(SignatureAlgorithm::Ed25519);
+        let encoded = cert.serialize()?;
+
+        for length in 0..encoded.len() {
+            assert!(Certificate::deserialize(&encoded[..length]).is_err());
+        }
+        for position in 0..encoded.len() {
+            let mut corrupted = encoded.clone();
+            corrupted[position] ^= 0xff;
+            let _ = Certificate::deserialize(&corrupted);
+        }
+
+        // Every field present but empty
+        let empty: Vec<(u8, &[u8])> = (1..=9).map(|tag| (tag, &[][..])).collect();
+        assert!(TbsCertificate::deserialize(&TLVParser::pack(&empty)?).is_err());
+        Ok(())
+    }
+
+    // root -> intermediate -> leaf
+    fn build_chain(intermediate_validity: Validity) -> (Certificate, Certificate, Certificate) {
+        let (root_priv, root_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (int_priv, int_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+
+        let root = cert("Root", "Root").ca(None).sign(&root_pub, &root_priv);
+        let intermediate = cert("Intermediate", "Root")
+            .ca(None)
+            .validity(intermediate_validity)
+            .sign(&int_pub, &root_priv);
+        let leaf = cert("Leaf", "Intermediate").sign(&leaf_pub, &int_priv);
+
+        (root, intermediate, leaf)
+    }
+
+    #[test]
+    fn test_path_valid() {
+        let (root, intermediate, leaf) = build_chain(Validity::new(0, 1000));
+
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::new(root)], 500);
+        assert!(report.is_valid(), "{:?}", report.failure);
+        assert_eq!(report.chain.len(), 2);
+    }
+
+    #[test]
+    fn test_path_missing_intermediate() {
+        let (root, _, leaf) = build_chain(Validity::new(0, 1000));
+
+        let report = validate_path(&leaf, &[], &[TrustAnchor::new(root)], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 0);
+        assert_eq!(failure.issuer, dn("Intermediate"));
+        assert_eq!(failure.reason, LinkFailureReason::IssuerNotFound);
+    }
+
+    #[test]
+    fn test_path_expired_intermediate() {
+        let (root, intermediate, leaf) = build_chain(Validity::new(0, 100));
+
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::new(root)], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 1);
+        assert_eq!(failure.subject, dn("Intermediate"));
+        assert!(matches!(failure.reason, LinkFailureReason::NotValidAt { time: 500, .. }));
+    }
+
+    #[test]
+    fn test_path_wrong_anchor_key() {
+        let (_, intermediate, leaf) = build_chain(Validity::new(0, 1000));
+        let (other_priv, other_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let impostor = cert("Root", "Root").ca(None).sign(&other_pub, &other_priv);
+
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::new(impostor)], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 1);
+        assert_eq!(failure.reason, LinkFailureReason::SignatureInvalid);
+    }
+
+    #[test]
+    fn test_path_issuer_key_algorithm() {
+        let (root_priv, root_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (int_priv, int_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+
+        let root = cert("Root", "Root").ca(None).sign(&root_pub, &root_priv);
+        let leaf = cert("Leaf", "Intermediate").sign(&leaf_pub, &int_priv);
+
+        let declared = cert("Intermediate", "Root")
+            .ca(None)
+            .key_algorithm(SignatureAlgorithm::Ed25519)
+            .sign(&int_pub, &root_priv);
+        let report = validate_path(&leaf, &[declared], &[TrustAnchor::new(root.clone())], 500);
+        assert!(report.is_valid(), "{:?}", report.failure);
+
+        // the leaf names Ed25519, the issuer declares its key as ML-DSA-44
+        let other = cert("Intermediate", "Root")
+            .ca(None)
+            .key_algorithm(SignatureAlgorithm::MlDsa44)
+            .sign(&int_pub, &root_priv);
+        let report = validate_path(&leaf, &[other], &[TrustAnchor::new(root)], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 0);
+        assert_eq!(failure.reason, LinkFailureReason::AlgorithmMismatch);
+    }
+
+    #[test]
+    fn test_extensions_serialization() -> Result<(), StpcError> {
+        let extensions = vec![
+            Extension::new(true, ExtensionValue::BasicConstraints { ca: true, path_len: Some(1) }),
+            Extension::new(true, ExtensionValue::KeyUsage(KeyUsage(KeyUsage::KEY_CERT_SIGN | KeyUsage::CRL_SIGN))),
+            Extension::new(false, ExtensionValue::ExtendedKeyUsage(vec![KeyPurpose::ServerAuth, KeyPurpose::Other(42)])),
+            Extension::new(false, ExtensionValue::SubjectAltName(vec![
+                GeneralName::Dns("example.com".to_string()),
+                GeneralName::Ip("127.0.0.1".parse::<IpAddr>().unwrap()),
+                GeneralName::Ip("::1".parse::<IpAddr>().unwrap()),
+                GeneralName::Email("admin@example.com".to_string()),
+                GeneralName::Uri("https://example.com".to_string()),
+            ])),
+            Extension::new(false, ExtensionValue::SubjectKeyId(vec![1, 2, 3])),
+            Extension::new(false, ExtensionValue::AuthorityKeyId(vec![4, 5, 6])),
+            Extension::new(false, ExtensionValue::Unknown { id: 200, value: vec![7, 8, 9] }),
+        ];
+        let tbs = TbsCertificate::new(
+            CertificateVersion::V1,
+            SignatureAlgorithm::Ed25519,
+            dn("CN"),
+            Validity::new(0, 1000),
+            dn("CN"),
+            vec![1, 2, 3, 4],
+            "http://ocsp.example.com".to_string()
+        )
+        .with_extensions(extensions.clone());
+
+        let encoded = tbs.serialize()?;
+        let deserialized = TbsCertificate::deserialize_canonical(&encoded)?;
+        assert_eq!(deserialized.extensions, extensions);
+        assert_eq!(deserialized.basic_constraints(), Some((true, Some(1))));
+
+        // The extension list and the names inside SubjectAltName are canonical lists
+        let list = TLVParser::fields(&encoded)?.required(9, "extensions")?;
+        assert!(TLVParser::is_canonical(&list));
+        let names = TLVParser::fields(&extensions[3].serialize()?)?.required(3, "value")?;
+        assert!(TLVParser::is_canonical(&names));
+
+        // Each extension id at most once
+        let twice = tbs.with_extensions(vec![extensions[0].clone(), extensions[0].clone()]);
+        assert!(TbsCertificate::deserialize(&twice.serialize()?).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_unknown_critical_extension() {
+        let (root_priv, root_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let root = cert("Root", "Root").ca(None).sign(&root_pub, &root_priv);
+        let leaf = cert("Leaf", "Root")
+            .extension(true, ExtensionValue::Unknown { id: 200, value: Vec::new() })
+            .sign(&leaf_pub, &root_priv);
+
+        let report = validate_path(&leaf, &[], &[TrustAnchor::new(root)], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 0);
+        assert_eq!(failure.reason, LinkFailureReason::UnhandledCriticalExtension(200));
+    }
+
+    #[test]
+    fn test_path_issuer_constraints() {
+        let (root_priv, root_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (int_priv, int_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+
+        // root allows no intermediate CAs below it
+        let root = cert("Root", "Root").ca(Some(0)).sign(&root_pub, &root_priv);
+        let intermediate = cert("Intermediate", "Root").ca(None).sign(&int_pub, &root_priv);
+        let leaf = cert("Leaf", "Intermediate").sign(&leaf_pub, &int_priv);
+
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::new(root)], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 2);
+        assert_eq!(failure.reason, LinkFailureReason::PathLenExceeded { path_len: 0 });
+
+        // intermediate is an end-entity certificate
+        let root = cert("Root", "Root").ca(None).sign(&root_pub, &root_priv);
+        let intermediate = cert("Intermediate", "Root")
+            .extension(true, ExtensionValue::BasicConstraints { ca: false, path_len: None })
+            .sign(&int_pub, &root_priv);
+
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::new(root.clone())], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 1);
+        assert_eq!(failure.reason, LinkFailureReason::NotCa);
+
+        // CA chain within the path length is accepted
+        let intermediate = cert("Intermediate", "Root").ca(Some(0)).sign(&int_pub, &root_priv);
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::new(root)], 500);
+        assert!(report.is_valid(), "{:?}", report.failure);
+    }
+
+    #[test]
+    fn test_path_backtracks_over_issuers() {
+        let (root_priv, root_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (old_priv, old_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (int_priv, int_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+
+        let root = cert("Root", "Root").ca(None).sign(&root_pub, &root_priv);
+        let leaf = cert("Leaf", "Intermediate").sign(&leaf_pub, &int_priv);
+
+        // the same intermediate key, cross-signed by an untrusted CA, expired, and issued by the root
+        let cross_signed = cert("Intermediate", "Old Root").ca(None).sign(&int_pub, &old_priv);
+        let expired = cert("Intermediate", "Root").ca(None).validity(Validity::new(0, 100)).sign(&int_pub, &root_priv);
+        let current = cert("Intermediate", "Root").ca(None).sign(&int_pub, &root_priv);
+        let old_root = cert("Old Root", "Old Root").ca(None).sign(&old_pub, &old_priv);
+
+        let pool = [cross_signed, expired.clone(), current.clone()];
+        let report = validate_path(&leaf, &pool, &[TrustAnchor::new(root.clone())], 500);
+        assert!(report.is_valid(), "{:?}", report.failure);
+        assert_eq!(report.chain[1].tbs_certificate.serial_number, current.tbs_certificate.serial_number);
+
+        // without a good candidate the deepest failure is reported
+        let report = validate_path(&leaf, &[expired], &[TrustAnchor::new(root.clone())], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 1);
+        assert!(matches!(failure.reason, LinkFailureReason::NotValidAt { .. }));
+        assert_eq!(report.chain.len(), 1);
+
+        // a re-keyed root shares its name with the old one
+        let rekeyed = cert("Old Root", "Old Root").ca(None).sign(&root_pub, &root_priv);
+        let report = validate_path(&leaf, &pool[..1], &[TrustAnchor::new(rekeyed), TrustAnchor::new(old_root)], 500);
+        assert!(report.is_valid(), "{:?}", report.failure);
+    }
+
+    #[test]
+    fn test_path_issuer_without_basic_constraints() {
+        let (root_priv, root_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (int_priv, int_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+
+        // an end-entity certificate without extensions cannot sign for others
+        let root = cert("Root", "Root").ca(None).sign(&root_pub, &root_priv);
+        let end_entity = cert("Intermediate", "Root").sign(&int_pub, &root_priv);
+        let leaf = cert("Leaf", "Intermediate").sign(&leaf_pub, &int_priv);
+
+        let report = validate_path(&leaf, std::slice::from_ref(&end_entity), &[TrustAnchor::new(root.clone())], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 1);
+        assert_eq!(failure.reason, LinkFailureReason::NotCa);
+
+        // nor does the legacy opt-in of the root extend to it
+        let report = validate_path(&leaf, &[end_entity], &[TrustAnchor::legacy(root)], 500);
+        assert_eq!(report.failure.expect("Path should fail").reason, LinkFailureReason::NotCa);
+
+        // a root from before extensions is only trusted as a CA when marked legacy
+        let old_root = cert("Root", "Root").sign(&root_pub, &root_priv);
+        let intermediate = cert("Intermediate", "Root").ca(None).sign(&int_pub, &root_priv);
+
+        let report = validate_path(&leaf, std::slice::from_ref(&intermediate), &[TrustAnchor::new(old_root.clone())], 500);
+        let failure = report.failure.expect("Path should fail");
+        assert_eq!(failure.depth, 2);
+        assert_eq!(failure.reason, LinkFailureReason::NotCa);
+
+        let report = validate_path(&leaf, &[intermediate], &[TrustAnchor::legacy(old_root)], 500);
+        assert!(report.is_valid(), "{:?}", report.failure);
+    }
+
+    fn issuance_policy(allow_ca: bool) -> IssuancePolicy {
+        IssuancePolicy {
+            signature_algorithm: SignatureAlgorithm::Ed25519,
+            validity_period: 1000,
+            ocsp_url: "http://ocsp.example.com".to_string(),
+            allowed_algorithms: vec![SignatureAlgorithm::Ed25519, SignatureAlgorithm::Falcon512],
+            allow_ca,
+            allow_ocsp_signing: false,
+        }
+    }
+
+    #[test]
+    fn test_csr_serialization_and_pop() -> Result<(), StpcError> {
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Falcon512)?;
+        let csr = CertificationRequestInfo::new(
+            dn("Subject"),
+            SignatureAlgorithm::Falcon512,
+            pub_key.key().as_bytes().to_vec(),
+            vec![Extension::new(false, ExtensionValue::SubjectAltName(vec![GeneralName::Dns("example.com".to_string())]))],
+        )
+        .sign(&priv_key)?;
+
+        let deserialized = CertificationRequest::deserialize(&csr.serialize()?)?;
+        assert!(deserialized.verify()?);
+        assert_eq!(deserialized.request_info.extensions, csr.request_info.extensions);
+
+        let mut tampered = deserialized;
+        tampered.request_info.subject = dn("Someone else");
+        assert!(tampered.verify().is_err());
+        assert!(tampered.to_tbs_certificate(dn("Root"), 0, &issuance_policy(true)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_csr_issuance() -> Result<(), StpcError> {
+        let (ca_priv, ca_pub) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let (subject_priv, subject_pub) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let root = cert("Root", "Root").ca(None).validity(Validity::new(0, 10_000)).sign(&ca_pub, &ca_priv);
+
+        let csr = CertificationRequestInfo::new(
+            dn("Subject"),
+            SignatureAlgorithm::Ed25519,
+            subject_pub.key().as_bytes().to_vec(),
+            vec![Extension::new(false, ExtensionValue::SubjectKeyId(vec![1, 2, 3]))],
+        )
+        .sign(&subject_priv)?;
+
+        let tbs = csr.to_tbs_certificate(dn("Root"), 100, &issuance_policy(false))?;
+        assert_eq!(tbs.validity.not_after, 1100);
+        assert_eq!(tbs.subject_public_key, subject_pub.key().as_bytes());
+        assert_eq!(tbs.basic_constraints(), Some((false, None)));
+        assert!(tbs.extension(stpc_certs::extensions::SUBJECT_KEY_ID).is_none());
+        assert_eq!(tbs.subject_key()?.algorithm(), SignatureAlgorithm::Ed25519);
+
+        let issued = tbs.sign(&ca_priv)?;
+        assert!(validate_path(&issued, &[], &[TrustAnchor::new(root.clone())], 500).is_valid());
+
+        // the issued certificate cannot act as an issuer itself
+        let (_, other_pub) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let below = cert("Other", "Subject").sign(&other_pub, &subject_priv);
+        let report = validate_path(&below, &[issued], &[TrustAnchor::new(root)], 500);
+        assert_eq!(report.failure.expect("Path should fail").reason, LinkFailureReason::NotCa);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_csr_policy_violations() -> Result<(), StpcError> {
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let ca_request = CertificationRequestInfo::new(
+            dn("Subject"),
+            SignatureAlgorithm::Ed25519,
+            pub_key.key().as_bytes().to_vec(),
+            vec![Extension::new(true, ExtensionValue::BasicConstraints { ca: true, path_len: None })],
+        )
+        .sign(&priv_key)?;
+
+        assert!(matches!(
+            ca_request.to_tbs_certificate(dn("Root"), 0, &issuance_policy(false)),
+            Err(StpcError::IssuancePolicyError(_))
+        ));
+        assert!(ca_request.to_tbs_certificate(dn("Root"), 0, &issuance_policy(true)).is_ok());
+
+        let request = |extension: Extension| {
+            CertificationRequestInfo::new(dn("Subject"), SignatureAlgorithm::Ed25519, pub_key.key().as_bytes().to_vec(), vec![extension])
+                .sign(&priv_key)
+        };
+        let cert_sign = request(Extension::new(true, ExtensionValue::KeyUsage(KeyUsage(KeyUsage::KEY_CERT_SIGN))))?;
+        assert!(matches!(
+            cert_sign.to_tbs_certificate(dn("Root"), 0, &issuance_policy(false)),
+            Err(StpcError::IssuancePolicyError(_))
+        ));
+        assert!(cert_sign.to_tbs_certificate(dn("Root"), 0, &issuance_policy(true)).is_ok());
+
+        let ocsp_signing = request(Extension::new(false, ExtensionValue::ExtendedKeyUsage(vec![
+            KeyPurpose::ServerAuth,
+            KeyPurpose::OcspSigning,
+        ])))?;
+        assert!(matches!(
+            ocsp_signing.to_tbs_certificate(dn("Root"), 0, &issuance_policy(true)),
+            Err(StpcError::IssuancePolicyError(_))
+        ));
+        let policy = IssuancePolicy { allow_ocsp_signing: true, ..issuance_policy(false) };
+        assert!(ocsp_signing.to_tbs_certificate(dn("Root"), 0, &policy).is_ok());
+
+        let unknown = request(Extension::new(true, ExtensionValue::Unknown { id: 200, value: Vec::new() }))?;
+        assert!(matches!(
+            unknown.to_tbs_certificate(dn("Root"), 0, &issuance_policy(true)),
+            Err(StpcError::IssuancePolicyError(_))
+        ));
+        let unknown = request(Extension::new(false, ExtensionValue::Unknown { id: 200, value: Vec::new() }))?;
+        assert!(unknown.to_tbs_certificate(dn("Root"), 0, &issuance_policy(false)).is_ok());
+
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Falcon1024)?;
+        let falcon_request = CertificationRequestInfo::new(
+            dn("Subject"),
+            SignatureAlgorithm::Falcon1024,
+            pub_key.key().as_bytes().to_vec(),
+            Vec::new(),
+        )
+        .sign(&priv_key)?;
+
+        assert!(matches!(
+            falcon_request.to_tbs_certificate(dn("Root"), 0, &issuance_policy(true)),
+            Err(StpcError::IssuancePolicyError(_))
+        ));
+
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use stpc_core::{PrivateKey, PublicKey};
+use stpc_core::{Fingerprint, HashAlgorithm, PrivateKey, PublicKey};
 use stpc_core::Key;
 
 #[cfg(test)]
@@ -16,4 +16,32 @@ mod tests {
         let pk = PublicKey::from_bytes(&[1u8; 32]);
         assert_eq!(pk.as_bytes().len(), 32);
     }
+
+    #[test]
+    fn test_fingerprint_rendering() {
+        let fingerprint = Fingerprint::new(HashAlgorithm::Sha256, vec![0x00, 0x0a, 0xff]);
+
+        assert_eq!(fingerprint.to_colon_hex(), "00:0A:FF");
+        assert_eq!(fingerprint.to_string(), "SHA-256 00:0A:FF");
+        assert_eq!(Fingerprint::new(HashAlgorithm::Sha256, Vec::new()).to_colon_hex(), "");
+    }
+
+    // RFC 4648, section 10 (without padding)
+    #[test]
+    fn test_fingerprint_base32() {
+        let vectors = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+
+        for (input, expected) in vectors {
+            let fingerprint = Fingerprint::new(HashAlgorithm::Sha256, input.as_bytes().to_vec());
+            assert_eq!(fingerprint.to_base32(), expected);
+        }
+    }
 }
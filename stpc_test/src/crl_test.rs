@@ -0,0 +1,197 @@
+use stpc_certs::CertSerializable;
+use stpc_core::{SignatureAlgorithm, StpcError};
+use stpc_crl::{CertificateRevocationList, RevocationReason, RevocationView, RevokedCertificate, TbsCertList};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cert, dn, throwaway_key};
+    use stpc_crypto::TypedPrivateKey;
+
+    #[test]
+    fn test_crl_sign_verify_serialization() -> Result<(), StpcError> {
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Falcon512)?;
+        let crl = TbsCertList::new(
+            SignatureAlgorithm::Falcon512,
+            dn("CA"),
+            100,
+            200,
+            vec![
+                RevokedCertificate::new([1; 8], 150, RevocationReason::KeyCompromise),
+                RevokedCertificate::new([2; 8], 160, RevocationReason::AaCompromise),
+            ],
+            1,
+        )
+        .sign(&priv_key)?;
+
+        let deserialized = CertificateRevocationList::deserialize(&crl.serialize()?)?;
+        assert!(deserialized.verify(&pub_key)?);
+        assert_eq!(deserialized.tbs_cert_list.revoked_certificates, crl.tbs_cert_list.revoked_certificates);
+        assert!(deserialized.is_current(150));
+        assert!(!deserialized.is_current(201));
+
+        let mut tampered = deserialized;
+        tampered.tbs_cert_list.revoked_certificates.pop();
+        assert!(tampered.verify(&pub_key).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crl_canonical_only() -> Result<(), StpcError> {
+        use stpc_encoding::{TLVParser, TLV};
+
+        let (priv_key, _) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let crl = TbsCertList::new(SignatureAlgorithm::Ed25519, dn("CA"), 100, 200, Vec::new(), 1).sign(&priv_key)?;
+
+        let mut tbs = TLVParser::unpack(&crl.tbs_cert_list.serialize()?)?;
+        tbs.reverse();
+        let tbs: Vec<(u8, &[u8])> = tbs.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        let encoded = TLVParser::pack(&[
+            (1, &TLVParser::pack(&tbs)?),
+            (2, &[1]),
+            (3, &crl.signature_value),
+        ])?;
+
+        assert!(matches!(CertificateRevocationList::deserialize(&encoded), Err(StpcError::NonCanonicalError(_))));
+        assert!(CertificateRevocationList::deserialize(&crl.serialize()?).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_crl_v2_framing() -> Result<(), StpcError> {
+        use stpc_encoding::Framing;
+
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let revoked: Vec<RevokedCertificate> =
+            (0..20).map(|i| RevokedCertificate::new([i; 8], 150, RevocationReason::KeyCompromise)).collect();
+        let tbs = TbsCertList::new(SignatureAlgorithm::Ed25519, dn("CA"), 100, 200, revoked, 1);
+
+        let v1 = tbs.clone().sign(&priv_key)?.serialize()?;
+        let v2 = tbs.with_framing(Framing::V2).sign(&priv_key)?.serialize()?;
+        assert!(v2.len() * 10 < v1.len() * 7);
+
+        let crl = CertificateRevocationList::deserialize(&v2)?;
+        assert_eq!(crl.tbs_cert_list.framing, Framing::V2);
+        assert_eq!(crl.serialize()?, v2);
+        assert!(crl.verify(&pub_key)?);
+        assert!(CertificateRevocationList::deserialize(&v1)?.verify(&pub_key)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_crl_is_revoked() -> Result<(), StpcError> {
+        let (priv_key, _) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let revoked = cert("Leaf", "CA").sign(&throwaway_key(), &priv_key);
+        let good = cert("Leaf", "CA").sign(&throwaway_key(), &priv_key);
+        let foreign = cert("Leaf", "Other CA").sign(&throwaway_key(), &priv_key);
+
+        let crl = TbsCertList::new(
+            SignatureAlgorithm::Ed25519,
+            dn("CA"),
+            100,
+            200,
+            vec![RevokedCertificate::new(revoked.tbs_certificate.serial_number, 150, RevocationReason::Superseded)],
+            1,
+        )
+        .sign(&priv_key)?;
+
+        assert!(crl.is_revoked(&revoked));
+        assert_eq!(crl.revoked_entry(&revoked).map(|e| e.reason), Some(RevocationReason::Superseded));
+        assert!(!crl.is_revoked(&good));
+
+        let mut foreign = foreign;
+        foreign.tbs_certificate.serial_number = revoked.tbs_certificate.serial_number;
+        assert!(!crl.is_revoked(&foreign), "CRL must only apply to its issuer's certificates");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crl_rejects_truncated() {
+        assert!(CertificateRevocationList::deserialize(&[0u8; 4]).is_err());
+        assert!(RevocationReason::from_code(7).is_err());
+    }
+
+    #[test]
+    fn test_delta_crl_merge() -> Result<(), StpcError> {
+        let (priv_key, _) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let first = cert("Leaf", "CA").sign(&throwaway_key(), &priv_key);
+        let second = cert("Leaf", "CA").sign(&throwaway_key(), &priv_key);
+        let third = cert("Leaf", "CA").sign(&throwaway_key(), &priv_key);
+
+        let base = TbsCertList::new(
+            SignatureAlgorithm::Ed25519,
+            dn("CA"),
+            100,
+            200,
+            vec![
+                RevokedCertificate::new(first.tbs_certificate.serial_number, 90, RevocationReason::CertificateHold),
+                RevokedCertificate::new(second.tbs_certificate.serial_number, 95, RevocationReason::KeyCompromise),
+            ],
+            5,
+        )
+        .sign(&priv_key)?;
+
+        let delta = TbsCertList::new(
+            SignatureAlgorithm::Ed25519,
+            dn("CA"),
+            150,
+            160,
+            vec![
+                RevokedCertificate::new(first.tbs_certificate.serial_number, 150, RevocationReason::RemoveFromCrl),
+                RevokedCertificate::new(third.tbs_certificate.serial_number, 140, RevocationReason::Superseded),
+            ],
+            6,
+        )
+        .with_delta_base(5)
+        .sign(&priv_key)?;
+
+        let delta = CertificateRevocationList::deserialize(&delta.serialize()?)?;
+        assert!(delta.is_delta());
+        assert_eq!(delta.tbs_cert_list.base_crl_number, Some(5));
+
+        let view = base.merge(&delta)?;
+        assert!(!view.is_revoked(&first), "Hold released by delta");
+        assert!(view.is_revoked(&second));
+        assert!(view.is_revoked(&third));
+        assert_eq!(view.crl_number, 6);
+        assert_eq!(view.next_update, 160);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delta_crl_rejections() -> Result<(), StpcError> {
+        let (priv_key, _) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let list = |issuer: &str, crl_number| {
+            TbsCertList::new(SignatureAlgorithm::Ed25519, dn(issuer), 100, 200, Vec::new(), crl_number)
+        };
+
+        let base = list("CA", 5).sign(&priv_key)?;
+
+        // delta built against a newer base than the one we hold
+        let stale = list("CA", 8).with_delta_base(7).sign(&priv_key)?;
+        assert!(matches!(base.merge(&stale), Err(StpcError::CrlError(_))));
+
+        // delta from another issuer
+        let foreign = list("Other CA", 6).with_delta_base(5).sign(&priv_key)?;
+        assert!(matches!(base.merge(&foreign), Err(StpcError::CrlError(_))));
+
+        // delta numbered before the base
+        let old = list("CA", 4).with_delta_base(3).sign(&priv_key)?;
+        assert!(matches!(base.merge(&old), Err(StpcError::CrlError(_))));
+
+        // full CRLs in the wrong role
+        let full = list("CA", 6).sign(&priv_key)?;
+        assert!(matches!(base.merge(&full), Err(StpcError::CrlError(_))));
+        let delta = list("CA", 6).with_delta_base(5).sign(&priv_key)?;
+        assert!(RevocationView::from_base(&delta).is_err());
+
+        // a delta against an older base still applies
+        let mut view = base.merge(&list("CA", 6).with_delta_base(4).sign(&priv_key)?)?;
+        assert!(view.apply_delta(&delta).is_err(), "Same delta number must not be applied twice");
+
+        Ok(())
+    }
+}
\ No newline at end of file
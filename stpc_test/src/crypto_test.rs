@@ -1,5 +1,8 @@
-use stpc_core::{SigningOperands};
-use stpc_crypto::{Ed25519, Falcon512, Falcon1024};
+use stpc_core::{Ciphertext, KemAlgorithm, Key, KeyEncapsulation, PrivateKey, PublicKey, Signature, SignatureAlgorithm, SigningOperands, StpcError};
+use stpc_crypto::{TypedPrivateKey, TypedPublicKey, TypedSignature};
+use stpc_crypto::{Ed25519, Ed25519Falcon512, Falcon512, Falcon1024, MlDsa44, MlDsa65, MlDsa87, SlhDsaSha2_128s, SlhDsaShake256s};
+use stpc_crypto::composite::{CompositePublicKey, CompositeSignature};
+use stpc_crypto::kem::{MlKem768, X25519, X25519MlKem768};
 
 #[cfg(test)]
 mod tests {
@@ -38,4 +41,465 @@ mod tests {
     fn test_falcon1024() {
         test_algorithm::<Falcon1024>();
     }
+
+    #[test]
+    fn test_ml_dsa44() {
+        test_algorithm::<MlDsa44>();
+    }
+
+    #[test]
+    fn test_ml_dsa65() {
+        test_algorithm::<MlDsa65>();
+    }
+
+    #[test]
+    fn test_ml_dsa87() {
+        test_algorithm::<MlDsa87>();
+    }
+
+    // Known-answer vectors: public keys derived from the seed 00 01 .. 1f, taken from
+    // the IETF LAMPS ML-DSA certificate examples (draft-ietf-lamps-dilithium-certificates)
+    const ML_DSA_SEED: [u8; 32] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+    ];
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        let hex: String = hex.split_whitespace().collect();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Invalid hex"))
+            .collect()
+    }
+
+    #[test]
+    fn test_ml_dsa44_known_answer() {
+        let (priv_key, pub_key) = MlDsa44::keypair_from_seed(&ML_DSA_SEED);
+        assert_eq!(priv_key.as_bytes(), &ML_DSA_SEED);
+        assert_eq!(pub_key.as_bytes(), from_hex(include_str!("../vectors/ml_dsa_44.pk")).as_slice());
+    }
+
+    #[test]
+    fn test_ml_dsa65_known_answer() {
+        let (_, pub_key) = MlDsa65::keypair_from_seed(&ML_DSA_SEED);
+        assert_eq!(pub_key.as_bytes(), from_hex(include_str!("../vectors/ml_dsa_65.pk")).as_slice());
+    }
+
+    #[test]
+    fn test_ml_dsa87_known_answer() {
+        let (_, pub_key) = MlDsa87::keypair_from_seed(&ML_DSA_SEED);
+        assert_eq!(pub_key.as_bytes(), from_hex(include_str!("../vectors/ml_dsa_87.pk")).as_slice());
+    }
+
+    #[test]
+    fn test_ml_dsa_deterministic_signature() {
+        let (priv_key, pub_key) = MlDsa44::keypair_from_seed(&ML_DSA_SEED);
+
+        let first = MlDsa44::sign(b"Hello, STPC!", &priv_key).expect("Signing failed");
+        let second = MlDsa44::sign(b"Hello, STPC!", &priv_key).expect("Signing failed");
+        assert_eq!(first.as_bytes(), second.as_bytes());
+        assert_eq!(first.as_bytes().len(), 2420);
+        assert!(MlDsa44::verify(b"Hello, STPC!", &pub_key, &first).expect("Verification failed"));
+    }
+
+    #[test]
+    fn test_ml_dsa_wrong_parameter_set() {
+        let (priv_key, pub_key) = MlDsa44::keypair_from_seed(&ML_DSA_SEED);
+        let signature = MlDsa44::sign(b"Hello, STPC!", &priv_key).expect("Signing failed");
+
+        assert!(MlDsa65::verify(b"Hello, STPC!", &pub_key, &signature).is_err());
+    }
+
+    #[test]
+    fn test_slh_dsa_sha2_128s() {
+        test_algorithm::<SlhDsaSha2_128s>();
+    }
+
+    #[test]
+    fn test_slh_dsa_shake_256s() {
+        test_algorithm::<SlhDsaShake256s>();
+    }
+
+    // NIST ACVP SLH-DSA-keyGen-FIPS205, SLH-DSA-SHA2-128s, tcId 1
+    #[test]
+    fn test_slh_dsa_sha2_128s_known_answer() {
+        let (priv_key, pub_key) = SlhDsaSha2_128s::keypair_from_seed(
+            &from_hex("2F896D61D9CD9038CA303394FADAA22A"),
+            &from_hex("24AC5EC1D86A989CA2196C3C8632419C"),
+            &from_hex("1A05A42FE300E87B16AEE116CB2E2363"),
+        )
+        .expect("Keypair generation failed");
+
+        assert_eq!(
+            pub_key.as_bytes(),
+            from_hex("1A05A42FE300E87B16AEE116CB2E236358E2C3E62632C9DE03D08A535A0EB7E7").as_slice()
+        );
+        assert_eq!(
+            priv_key.as_bytes(),
+            from_hex(
+                "2F896D61D9CD9038CA303394FADAA22A24AC5EC1D86A989CA2196C3C8632419C\
+                 1A05A42FE300E87B16AEE116CB2E236358E2C3E62632C9DE03D08A535A0EB7E7"
+            )
+            .as_slice()
+        );
+    }
+
+    #[test]
+    fn test_slh_dsa_seed_length() {
+        assert!(SlhDsaSha2_128s::keypair_from_seed(&[0u8; 32], &[0u8; 32], &[0u8; 32]).is_err());
+        assert!(SlhDsaShake256s::keypair_from_seed(&[0u8; 32], &[0u8; 32], &[0u8; 32]).is_ok());
+    }
+
+    #[test]
+    fn test_algorithm_info_matches_keys() {
+        for &algorithm in SignatureAlgorithm::all() {
+            let info = algorithm.info();
+            let (priv_key, pub_key) = stpc_crypto::keypair(algorithm).expect("Keypair generation failed");
+            let signature = stpc_crypto::sign(algorithm, b"Hello, STPC!", &priv_key).expect("Signing failed");
+
+            assert_eq!(pub_key.as_bytes().len(), info.public_key_size, "{}", info.name);
+            assert_eq!(priv_key.as_bytes().len(), info.private_key_size, "{}", info.name);
+            assert!(signature.as_bytes().len() <= info.signature_size, "{}", info.name);
+            assert_eq!(info.is_post_quantum(), algorithm != SignatureAlgorithm::Ed25519);
+        }
+    }
+
+    #[test]
+    fn test_composite() {
+        test_algorithm::<Ed25519Falcon512>();
+    }
+
+    #[test]
+    fn test_composite_requires_both_components() {
+        let (priv_key, pub_key) = Ed25519Falcon512::keypair().expect("Keypair generation failed");
+        let signature = Ed25519Falcon512::sign(b"Hello, STPC!", &priv_key).expect("Signing failed");
+        let components = CompositeSignature::decode(&signature).expect("Invalid composite signature");
+
+        // Replace one component with a valid signature from another key
+        let (other_ed, _) = Ed25519::keypair().expect("Keypair generation failed");
+        let (other_falcon, _) = Falcon512::keypair().expect("Keypair generation failed");
+        let forged_classical = CompositeSignature::new(
+            Ed25519::sign(b"Hello, STPC!", &other_ed).expect("Signing failed"),
+            Signature::from_bytes(components.post_quantum.as_bytes()),
+        );
+        let forged_post_quantum = CompositeSignature::new(
+            Signature::from_bytes(components.classical.as_bytes()),
+            Falcon512::sign(b"Hello, STPC!", &other_falcon).expect("Signing failed"),
+        );
+
+        for forged in [forged_classical, forged_post_quantum] {
+            let verified = Ed25519Falcon512::verify(b"Hello, STPC!", &pub_key, &forged.encode()).unwrap_or(false);
+            assert!(!verified, "Both components must verify");
+        }
+    }
+
+    #[test]
+    fn test_composite_component_not_usable_alone() {
+        let (priv_key, pub_key) = Ed25519Falcon512::keypair().expect("Keypair generation failed");
+        let signature = Ed25519Falcon512::sign(b"Hello, STPC!", &priv_key).expect("Signing failed");
+
+        let keys = CompositePublicKey::decode(&pub_key).expect("Invalid composite public key");
+        let components = CompositeSignature::decode(&signature).expect("Invalid composite signature");
+
+        assert!(Ed25519::verify(b"Hello, STPC!", &keys.classical, &components.classical).is_err());
+        assert!(Falcon512::verify(b"Hello, STPC!", &keys.post_quantum, &components.post_quantum).is_err());
+    }
+
+    #[test]
+    fn test_composite_truncated() {
+        let (priv_key, pub_key) = Ed25519Falcon512::keypair().expect("Keypair generation failed");
+        let signature = Ed25519Falcon512::sign(b"Hello, STPC!", &priv_key).expect("Signing failed");
+
+        let truncated = Signature::from_bytes(&signature.as_bytes()[..64]);
+        assert!(Ed25519Falcon512::verify(b"Hello, STPC!", &pub_key, &truncated).is_err());
+        assert!(CompositePublicKey::decode(&PublicKey::from_bytes(&[0u8; 32])).is_err());
+    }
+
+    fn test_dispatch(algorithm: SignatureAlgorithm) {
+        let (priv_key, pub_key) = stpc_crypto::keypair(algorithm).expect("Keypair generation failed");
+
+        let message = b"Hello, STPC!";
+        let signature = stpc_crypto::sign(algorithm, message, &priv_key).expect("Signing failed");
+
+        let verified = stpc_crypto::verify(algorithm, message, &pub_key, &signature).expect("Verification failed");
+        assert!(verified, "Signature should be valid");
+
+        let verified = stpc_crypto::verify(algorithm, b"Hello, STPC?", &pub_key, &signature).unwrap_or(false);
+        assert!(!verified, "Tampered message should not verify");
+    }
+
+    #[test]
+    fn test_dispatch_ed25519() {
+        test_dispatch(SignatureAlgorithm::Ed25519);
+    }
+
+    #[test]
+    fn test_dispatch_falcon512() {
+        test_dispatch(SignatureAlgorithm::Falcon512);
+    }
+
+    #[test]
+    fn test_dispatch_falcon1024() {
+        test_dispatch(SignatureAlgorithm::Falcon1024);
+    }
+
+    #[test]
+    fn test_dispatch_ml_dsa() {
+        test_dispatch(SignatureAlgorithm::MlDsa44);
+        test_dispatch(SignatureAlgorithm::MlDsa65);
+        test_dispatch(SignatureAlgorithm::MlDsa87);
+    }
+
+    #[test]
+    fn test_dispatch_slh_dsa() {
+        test_dispatch(SignatureAlgorithm::SlhDsaSha2_128s);
+    }
+
+    #[test]
+    fn test_dispatch_composite() {
+        test_dispatch(SignatureAlgorithm::Ed25519Falcon512);
+    }
+
+    #[test]
+    fn test_dispatch_wrong_algorithm() {
+        let (priv_key, _) = stpc_crypto::keypair(SignatureAlgorithm::Falcon512).expect("Keypair generation failed");
+        assert!(stpc_crypto::sign(SignatureAlgorithm::Ed25519, b"Hello, STPC!", &priv_key).is_err());
+    }
+
+    #[test]
+    fn test_wrong_key_is_invalid_key() {
+        let (priv_key, pub_key) = Falcon512::keypair().expect("Keypair generation failed");
+        let (_, ed_pub) = Ed25519::keypair().expect("Keypair generation failed");
+        let signature = Signature::from_bytes(&[0u8; 64]);
+
+        assert!(matches!(Ed25519::sign(b"message", &priv_key), Err(StpcError::InvalidKeyError(_))));
+        assert!(matches!(Ed25519::verify(b"message", &pub_key, &signature), Err(StpcError::InvalidKeyError(_))));
+        assert!(matches!(Falcon512::verify(b"message", &ed_pub, &signature), Err(StpcError::InvalidKeyError(_))));
+        assert!(matches!(
+            stpc_crypto::sign(SignatureAlgorithm::Falcon1024, b"message", &priv_key),
+            Err(StpcError::InvalidKeyError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_falcon_headers() {
+        let (priv_key, pub_key) = Falcon512::keypair().expect("Keypair generation failed");
+        let signature = Falcon512::sign(b"message", &priv_key).expect("Signing failed");
+        assert_eq!(priv_key.as_bytes()[0], 0x59);
+        assert_eq!(pub_key.as_bytes()[0], 0x09);
+        assert_eq!(signature.as_bytes()[0], 0x39);
+
+        let (priv_key, pub_key) = Falcon1024::keypair().expect("Keypair generation failed");
+        let signature = Falcon1024::sign(b"message", &priv_key).expect("Signing failed");
+        assert_eq!(priv_key.as_bytes()[0], 0x5a);
+        assert_eq!(pub_key.as_bytes()[0], 0x0a);
+        assert_eq!(signature.as_bytes()[0], 0x3a);
+
+        // Right size, wrong header
+        let mut forged = pub_key.as_bytes().to_vec();
+        forged[0] = 0x09;
+        assert!(matches!(
+            stpc_crypto::validate_public_key(SignatureAlgorithm::Falcon1024, &forged),
+            Err(StpcError::InvalidKeyError(_))
+        ));
+    }
+
+    #[test]
+    fn test_typed_keys() {
+        for &algorithm in &[SignatureAlgorithm::Ed25519, SignatureAlgorithm::Falcon512, SignatureAlgorithm::MlDsa44] {
+            let (priv_key, pub_key) = TypedPrivateKey::generate(algorithm).expect("Keypair generation failed");
+            assert_eq!(priv_key.algorithm(), algorithm);
+
+            let signature = priv_key.sign(b"Hello, STPC!").expect("Signing failed");
+            assert_eq!(signature.algorithm(), algorithm);
+            assert!(pub_key.verify(b"Hello, STPC!", &signature).expect("Verification failed"));
+
+            // Round trip through the raw encodings
+            let pub_key = TypedPublicKey::from_bytes(algorithm, pub_key.key().as_bytes()).expect("Public key rejected");
+            let signature = TypedSignature::from_bytes(algorithm, signature.signature().as_bytes()).expect("Signature rejected");
+            assert!(pub_key.verify(b"Hello, STPC!", &signature).expect("Verification failed"));
+            TypedPrivateKey::from_bytes(algorithm, priv_key.key().as_bytes()).expect("Private key rejected");
+        }
+    }
+
+    #[test]
+    fn test_typed_keys_reject_wrong_size() {
+        let (priv_key, pub_key) = Falcon512::keypair().expect("Keypair generation failed");
+
+        assert!(matches!(
+            TypedPrivateKey::new(SignatureAlgorithm::Ed25519, priv_key),
+            Err(StpcError::InvalidKeyError(_))
+        ));
+        assert!(matches!(
+            TypedPublicKey::new(SignatureAlgorithm::MlDsa65, pub_key),
+            Err(StpcError::InvalidKeyError(_))
+        ));
+        assert!(TypedSignature::from_bytes(SignatureAlgorithm::Ed25519, &[0u8; 63]).is_err());
+        assert!(TypedSignature::from_bytes(SignatureAlgorithm::Falcon512, &[]).is_err());
+        assert!(TypedPrivateKey::new(SignatureAlgorithm::SlhDsaSha2_128s, PrivateKey::from_bytes(&[0u8; 32])).is_err());
+    }
+
+    #[test]
+    fn test_typed_keys_algorithm_mismatch() {
+        let (ed_priv, ed_pub) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519).expect("Keypair generation failed");
+        let (falcon_priv, _) = TypedPrivateKey::generate(SignatureAlgorithm::Falcon512).expect("Keypair generation failed");
+
+        let signature = falcon_priv.sign(b"message").expect("Signing failed");
+        assert!(matches!(ed_pub.verify(b"message", &signature), Err(StpcError::AlgorithmMismatchError(_))));
+
+        assert!(matches!(ed_priv.as_falcon512(), Err(StpcError::AlgorithmMismatchError(_))));
+        assert!(matches!(ed_pub.as_composite(), Err(StpcError::AlgorithmMismatchError(_))));
+        assert!(matches!(falcon_priv.as_ed25519(), Err(StpcError::AlgorithmMismatchError(_))));
+        assert!(matches!(ed_priv.as_ml_dsa44(), Err(StpcError::AlgorithmMismatchError(_))));
+        assert!(matches!(ed_pub.as_slh_dsa_sha2_128s(), Err(StpcError::AlgorithmMismatchError(_))));
+
+        // A key only signs for structures that declare its own algorithm
+        assert!(matches!(
+            ed_priv.sign_as(SignatureAlgorithm::Falcon512, b"message"),
+            Err(StpcError::AlgorithmMismatchError(_))
+        ));
+        assert!(ed_priv.sign_as(SignatureAlgorithm::Ed25519, b"message").is_ok());
+    }
+
+    #[test]
+    fn test_typed_key_views() {
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519).expect("Keypair generation failed");
+        let signing = priv_key.as_ed25519().expect("No Ed25519 view");
+        assert_eq!(signing.verifying_key(), pub_key.as_ed25519().expect("No Ed25519 view"));
+
+        let (_, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::Falcon512).expect("Keypair generation failed");
+        pub_key.as_falcon512().expect("No Falcon-512 view");
+
+        let (priv_key, pub_key) =
+            TypedPrivateKey::generate(SignatureAlgorithm::Ed25519Falcon512).expect("Keypair generation failed");
+        let composite = pub_key.as_composite().expect("No composite view");
+        assert_eq!(composite.classical.as_bytes().len(), 32);
+        priv_key.as_composite().expect("No composite view");
+
+        let signature = priv_key.sign(b"message").expect("Signing failed");
+        let parts = signature.as_composite().expect("No composite view");
+        assert_eq!(parts.classical.as_bytes().len(), 64);
+
+        let (priv_key, pub_key) = TypedPrivateKey::generate(SignatureAlgorithm::MlDsa65).expect("Keypair generation failed");
+        priv_key.as_ml_dsa65().expect("No ML-DSA-65 view");
+        let verifying = pub_key.as_ml_dsa65().expect("No ML-DSA-65 view");
+        assert_eq!(verifying.encode().as_slice(), pub_key.key().as_bytes());
+        let signature = priv_key.sign(b"message").expect("Signing failed");
+        assert_eq!(signature.as_ml_dsa65().expect("No ML-DSA-65 view").encode().as_slice(), signature.signature().as_bytes());
+
+        let (priv_key, pub_key) =
+            TypedPrivateKey::generate(SignatureAlgorithm::SlhDsaSha2_128s).expect("Keypair generation failed");
+        let signing = priv_key.as_slh_dsa_sha2_128s().expect("No SLH-DSA view");
+        assert_eq!(signing.as_ref(), &pub_key.as_slh_dsa_sha2_128s().expect("No SLH-DSA view"));
+        let signature = priv_key.sign(b"message").expect("Signing failed");
+        signature.as_slh_dsa_sha2_128s().expect("No SLH-DSA view");
+        assert!(matches!(signature.as_slh_dsa_shake256s(), Err(StpcError::AlgorithmMismatchError(_))));
+    }
+
+    #[test]
+    fn test_validate_composite() {
+        let (priv_key, pub_key) = Ed25519Falcon512::keypair().expect("Keypair generation failed");
+        let algorithm = SignatureAlgorithm::Ed25519Falcon512;
+        stpc_crypto::validate_public_key(algorithm, pub_key.as_bytes()).expect("Public key rejected");
+        stpc_crypto::validate_private_key(algorithm, priv_key.as_bytes()).expect("Private key rejected");
+
+        // Falcon-1024 header in the post-quantum half
+        let mut forged = pub_key.as_bytes().to_vec();
+        forged[32] = 0x0a;
+        assert!(stpc_crypto::validate_public_key(algorithm, &forged).is_err());
+    }
+
+    fn test_kem<K: KeyEncapsulation>() {
+        let (priv_key, pub_key) = K::keypair().expect("Keypair generation failed");
+
+        let (sender_secret, ciphertext) = K::encapsulate(&pub_key).expect("Encapsulation failed");
+        let receiver_secret = K::decapsulate(&priv_key, &ciphertext).expect("Decapsulation failed");
+        assert_eq!(sender_secret.as_bytes(), receiver_secret.as_bytes());
+        assert_eq!(sender_secret.as_bytes().len(), 32);
+
+        // Another encapsulation to the same key yields another secret
+        let (other_secret, other_ciphertext) = K::encapsulate(&pub_key).expect("Encapsulation failed");
+        assert_ne!(other_secret.as_bytes(), sender_secret.as_bytes());
+        assert_ne!(other_ciphertext, ciphertext);
+
+        // A different private key does not recover the secret
+        let (wrong_key, _) = K::keypair().expect("Keypair generation failed");
+        if let Ok(wrong_secret) = K::decapsulate(&wrong_key, &ciphertext) {
+            assert_ne!(wrong_secret.as_bytes(), sender_secret.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_kem_x25519() {
+        test_kem::<X25519>();
+    }
+
+    #[test]
+    fn test_kem_ml_kem768() {
+        test_kem::<MlKem768>();
+    }
+
+    #[test]
+    fn test_kem_hybrid() {
+        test_kem::<X25519MlKem768>();
+    }
+
+    #[test]
+    fn test_kem_dispatch() {
+        for algorithm in [KemAlgorithm::X25519, KemAlgorithm::MlKem768, KemAlgorithm::X25519MlKem768] {
+            let (priv_key, pub_key) = stpc_crypto::kem::keypair(algorithm).expect("Keypair generation failed");
+            let (secret, ciphertext) = stpc_crypto::kem::encapsulate(algorithm, &pub_key).expect("Encapsulation failed");
+            let recovered = stpc_crypto::kem::decapsulate(algorithm, &priv_key, &ciphertext).expect("Decapsulation failed");
+            assert_eq!(secret.as_bytes(), recovered.as_bytes());
+        }
+    }
+
+    // RFC 7748, section 6.1 (Alice)
+    #[test]
+    fn test_x25519_known_answer() {
+        let secret: [u8; 32] = from_hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a")
+            .try_into()
+            .expect("Invalid vector");
+        let (_, pub_key) = X25519::keypair_from_secret(&secret);
+
+        assert_eq!(
+            pub_key.as_bytes(),
+            from_hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a").as_slice()
+        );
+    }
+
+    // Public key derived from the seed 00 01 .. 3f, taken from the IETF LAMPS
+    // ML-KEM certificate examples (draft-ietf-lamps-kyber-certificates)
+    #[test]
+    fn test_ml_kem768_known_answer() {
+        let seed: [u8; 64] = std::array::from_fn(|i| i as u8);
+        let (priv_key, pub_key) = MlKem768::keypair_from_seed(&seed);
+
+        assert_eq!(priv_key.as_bytes(), &seed);
+        assert_eq!(pub_key.as_bytes(), from_hex(include_str!("../vectors/ml_kem_768.pk")).as_slice());
+    }
+
+    #[test]
+    fn test_kem_rejects_low_order_point() {
+        let (priv_key, _) = X25519::keypair().expect("Keypair generation failed");
+
+        assert!(X25519::encapsulate(&PublicKey::from_bytes(&[0u8; 32])).is_err());
+        assert!(X25519::decapsulate(&priv_key, &Ciphertext::from_bytes(&[0u8; 32])).is_err());
+    }
+
+    #[test]
+    fn test_kem_hybrid_tampered_ciphertext() {
+        let (priv_key, pub_key) = X25519MlKem768::keypair().expect("Keypair generation failed");
+        let (secret, ciphertext) = X25519MlKem768::encapsulate(&pub_key).expect("Encapsulation failed");
+
+        // Flip a bit in the ML-KEM part: implicit rejection gives an unrelated secret
+        let mut tampered = ciphertext.as_bytes().to_vec();
+        tampered[0] ^= 1;
+        let recovered = X25519MlKem768::decapsulate(&priv_key, &Ciphertext::from_bytes(&tampered))
+            .expect("Decapsulation failed");
+        assert_ne!(recovered.as_bytes(), secret.as_bytes());
+
+        let truncated = Ciphertext::from_bytes(&ciphertext.as_bytes()[..1088]);
+        assert!(X25519MlKem768::decapsulate(&priv_key, &truncated).is_err());
+    }
 }
@@ -1,5 +1,5 @@
-use stpc_encoding;
 use stpc_core::StpcError;
+use stpc_encoding::{TlvDecode, TlvEncode};
 
 #[cfg(test)]
 mod tests {
@@ -15,7 +15,10 @@ mod tests {
             (3u8, b"TLVParser!"),
         ];
 
-        let _packed = stpc_encoding::TLVParser::pack(&blocks)?;
+        let packed = stpc_encoding::TLVParser::pack(&blocks)?;
+
+        let owned: Vec<(u8, Vec<u8>)> = blocks.iter().map(|(tag, value)| (*tag, value.to_vec())).collect();
+        assert_eq!(stpc_encoding::TLVParser::pack_owned(stpc_encoding::Framing::V1, &owned)?, packed);
         Ok(())
     }
 
@@ -32,4 +35,494 @@ mod tests {
         let _unpacked = stpc_encoding::TLVParser::unpack(&packed)?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[derive(Debug, Clone, PartialEq, TlvEncode, TlvDecode)]
+    struct Point {
+        #[tlv(tag = 1)]
+        x: u32,
+        #[tlv(tag = 2)]
+        y: u32,
+    }
+
+    /// Flag carried as 'Y' / 'N', to exercise `with`
+    mod letter {
+        use stpc_core::StpcError;
+
+        pub fn encode(value: &bool) -> Result<Vec<u8>, StpcError> {
+            Ok(vec![if *value { b'Y' } else { b'N' }])
+        }
+
+        pub fn decode(value: &[u8]) -> Result<bool, StpcError> {
+            match value {
+                b"Y" => Ok(true),
+                b"N" => Ok(false),
+                _ => Err(StpcError::DeserilizateError("Invalid letter".into())),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, TlvEncode, TlvDecode)]
+    struct Shape {
+        // Declared out of tag order on purpose
+        #[tlv(tag = 3, repeated)]
+        points:  Vec<Point>,
+        #[tlv(tag = 1)]
+        name:    String,
+        #[tlv(tag = 2, optional)]
+        label:   Option<String>,
+        #[tlv(tag = 4, repeated, optional)]
+        holes:   Vec<Point>,
+        #[tlv(tag = 5, with = "letter")]
+        closed:  bool,
+        #[tlv(tag = 6)]
+        id:      [u8; 4],
+    }
+
+    /// Tags past u8, carried by v2 framing only
+    #[derive(Debug, Clone, PartialEq, TlvEncode, TlvDecode)]
+    struct Wide {
+        #[tlv(tag = 1)]
+        small: u32,
+        #[tlv(tag = 70000)]
+        large: String,
+        #[tlv(framing)]
+        framing: stpc_encoding::Framing,
+    }
+
+    fn shape() -> Shape {
+        Shape {
+            points: vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }],
+            name:   "triangle".into(),
+            label:  None,
+            holes:  Vec::new(),
+            closed: true,
+            id:     [1, 2, 3, 4],
+        }
+    }
+
+    #[test]
+    fn test_derive_roundtrip() -> Result<(), StpcError> {
+        let plain = shape();
+        assert_eq!(Shape::tlv_decode(&plain.tlv_encode()?)?, plain);
+
+        let full = Shape { label: Some("big".into()), holes: vec![Point { x: 0, y: 0 }], ..shape() };
+        assert_eq!(Shape::tlv_decode(&full.tlv_encode()?)?, full);
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_layout() -> Result<(), StpcError> {
+        let encoded = shape().tlv_encode()?;
+        let blocks = stpc_encoding::TLVParser::unpack(&encoded)?;
+
+        // Ascending tags, absent optional fields left out
+        let tags: Vec<u8> = blocks.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, vec![1, 3, 5, 6]);
+        assert_eq!(blocks[0].1, b"triangle");
+        assert_eq!(blocks[2].1, b"Y");
+
+        // A repeated field is a list, each element a nested struct
+        let points = stpc_encoding::TLVParser::unpack_wide(&blocks[1].1)?;
+        assert_eq!(points.len(), 2);
+        assert!(points.iter().all(|(tag, _)| *tag == stpc_encoding::schema::LIST_TAG));
+        assert_eq!(Point::tlv_decode(&points[1].1)?, Point { x: 3, y: 4 });
+        assert!(stpc_encoding::TLVParser::is_canonical(&blocks[1].1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_any_order() -> Result<(), StpcError> {
+        let packed = stpc_encoding::TLVParser::pack(&[(2, &9u32.to_be_bytes()), (1, &7u32.to_be_bytes())])?;
+
+        assert_eq!(Point::tlv_decode(&packed)?, Point { x: 7, y: 9 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_matches_hand_written() -> Result<(), StpcError> {
+        let packed = stpc_encoding::TLVParser::pack(&[(1, &7u32.to_be_bytes()), (2, &9u32.to_be_bytes())])?;
+
+        assert_eq!(Point { x: 7, y: 9 }.tlv_encode()?, packed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_rejects_malformed() -> Result<(), StpcError> {
+        let pack = |blocks: &[(u8, &[u8])]| stpc_encoding::TLVParser::pack(blocks);
+
+        // Missing field
+        assert!(Point::tlv_decode(&pack(&[(1, &[0; 4])])?).is_err());
+        // Wrong width
+        assert!(Point::tlv_decode(&pack(&[(1, &[0; 4]), (2, &[0; 8])])?).is_err());
+        // Duplicate
+        assert!(Point::tlv_decode(&pack(&[(1, &[0; 4]), (1, &[0; 4]), (2, &[0; 4])])?).is_err());
+        // Left over
+        assert!(Point::tlv_decode(&pack(&[(1, &[0; 4]), (2, &[0; 4]), (3, &[])])?).is_err());
+        // Bad `with` value and truncated input
+        let mut encoded = shape().tlv_encode()?;
+        assert!(Shape::tlv_decode(&encoded[..encoded.len() - 1]).is_err());
+        let position = encoded.windows(1).rposition(|window| window == b"Y").unwrap();
+        encoded[position] = b'?';
+        assert!(Shape::tlv_decode(&encoded).is_err());
+        Ok(())
+    }
+
+    fn sample() -> Result<Vec<u8>, StpcError> {
+        stpc_encoding::TLVParser::pack(&[(3, b"three"), (1, b"one"), (7, b"a"), (7, b"b")])
+    }
+
+    #[test]
+    fn test_fields_lookup_by_tag() -> Result<(), StpcError> {
+        let mut fields = stpc_encoding::TLVParser::fields_strict(&sample()?)?;
+
+        assert_eq!(fields.required(1, "one")?, b"one");
+        assert_eq!(fields.required(3, "three")?, b"three");
+        assert_eq!(fields.optional(5)?, None);
+        assert_eq!(fields.repeated(7), vec![b"a".to_vec(), b"b".to_vec()]);
+        fields.finish()
+    }
+
+    #[test]
+    fn test_fields_errors() -> Result<(), StpcError> {
+        let packet = sample()?;
+
+        let mut fields = stpc_encoding::TLVParser::fields(&packet)?;
+        assert!(matches!(fields.required(2, "two"), Err(StpcError::MissingTagError { tag: 2, .. })));
+        assert!(matches!(fields.optional(7), Err(StpcError::DuplicateTagError(7))));
+
+        // Leftover blocks are only an error in strict mode
+        let mut lenient = stpc_encoding::TLVParser::fields(&packet)?;
+        lenient.required(1, "one")?;
+        lenient.finish()?;
+
+        let mut strict = stpc_encoding::TLVParser::fields_strict(&packet)?;
+        strict.required(1, "one")?;
+        strict.repeated(7);
+        assert!(matches!(strict.finish(), Err(StpcError::UnexpectedTagError(3))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fields_trailing_bytes() -> Result<(), StpcError> {
+        let packet = [sample()?, vec![0xAA; 3]].concat();
+
+        assert!(stpc_encoding::TLVParser::fields(&packet).is_ok());
+        assert!(matches!(
+            stpc_encoding::TLVParser::fields_strict(&packet),
+            Err(StpcError::TrailingBytesError(3))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_borrows() -> Result<(), StpcError> {
+        let packet = sample()?;
+        let blocks = stpc_encoding::TLVParser::iter(&packet)?.collect::<Result<Vec<_>, _>>()?;
+
+        let owned = stpc_encoding::TLVParser::unpack_wide(&packet)?;
+        assert_eq!(blocks.len(), owned.len());
+        for ((tag, value), (owned_tag, owned_value)) in blocks.iter().zip(&owned) {
+            assert_eq!((tag, *value), (owned_tag, owned_value.as_slice()));
+            // The value points into the packet itself
+            assert!(packet.as_ptr_range().contains(&value.as_ptr()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_malformed() -> Result<(), StpcError> {
+        let packet = sample()?;
+        assert!(stpc_encoding::TLVParser::iter(&packet[..7]).is_err());
+        assert!(stpc_encoding::TLVParser::iter(&packet[..packet.len() - 1]).is_err());
+
+        // Payload length shortened into the middle of the last block
+        let mut cut = packet.clone();
+        cut[7] -= 1;
+        let results: Vec<_> = stpc_encoding::TLVParser::iter(&cut)?.collect();
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(|block| block.is_ok()));
+        assert!(results[3].is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_chunks() -> Result<(), StpcError> {
+        let packet = sample()?;
+        let expected = stpc_encoding::TLVParser::unpack_wide(&packet)?;
+
+        for chunk_size in [1, 3, 7, packet.len()] {
+            let mut decoder = stpc_encoding::TlvDecoder::new();
+            let mut blocks = Vec::new();
+            for chunk in packet.chunks(chunk_size).chain([&[0xEE, 0xFF][..]]) {
+                decoder.feed(chunk);
+                while let Some(block) = decoder.next_block()? {
+                    blocks.push(block);
+                }
+            }
+
+            assert_eq!(blocks, expected);
+            assert!(decoder.is_finished());
+            assert_eq!(decoder.trailing(), &[0xEE, 0xFF]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_limits() -> Result<(), StpcError> {
+        let big = stpc_encoding::TLVParser::pack(&[(1, &[0u8; 100])])?;
+
+        // Rejected as soon as the block header is in, before the value arrives
+        let mut decoder = stpc_encoding::TlvDecoder::with_max_block_size(64);
+        decoder.feed(&big[..13]);
+        assert!(matches!(decoder.next_block(), Err(StpcError::InvalidPacketError(_))));
+
+        // Block running past the declared payload
+        let mut lying = big.clone();
+        lying[7] = 50;
+        let mut decoder = stpc_encoding::TlvDecoder::new();
+        decoder.feed(&lying);
+        assert!(decoder.next_block().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader() -> Result<(), StpcError> {
+        let packet = sample()?;
+        let mut reader = stpc_encoding::TlvReader::new(std::io::Cursor::new([packet.as_slice(), b"next"].concat()))?;
+
+        let mut tags = Vec::new();
+        while let Some((tag, value)) = reader.next_block()? {
+            tags.push(tag);
+            assert!(!value.is_empty());
+        }
+        assert_eq!(tags, vec![3, 1, 7, 7]);
+
+        // The reader stops at the end of the packet
+        let mut rest = Vec::new();
+        std::io::Read::read_to_end(&mut reader.into_inner(), &mut rest).unwrap();
+        assert_eq!(rest, b"next");
+
+        let mut truncated = stpc_encoding::TlvReader::new(std::io::Cursor::new(&packet[..packet.len() - 2]))?;
+        let mut result = truncated.next_block().map(|block| block.is_some());
+        while let Ok(true) = result {
+            result = truncated.next_block().map(|block| block.is_some());
+        }
+        assert!(matches!(result, Err(StpcError::InvalidPacketError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer() -> Result<(), StpcError> {
+        use stpc_encoding::TlvWriter;
+
+        let large = vec![0x5Au8; 10_000];
+        let payload_len = TlvWriter::<Vec<u8>>::block_size(3) + TlvWriter::<Vec<u8>>::block_size(large.len());
+
+        let mut writer = TlvWriter::new(Vec::new(), payload_len)?;
+        writer.write_block(1, b"abc")?;
+        writer.write_block_from(2, large.len(), &mut large.as_slice())?;
+        let written = writer.finish()?;
+
+        assert_eq!(written, stpc_encoding::TLVParser::pack(&[(1, b"abc"), (2, &large)])?);
+
+        // Declared length must be met exactly
+        let mut short = TlvWriter::new(Vec::new(), 20)?;
+        short.write_block(1, b"abc")?;
+        assert!(short.write_block(2, &[0; 20]).is_err());
+        assert!(short.finish().is_err());
+
+        let mut starved = TlvWriter::new(Vec::new(), 15)?;
+        assert!(starved.write_block_from(1, 10, &mut &b"four"[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_canonical() -> Result<(), StpcError> {
+        use stpc_encoding::TLVParser;
+
+        let canonical = TLVParser::pack(&[(1, b"a"), (2, b""), (7, b"c")])?;
+        assert!(TLVParser::is_canonical(&canonical));
+        assert!(TLVParser::is_canonical(&TLVParser::pack(&[])?));
+
+        let unordered = TLVParser::pack(&[(2, b"a"), (1, b"b")])?;
+        assert!(!TLVParser::is_canonical(&unordered));
+        assert!(matches!(TLVParser::check_canonical(&unordered), Err(StpcError::NonCanonicalError(_))));
+
+        let duplicate = TLVParser::pack(&[(1, b"a"), (2, b"b"), (1, b"c")])?;
+        assert!(matches!(TLVParser::check_canonical(&duplicate), Err(StpcError::DuplicateTagError(1))));
+
+        // Lists repeat the list tag and nothing else
+        assert!(TLVParser::is_canonical(&TLVParser::pack(&[(0, b"b"), (0, b"a"), (0, b"b")])?));
+        let mixed = TLVParser::pack(&[(0, b"a"), (1, b"b")])?;
+        assert!(matches!(TLVParser::check_canonical(&mixed), Err(StpcError::NonCanonicalError(_))));
+        assert!(!TLVParser::is_canonical(&TLVParser::pack(&[(1, b"a"), (0, b"b")])?));
+
+        let trailing = [canonical.as_slice(), &[0]].concat();
+        assert!(matches!(TLVParser::check_canonical(&trailing), Err(StpcError::TrailingBytesError(1))));
+        assert!(!TLVParser::is_canonical(&canonical[..canonical.len() - 1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_decode() -> Result<(), StpcError> {
+        use stpc_encoding::schema::canonical;
+
+        let encoded = shape().tlv_encode()?;
+        assert_eq!(canonical::decode::<Shape>(&encoded)?, shape());
+
+        // Decodes fine, but is not how the value encodes
+        let reordered = stpc_encoding::TLVParser::pack(&[(2, &7u32.to_be_bytes()), (1, &3u32.to_be_bytes())])?;
+        assert_eq!(Point::tlv_decode(&reordered)?, Point { x: 3, y: 7 });
+        assert!(matches!(canonical::decode::<Point>(&reordered), Err(StpcError::NonCanonicalError(_))));
+
+        // An optional list present but empty
+        let mut blocks = stpc_encoding::TLVParser::unpack(&encoded)?;
+        blocks.insert(2, (4, stpc_encoding::TLVParser::pack(&[])?));
+        let blocks: Vec<(u8, &[u8])> = blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        let empty_holes = stpc_encoding::TLVParser::pack(&blocks)?;
+        assert!(stpc_encoding::TLVParser::is_canonical(&empty_holes));
+        assert_eq!(Shape::tlv_decode(&empty_holes)?, shape());
+        assert!(matches!(canonical::decode::<Shape>(&empty_holes), Err(StpcError::NonCanonicalError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_varint() -> Result<(), StpcError> {
+        use stpc_encoding::framing::{read_varint, varint_size, write_varint};
+
+        for value in [0, 1, 127, 128, 300, 16_383, 16_384, u32::MAX as u64, u64::MAX] {
+            let mut encoded = Vec::new();
+            write_varint(value, &mut encoded);
+            assert_eq!(encoded.len(), varint_size(value));
+            assert_eq!(read_varint(&encoded)?, Some((value, encoded.len())));
+            assert_eq!(read_varint(&encoded[..encoded.len() - 1])?, None);
+        }
+        assert_eq!(varint_size(127), 1);
+        assert_eq!(varint_size(128), 2);
+
+        // One encoding per value, nothing past u64
+        assert!(read_varint(&[0x80, 0x00]).is_err());
+        assert!(read_varint(&[0xff, 0x80, 0x00]).is_err());
+        assert!(read_varint(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02]).is_err());
+        assert!(read_varint(&[0x80; 11]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_framing() -> Result<(), StpcError> {
+        use stpc_encoding::{Framing, TLVParser};
+
+        let blocks: Vec<(u8, &[u8])> = vec![(1, &[1]), (2, b"name"), (3, &[0; 200])];
+        let v1 = TLVParser::pack(&blocks)?;
+        let v2 = TLVParser::pack_as(Framing::V2, &blocks)?;
+
+        assert_eq!(v1.len() - v2.len(), (8 - 3) + (5 - 2) * 2 + (5 - 3));
+        assert_eq!(Framing::detect(&v1)?, Framing::V1);
+        assert_eq!(Framing::detect(&v2)?, Framing::V2);
+        assert_eq!(TLVParser::unpack(&v2)?, TLVParser::unpack(&v1)?);
+        assert_eq!(TLVParser::pack_as(Framing::V2, &[])?, vec![2, 0]);
+
+        let mut fields = TLVParser::fields_strict(&v2)?;
+        assert_eq!(fields.framing(), Framing::V2);
+        assert_eq!(fields.required(2, "name")?, b"name");
+
+        assert!(TLVParser::is_canonical(&v2));
+        assert!(!TLVParser::is_canonical(&TLVParser::pack_as(Framing::V2, &[(2, b"a"), (1, b"b")])?));
+
+        // Unknown framing, overlong length
+        assert!(TLVParser::unpack(&[7, 0]).is_err());
+        assert!(TLVParser::unpack(&[2, 0x83, 0x00, 1, 1, 9, 0, 0]).is_err());
+        assert!(TLVParser::unpack(&v2[..v2.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_wide_tags() -> Result<(), StpcError> {
+        use stpc_encoding::{Framing, TLVParser};
+
+        let packet = TLVParser::pack_wide(Framing::V2, &[(1, b"a"), (300, b"b"), (u32::MAX, b"c")])?;
+        assert_eq!(
+            TLVParser::unpack_wide(&packet)?,
+            vec![(1, b"a".to_vec()), (300, b"b".to_vec()), (u32::MAX, b"c".to_vec())]
+        );
+        assert!(TLVParser::is_canonical(&packet));
+
+        let tags: Vec<u32> = TLVParser::iter(&packet)?.map(|block| block.map(|(tag, _)| tag)).collect::<Result<_, _>>()?;
+        assert_eq!(tags, vec![1, 300, u32::MAX]);
+
+        // Lenient lookups skip the wide tags nobody asks for, strict ones name them
+        let mut fields = TLVParser::fields(&packet)?;
+        assert_eq!(fields.required(1, "a")?, b"a");
+        fields.finish()?;
+        let mut fields = TLVParser::fields_strict(&packet)?;
+        assert_eq!(fields.required(1, "a")?, b"a");
+        assert_eq!(fields.required(u32::MAX, "c")?, b"c");
+        assert!(matches!(fields.finish(), Err(StpcError::UnexpectedTagError(300))));
+
+        // The u8 API refuses what it cannot represent
+        assert!(TLVParser::unpack(&packet).is_err());
+        assert!(TLVParser::pack_wide(Framing::V1, &[(300, b"b")]).is_err());
+
+        let wide = Wide { small: 7, large: "far".into(), framing: Framing::V2 };
+        let encoded = wide.tlv_encode()?;
+        assert_eq!(TLVParser::unpack_wide(&encoded)?[1].0, 70000);
+        assert_eq!(Wide::tlv_decode(&encoded)?, wide);
+        assert!(Wide { framing: Framing::V1, ..wide }.tlv_encode().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_v2_streaming() -> Result<(), StpcError> {
+        use stpc_encoding::{Framing, TLVParser, TlvDecoder, TlvReader, TlvWriter};
+
+        let large = vec![0x5Au8; 1000];
+        let blocks: Vec<(u8, &[u8])> = vec![(1, b"abc"), (2, &large)];
+        let packet = TLVParser::pack_as(Framing::V2, &blocks)?;
+
+        let mut decoder = TlvDecoder::new();
+        let mut decoded = Vec::new();
+        for byte in &packet {
+            decoder.feed(&[*byte]);
+            while let Some(block) = decoder.next_block()? {
+                decoded.push(block);
+            }
+        }
+        assert!(decoder.is_finished());
+        assert_eq!(decoded, TLVParser::unpack_wide(&packet)?);
+
+        let mut reader = TlvReader::new(std::io::Cursor::new(&packet))?;
+        assert_eq!(reader.framing(), Framing::V2);
+        assert_eq!(reader.next_block()?, Some((1, &b"abc"[..])));
+        assert_eq!(reader.next_block()?.map(|(tag, value)| (tag, value.len())), Some((2, 1000)));
+        assert_eq!(reader.next_block()?, None);
+
+        let payload_len = Framing::V2.block_size(1, 3) + Framing::V2.block_size(2, large.len());
+        let mut writer = TlvWriter::with_framing(Vec::new(), Framing::V2, payload_len)?;
+        writer.write_block(1, b"abc")?;
+        writer.write_block_from(2, large.len(), &mut large.as_slice())?;
+        assert_eq!(writer.finish()?, packet);
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_v2() -> Result<(), StpcError> {
+        use stpc_encoding::schema::canonical;
+        use stpc_encoding::Framing;
+
+        let full = Shape { label: Some("big".into()), holes: vec![Point { x: 0, y: 0 }], ..shape() };
+        let v1 = full.tlv_encode()?;
+        let v2 = full.tlv_encode_as(Framing::V2)?;
+        assert!(v2.len() < v1.len());
+        assert_eq!(Shape::tlv_decode(&v2)?, full);
+        assert_eq!(canonical::decode::<Shape>(&v2)?, full);
+
+        // Nested packets follow the framing of the struct around them
+        let mut blocks = stpc_encoding::TLVParser::unpack(&v2)?;
+        blocks[2].1 = stpc_encoding::schema::encode_repeated(Framing::V1, &full.points, |point| point.tlv_encode())?;
+        let blocks: Vec<(u8, &[u8])> = blocks.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        let mixed = stpc_encoding::TLVParser::pack_as(Framing::V2, &blocks)?;
+        assert_eq!(Shape::tlv_decode(&mixed)?, full);
+        assert!(matches!(canonical::decode::<Shape>(&mixed), Err(StpcError::NonCanonicalError(_))));
+        Ok(())
+    }
+}
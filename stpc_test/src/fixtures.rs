@@ -0,0 +1,104 @@
+use stpc_certs::{TbsCertificate, Certificate, Validity, DistinguishedName};
+use stpc_certs::extensions::{Extension, ExtensionValue};
+use stpc_core::{CertificateVersion, Key, SignatureAlgorithm};
+use stpc_crypto::{TypedPrivateKey, TypedPublicKey};
+
+pub const OCSP_URL: &str = "http://ocsp.example.com";
+
+pub fn dn(common_name: &str) -> DistinguishedName {
+    DistinguishedName::new(common_name.to_string(), None, None, None, None, None, None)
+}
+
+/// Fresh keypair of `algorithm`
+pub fn keypair(algorithm: SignatureAlgorithm) -> (TypedPrivateKey, TypedPublicKey) {
+    TypedPrivateKey::generate(algorithm).expect("Keypair generation failed")
+}
+
+/// Public key of a fresh Ed25519 pair, for subjects that never sign anything
+pub fn throwaway_key() -> TypedPublicKey {
+    keypair(SignatureAlgorithm::Ed25519).1
+}
+
+// CertSpec
+/// Certificate from `issuer` to `subject`, signed with Ed25519, valid over 0..1000
+/// with `OCSP_URL`, no extensions and no declared subject key algorithm unless changed
+pub struct CertSpec {
+    subject:       String,
+    issuer:        String,
+    algorithm:     SignatureAlgorithm,
+    key_algorithm: Option<SignatureAlgorithm>,
+    validity:      Validity,
+    ocsp_url:      String,
+    extensions:    Vec<Extension>,
+}
+
+pub fn cert(subject: &str, issuer: &str) -> CertSpec {
+    CertSpec {
+        subject:       subject.to_string(),
+        issuer:        issuer.to_string(),
+        algorithm:     SignatureAlgorithm::Ed25519,
+        key_algorithm: None,
+        validity:      Validity::new(0, 1000),
+        ocsp_url:      OCSP_URL.to_string(),
+        extensions:    Vec::new(),
+    }
+}
+
+impl CertSpec {
+    /// Algorithm the issuer signs with
+    pub fn algorithm(mut self, algorithm: SignatureAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Algorithm declared for the subject key
+    pub fn key_algorithm(mut self, algorithm: SignatureAlgorithm) -> Self {
+        self.key_algorithm = Some(algorithm);
+        self
+    }
+
+    pub fn validity(mut self, validity: Validity) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    pub fn ocsp_url(mut self, ocsp_url: &str) -> Self {
+        self.ocsp_url = ocsp_url.to_string();
+        self
+    }
+
+    pub fn extension(mut self, critical: bool, value: ExtensionValue) -> Self {
+        self.extensions.push(Extension::new(critical, value));
+        self
+    }
+
+    pub fn extensions(mut self, extensions: Vec<Extension>) -> Self {
+        self.extensions.extend(extensions);
+        self
+    }
+
+    /// Mark the subject as a CA
+    pub fn ca(self, path_len: Option<u8>) -> Self {
+        self.extension(true, ExtensionValue::BasicConstraints { ca: true, path_len })
+    }
+
+    pub fn sign(self, subject_key: &TypedPublicKey, issuer_key: &TypedPrivateKey) -> Certificate {
+        let tbs = TbsCertificate::new(
+            CertificateVersion::V1,
+            self.algorithm,
+            dn(&self.issuer),
+            self.validity,
+            dn(&self.subject),
+            subject_key.key().as_bytes().to_vec(),
+            self.ocsp_url,
+        )
+        .with_extensions(self.extensions);
+
+        match self.key_algorithm {
+            Some(algorithm) => tbs.with_subject_key_algorithm(algorithm),
+            None => tbs,
+        }
+        .sign(issuer_key)
+        .expect("Certificate signing failed")
+    }
+}
@@ -0,0 +1,366 @@
+use stpc_certs::{Certificate, Validity};
+use stpc_core::{KemAlgorithm, SignatureAlgorithm, StpcError};
+use stpc_handshake::{Handshake, HandshakeConfig, Role, Session};
+use stpc_handshake::record::{CipherSuite, RecordStream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cert, dn, keypair, CertSpec};
+    use stpc_certs::extensions::{Extension, ExtensionValue, KeyPurpose};
+    use stpc_certs::path::TrustAnchor;
+    use stpc_certs::CertSerializable;
+    use stpc_crypto::TypedPrivateKey;
+    use stpc_encoding::{Framing, TLVParser};
+    use stpc_handshake::messages::{ClientHello, ServerHello, PROTOCOL_VERSION};
+    use stpc_time::TimeManager;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::net::UnixStream;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Certificate valid for as long as the handshakes read the clock
+    fn long_lived(algorithm: SignatureAlgorithm, subject: &str, issuer: &str) -> CertSpec {
+        cert(subject, issuer).algorithm(algorithm).validity(Validity::new(0, u64::MAX)).ocsp_url("")
+    }
+
+    fn purpose(purpose: KeyPurpose) -> Vec<Extension> {
+        vec![Extension::new(false, ExtensionValue::ExtendedKeyUsage(vec![purpose]))]
+    }
+
+    struct Pki {
+        ca: Certificate,
+        ca_key: TypedPrivateKey,
+        algorithm: SignatureAlgorithm,
+    }
+
+    impl Pki {
+        fn new(algorithm: SignatureAlgorithm, name: &str) -> Self {
+            let (ca_key, ca_pub) = keypair(algorithm);
+
+            Pki {
+                ca: long_lived(algorithm, name, name).key_algorithm(algorithm).ca(None).sign(&ca_pub, &ca_key),
+                ca_key,
+                algorithm,
+            }
+        }
+
+        /// Leaf of this CA for a subject key of `algorithm`
+        fn leaf(&self, algorithm: SignatureAlgorithm, subject: &str) -> CertSpec {
+            let ca_name = &self.ca.tbs_certificate.subject.common_name;
+            long_lived(self.algorithm, subject, ca_name).key_algorithm(algorithm)
+        }
+
+        /// Handshake for a fresh leaf of this CA that trusts `anchor`
+        fn endpoint(
+            &self,
+            algorithm: SignatureAlgorithm,
+            subject: &str,
+            extensions: Vec<Extension>,
+            anchor: &Certificate,
+            kem: KemAlgorithm,
+        ) -> Handshake {
+            let (key, public) = keypair(algorithm);
+            let leaf = self.leaf(algorithm, subject).extensions(extensions).sign(&public, &self.ca_key);
+
+            let config = HandshakeConfig {
+                chain:         vec![leaf],
+                private_key:   key,
+                trust_anchors: vec![TrustAnchor::new(anchor.clone())],
+                kem,
+            };
+            Handshake::new(config, Arc::new(TimeManager::new()))
+        }
+    }
+
+    /// Run both sides over a connected socket pair, the server in its own thread
+    fn run(client: Handshake, server: Handshake) -> (Result<Session, StpcError>, Result<Session, StpcError>) {
+        let (mut client_stream, mut server_stream) = UnixStream::pair().expect("Socket pair failed");
+
+        let server = thread::spawn(move || {
+            let result = server.accept(&mut server_stream);
+            drop(server_stream);
+            result
+        });
+        let client_result = client.connect(&mut client_stream);
+        drop(client_stream);
+
+        (client_result, server.join().expect("Server thread panicked"))
+    }
+
+    fn ed25519_pair(kem: KemAlgorithm) -> (Handshake, Handshake) {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+        let client = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", purpose(KeyPurpose::ClientAuth), &pki.ca, kem,
+        );
+        let server = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Server", purpose(KeyPurpose::ServerAuth), &pki.ca, kem,
+        );
+        (client, server)
+    }
+
+    fn assert_matching(client: &Session, server: &Session) {
+        assert_eq!(client.role, Role::Client);
+        assert_eq!(server.role, Role::Server);
+        assert_eq!(client.transcript_hash, server.transcript_hash);
+        assert_eq!(client.write_secret(), server.read_secret());
+        assert_eq!(client.read_secret(), server.write_secret());
+        assert_ne!(client.write_secret(), client.read_secret());
+    }
+
+    #[test]
+    fn test_handshake_in_memory() {
+        let (client, server) = ed25519_pair(KemAlgorithm::X25519);
+        let (client, server) = run(client, server);
+        let (client, server) = (client.expect("Client failed"), server.expect("Server failed"));
+
+        assert_matching(&client, &server);
+        assert_eq!(client.peer_certificate().tbs_certificate.subject, dn("Server"));
+        assert_eq!(server.peer_certificate().tbs_certificate.subject, dn("Client"));
+    }
+
+    #[test]
+    fn test_handshake_tcp() {
+        let (client, server) = ed25519_pair(KemAlgorithm::X25519MlKem768);
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Bind failed");
+        let address = listener.local_addr().expect("No local address");
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("Accept failed");
+            server.accept(&mut stream)
+        });
+        let mut stream = TcpStream::connect(address).expect("Connect failed");
+        let client = client.connect(&mut stream).expect("Client failed");
+        let server = server.join().expect("Server thread panicked").expect("Server failed");
+
+        assert_matching(&client, &server);
+    }
+
+    #[test]
+    fn test_handshake_then_records() {
+        let (client, server) = ed25519_pair(KemAlgorithm::X25519MlKem768);
+        let (mut client_stream, mut server_stream) = UnixStream::pair().expect("Socket pair failed");
+
+        let server = thread::spawn(move || {
+            let session = server.accept(&mut server_stream).expect("Server failed");
+            let mut records = RecordStream::new(server_stream, &session, CipherSuite::Aes256Gcm);
+
+            let mut request = [0u8; 5];
+            records.read_exact(&mut request).expect("Read failed");
+            assert_eq!(&request, b"hello");
+            records.write_all(b"world").expect("Write failed");
+            records.close().expect("Close failed");
+        });
+
+        let session = client.connect(&mut client_stream).expect("Client failed");
+        let mut records = RecordStream::new(client_stream, &session, CipherSuite::Aes256Gcm);
+        records.write_all(b"hello").expect("Write failed");
+
+        let mut response = Vec::new();
+        records.read_to_end(&mut response).expect("Read failed");
+        assert_eq!(response, b"world");
+        server.join().expect("Server thread panicked");
+    }
+
+    #[test]
+    fn test_handshake_post_quantum() {
+        let pki = Pki::new(SignatureAlgorithm::MlDsa44, "PQ CA");
+        let client = pki.endpoint(
+            SignatureAlgorithm::MlDsa44, "Client", Vec::new(), &pki.ca, KemAlgorithm::MlKem768,
+        );
+        let server = pki.endpoint(
+            SignatureAlgorithm::MlDsa44, "Server", Vec::new(), &pki.ca, KemAlgorithm::MlKem768,
+        );
+
+        let (client, server) = run(client, server);
+        assert_matching(&client.expect("Client failed"), &server.expect("Server failed"));
+    }
+
+    #[test]
+    fn test_handshake_leaf_algorithm_differs_from_ca() {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+        let client = pki.endpoint(
+            SignatureAlgorithm::MlDsa44, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        let server = pki.endpoint(
+            SignatureAlgorithm::MlDsa44, "Server", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+
+        let (client, server) = run(client, server);
+        assert_matching(&client.expect("Client failed"), &server.expect("Server failed"));
+    }
+
+    /// Server whose leaf is made by `leaf` for a fresh Ed25519 key, claiming Ed25519
+    fn server_with_leaf(pki: &Pki, leaf: CertSpec) -> Handshake {
+        let (key, public) = keypair(SignatureAlgorithm::Ed25519);
+        let config = HandshakeConfig {
+            chain:         vec![leaf.sign(&public, &pki.ca_key)],
+            private_key:   key,
+            trust_anchors: vec![TrustAnchor::new(pki.ca.clone())],
+            kem:           KemAlgorithm::X25519,
+        };
+        Handshake::new(config, Arc::new(TimeManager::new()))
+    }
+
+    #[test]
+    fn test_handshake_claim_must_match_leaf() {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+
+        // An Ed25519 key fits a 32-byte SLH-DSA key, so only the leaf can say which it is
+        let client = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        let server = server_with_leaf(&pki, pki.leaf(SignatureAlgorithm::SlhDsaSha2_128s, "Server"));
+        let (client, _) = run(client, server);
+        assert!(matches!(client, Err(StpcError::HandshakeError(_))));
+
+        // Nor is a leaf that does not say taken at the peer's word
+        let client = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        let server = server_with_leaf(&pki, long_lived(SignatureAlgorithm::Ed25519, "Server", "CA"));
+        let (client, _) = run(client, server);
+        assert!(matches!(client, Err(StpcError::InvalidKeyError(_))));
+    }
+
+    #[test]
+    fn test_handshake_fresh_keys() {
+        let (client, server) = ed25519_pair(KemAlgorithm::X25519);
+        let (first, _) = run(client, server);
+
+        let (client, server) = ed25519_pair(KemAlgorithm::X25519);
+        let (second, _) = run(client, server);
+
+        let (first, second) = (first.expect("Client failed"), second.expect("Client failed"));
+        assert_ne!(first.transcript_hash, second.transcript_hash);
+        assert_ne!(first.write_secret(), second.write_secret());
+    }
+
+    #[test]
+    fn test_handshake_untrusted_client() {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+        let rogue = Pki::new(SignatureAlgorithm::Ed25519, "Rogue CA");
+        let client = rogue.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        let server = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Server", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+
+        let (_, server) = run(client, server);
+        assert!(matches!(server, Err(StpcError::HandshakeError(_))));
+    }
+
+    #[test]
+    fn test_handshake_untrusted_server() {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+        let rogue = Pki::new(SignatureAlgorithm::Ed25519, "Rogue CA");
+        let client = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        let server = rogue.endpoint(
+            SignatureAlgorithm::Ed25519, "Server", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+
+        let (client, server) = run(client, server);
+        assert!(matches!(client, Err(StpcError::HandshakeError(_))));
+        assert!(server.is_err());
+    }
+
+    #[test]
+    fn test_handshake_wrong_purpose() {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+        let client = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        // A client certificate presented by the server
+        let server = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Server", purpose(KeyPurpose::ClientAuth), &pki.ca, KemAlgorithm::X25519,
+        );
+
+        let (client, _) = run(client, server);
+        assert!(matches!(client, Err(StpcError::HandshakeError(_))));
+    }
+
+    #[test]
+    fn test_handshake_key_not_matching_certificate() {
+        let pki = Pki::new(SignatureAlgorithm::Ed25519, "CA");
+        let client = pki.endpoint(
+            SignatureAlgorithm::Ed25519, "Client", Vec::new(), &pki.ca, KemAlgorithm::X25519,
+        );
+        // Certificate of another key holder
+        let (_, other) = keypair(SignatureAlgorithm::Ed25519);
+        let (key, _) = keypair(SignatureAlgorithm::Ed25519);
+        let config = HandshakeConfig {
+            chain:         vec![pki.leaf(SignatureAlgorithm::Ed25519, "Server").sign(&other, &pki.ca_key)],
+            private_key:   key,
+            trust_anchors: vec![TrustAnchor::new(pki.ca.clone())],
+            kem:           KemAlgorithm::X25519,
+        };
+        let server = Handshake::new(config, Arc::new(TimeManager::new()));
+
+        let (client, _) = run(client, server);
+        assert!(matches!(client, Err(StpcError::SignatureVerifyError)));
+    }
+
+    #[test]
+    fn test_handshake_kem_mismatch() {
+        let (client, _) = ed25519_pair(KemAlgorithm::X25519);
+        let (_, server) = ed25519_pair(KemAlgorithm::MlKem768);
+
+        let (client, server) = run(client, server);
+        assert!(matches!(server, Err(StpcError::HandshakeError(_))));
+        assert!(matches!(client, Err(StpcError::NetworkError(_))));
+    }
+
+    #[test]
+    fn test_handshake_messages_strict_and_canonical() -> Result<(), StpcError> {
+        let hello = ClientHello::new(KemAlgorithm::X25519, vec![7; 32]);
+        let packet = hello.serialize()?;
+        let decoded = ClientHello::deserialize_canonical(&packet)?;
+        assert_eq!((decoded.random, decoded.kem, decoded.public_key), (hello.random, hello.kem, hello.public_key));
+
+        let blocks = |version: u8, extra: Option<(u8, Vec<u8>)>| {
+            let mut blocks = vec![
+                (1, vec![1]),
+                (2, vec![version]),
+                (3, hello.random.to_vec()),
+                (4, vec![1]),
+                (5, vec![7; 32]),
+            ];
+            blocks.extend(extra);
+            blocks
+        };
+        assert_eq!(TLVParser::pack_owned(Framing::V1, &blocks(PROTOCOL_VERSION, None))?, packet);
+
+        // another message type, another protocol version
+        assert!(matches!(ServerHello::deserialize_canonical(&packet), Err(StpcError::HandshakeError(_))));
+        let future = TLVParser::pack_owned(Framing::V1, &blocks(PROTOCOL_VERSION + 1, None))?;
+        assert!(matches!(ClientHello::deserialize_canonical(&future), Err(StpcError::HandshakeError(_))));
+
+        // undeclared and duplicate tags
+        let extra = TLVParser::pack_owned(Framing::V1, &blocks(PROTOCOL_VERSION, Some((6, vec![0]))))?;
+        assert!(ClientHello::deserialize(&extra).is_err());
+        let duplicate = TLVParser::pack_owned(Framing::V1, &blocks(PROTOCOL_VERSION, Some((5, vec![7; 32]))))?;
+        assert!(ClientHello::deserialize(&duplicate).is_err());
+
+        // decodable out of order, but not canonical
+        let mut swapped = blocks(PROTOCOL_VERSION, None);
+        swapped.swap(2, 3);
+        let swapped = TLVParser::pack_owned(Framing::V1, &swapped)?;
+        assert!(ClientHello::deserialize(&swapped).is_ok());
+        assert!(matches!(ClientHello::deserialize_canonical(&swapped), Err(StpcError::NonCanonicalError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handshake_peer_closes() {
+        let (client, _) = ed25519_pair(KemAlgorithm::X25519);
+        let (mut stream, peer) = UnixStream::pair().expect("Socket pair failed");
+        drop(peer);
+
+        assert!(client.connect(&mut stream).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use stpc_core::{HashAlgorithm, PublicKey, StpcError};
+use stpc_crypto::hash::{digest, hasher, hkdf, hkdf_expand, hkdf_extract, hmac, hmac_verify, key_id};
+use stpc_crypto::PublicKeyExt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stpc_certs::{hash_from_tag, hash_tag};
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        let hex: String = hex.split_whitespace().collect();
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Invalid hex"))
+            .collect()
+    }
+
+    // FIPS 180-4 / FIPS 202 examples and the BLAKE3 reference implementation, input "abc"
+    const ABC_DIGESTS: [(HashAlgorithm, &str); 5] = [
+        (HashAlgorithm::Sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (
+            HashAlgorithm::Sha384,
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+        ),
+        (
+            HashAlgorithm::Sha512,
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        ),
+        (HashAlgorithm::Sha3_256, "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"),
+        (HashAlgorithm::Blake3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+    ];
+
+    #[test]
+    fn test_digest_known_answers() {
+        for (algorithm, expected) in ABC_DIGESTS {
+            let output = digest(algorithm, b"abc");
+            assert_eq!(output, from_hex(expected), "{}", algorithm.name());
+            assert_eq!(output.len(), algorithm.output_size());
+        }
+    }
+
+    #[test]
+    fn test_streaming_hasher() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+        for algorithm in HashAlgorithm::all() {
+            let mut streaming = hasher(*algorithm);
+            assert_eq!(streaming.algorithm(), *algorithm);
+            for chunk in data.chunks(777) {
+                streaming.update(chunk);
+            }
+            assert_eq!(streaming.finalize_reset(), digest(*algorithm, &data));
+
+            // finalize_reset leaves an empty hasher behind
+            streaming.update(b"abc");
+            assert_eq!(streaming.finalize_reset(), digest(*algorithm, b"abc"));
+        }
+    }
+
+    // RFC 4231, test case 2
+    #[test]
+    fn test_hmac_known_answer() {
+        let key = b"Jefe";
+        let data = b"what do ya want for nothing?";
+
+        let tag = hmac(HashAlgorithm::Sha256, key, data);
+        assert_eq!(tag, from_hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"));
+
+        let tag = hmac(HashAlgorithm::Sha512, key, data);
+        assert_eq!(
+            tag,
+            from_hex(
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+                 9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+            )
+        );
+    }
+
+    #[test]
+    fn test_hmac_verify() {
+        for algorithm in HashAlgorithm::all() {
+            let tag = hmac(*algorithm, b"key", b"message");
+            assert_eq!(tag.len(), algorithm.output_size());
+
+            assert!(hmac_verify(*algorithm, b"key", b"message", &tag));
+            assert!(!hmac_verify(*algorithm, b"other key", b"message", &tag));
+            assert!(!hmac_verify(*algorithm, b"key", b"other message", &tag));
+            assert!(!hmac_verify(*algorithm, b"key", b"message", &tag[1..]));
+        }
+    }
+
+    // RFC 5869, test case 1
+    #[test]
+    fn test_hkdf_known_answer() {
+        let ikm = [0x0b; 22];
+        let salt = from_hex("000102030405060708090a0b0c");
+        let info = from_hex("f0f1f2f3f4f5f6f7f8f9");
+
+        let prk = hkdf_extract(HashAlgorithm::Sha256, &salt, &ikm);
+        assert_eq!(
+            prk.as_slice(),
+            from_hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5").as_slice()
+        );
+
+        let expected = from_hex(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+        );
+        let okm = hkdf_expand(HashAlgorithm::Sha256, &prk, &info, 42).expect("Expand failed");
+        assert_eq!(okm.as_slice(), expected.as_slice());
+
+        let okm = hkdf(HashAlgorithm::Sha256, &salt, &ikm, &info, 42).expect("HKDF failed");
+        assert_eq!(okm.as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn test_hkdf_all_algorithms() {
+        for algorithm in HashAlgorithm::all() {
+            let first = hkdf(*algorithm, b"salt", b"secret", b"first", 64).expect("HKDF failed");
+            let second = hkdf(*algorithm, b"salt", b"secret", b"second", 64).expect("HKDF failed");
+            assert_eq!(first.len(), 64);
+            assert_ne!(first.as_slice(), second.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_hkdf_invalid_lengths() {
+        let prk = hkdf_extract(HashAlgorithm::Sha256, b"salt", b"secret");
+
+        // At most 255 blocks of output
+        assert!(matches!(
+            hkdf_expand(HashAlgorithm::Sha256, &prk, b"", 255 * 32 + 1),
+            Err(StpcError::KeyDerivationError(_))
+        ));
+        assert!(matches!(
+            hkdf_expand(HashAlgorithm::Sha256, &prk[..16], b"", 32),
+            Err(StpcError::KeyDerivationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_hash_wire_tags() {
+        for algorithm in HashAlgorithm::all() {
+            assert_eq!(hash_from_tag(&[hash_tag(*algorithm)]).expect("Unknown tag"), *algorithm);
+        }
+        assert!(hash_from_tag(&[0]).is_err());
+        assert!(hash_from_tag(&[1, 1]).is_err());
+    }
+
+    #[test]
+    fn test_public_key_id() {
+        let id = key_id(&PublicKey::from_bytes(&[0u8; 32]));
+
+        assert_eq!(id.algorithm, HashAlgorithm::Sha256);
+        assert_eq!(
+            id.to_colon_hex(),
+            "66:68:7A:AD:F8:62:BD:77:6C:8F:C1:8B:8E:9F:8E:20:08:97:14:85:6E:E2:33:B3:90:2A:59:1D:0D:5F:29:25"
+        );
+        assert_ne!(id, key_id(&PublicKey::from_bytes(&[1u8; 32])));
+        assert_eq!(PublicKey::from_bytes(&[0u8; 32]).key_id(), id);
+    }
+}
@@ -0,0 +1,159 @@
+use stpc_certs::keystore::{self, KdfParams};
+use stpc_core::{Key, SignatureAlgorithm, StpcError};
+use stpc_crypto::TypedPrivateKey;
+use stpc_encoding::{TLVParser, TLV};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters, the defaults make every test take a noticeable time
+    const FAST: KdfParams = KdfParams { memory_kib: 64, iterations: 1, lanes: 1 };
+
+    fn key(algorithm: SignatureAlgorithm) -> TypedPrivateKey {
+        TypedPrivateKey::generate(algorithm).expect("Keypair generation failed").0
+    }
+
+    /// Replace the value of `tag` in a TLV packet
+    fn replace(packet: &[u8], tag: u8, value: &[u8]) -> Vec<u8> {
+        let blocks = TLVParser::unpack(packet).expect("Unpack failed");
+        let blocks: Vec<(u8, &[u8])> = blocks
+            .iter()
+            .map(|(t, v)| (*t, if *t == tag { value } else { v.as_slice() }))
+            .collect();
+        TLVParser::pack(&blocks).expect("Pack failed")
+    }
+
+    #[test]
+    fn test_keystore_file_roundtrip() {
+        let key = key(SignatureAlgorithm::Ed25519);
+        let path = std::env::temp_dir().join(format!("stpc_keystore_test_{}.key", std::process::id()));
+
+        keystore::save_private_key(&path, &key, b"correct horse").expect("Save failed");
+        let loaded = keystore::load_private_key(&path, b"correct horse").expect("Load failed");
+
+        let contents = std::fs::read(&path).unwrap();
+        assert!(!contents.windows(32).any(|window| window == key.key().as_bytes()));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.algorithm(), SignatureAlgorithm::Ed25519);
+        assert_eq!(loaded.key().as_bytes(), key.key().as_bytes());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keystore_overwrite_tightens_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let key = key(SignatureAlgorithm::Ed25519);
+        let dir = std::env::temp_dir().join(format!("stpc_keystore_overwrite_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.key");
+        std::fs::write(&path, b"old contents").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        keystore::save_private_key(&path, &key, b"correct horse").expect("Save failed");
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        let loaded = keystore::load_private_key(&path, b"correct horse").expect("Load failed");
+        let leftovers = std::fs::read_dir(&dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(mode, 0o600);
+        assert_eq!(leftovers, 1, "temporary file left behind");
+        assert_eq!(loaded.key().as_bytes(), key.key().as_bytes());
+    }
+
+    #[test]
+    fn test_keystore_algorithms() {
+        for &algorithm in &[SignatureAlgorithm::Falcon512, SignatureAlgorithm::MlDsa65, SignatureAlgorithm::Ed25519Falcon512] {
+            let key = key(algorithm);
+            let envelope = keystore::encrypt_private_key(&key, b"passphrase", &FAST).expect("Encryption failed");
+            let loaded = keystore::decrypt_private_key(&envelope, b"passphrase").expect("Decryption failed");
+
+            assert_eq!(loaded.algorithm(), algorithm);
+            assert_eq!(loaded.key().as_bytes(), key.key().as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_keystore_fresh_salt_and_nonce() {
+        let key = key(SignatureAlgorithm::Ed25519);
+        let first = keystore::encrypt_private_key(&key, b"passphrase", &FAST).expect("Encryption failed");
+        let second = keystore::encrypt_private_key(&key, b"passphrase", &FAST).expect("Encryption failed");
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_keystore_wrong_passphrase() {
+        let envelope = keystore::encrypt_private_key(&key(SignatureAlgorithm::Ed25519), b"passphrase", &FAST)
+            .expect("Encryption failed");
+
+        assert!(matches!(
+            keystore::decrypt_private_key(&envelope, b"Passphrase"),
+            Err(StpcError::KeyDecryptionError)
+        ));
+    }
+
+    #[test]
+    fn test_keystore_header_is_authenticated() {
+        let envelope = keystore::encrypt_private_key(&key(SignatureAlgorithm::Ed25519), b"passphrase", &FAST)
+            .expect("Encryption failed");
+
+        // Claiming another algorithm of the same key size breaks the tag
+        let relabeled = replace(&envelope, 2, &[4]);
+        assert!(keystore::decrypt_private_key(&relabeled, b"passphrase").is_err());
+
+        let blocks = TLVParser::unpack(&envelope).unwrap();
+        let kdf = replace(&blocks[2].1, 3, &2u32.to_be_bytes());
+        let weakened = replace(&envelope, 3, &kdf);
+        assert!(matches!(
+            keystore::decrypt_private_key(&weakened, b"passphrase"),
+            Err(StpcError::KeyDecryptionError)
+        ));
+    }
+
+    #[test]
+    fn test_keystore_rejects_excessive_kdf_cost() {
+        let envelope = keystore::encrypt_private_key(&key(SignatureAlgorithm::Ed25519), b"passphrase", &FAST)
+            .expect("Encryption failed");
+
+        let blocks = TLVParser::unpack(&envelope).unwrap();
+        let kdf = replace(&blocks[2].1, 2, &u32::MAX.to_be_bytes());
+        let expensive = replace(&envelope, 3, &kdf);
+        assert!(matches!(
+            keystore::decrypt_private_key(&expensive, b"passphrase"),
+            Err(StpcError::KeyStoreError(_))
+        ));
+
+        // nor is such a file written in the first place
+        let costly = KdfParams { iterations: 11, ..FAST };
+        assert!(matches!(
+            keystore::encrypt_private_key(&key(SignatureAlgorithm::Ed25519), b"passphrase", &costly),
+            Err(StpcError::KeyStoreError(_))
+        ));
+    }
+
+    #[test]
+    fn test_keystore_malformed() {
+        let envelope = keystore::encrypt_private_key(&key(SignatureAlgorithm::Ed25519), b"passphrase", &FAST)
+            .expect("Encryption failed");
+
+        assert!(keystore::decrypt_private_key(&envelope[..envelope.len() - 1], b"passphrase").is_err());
+        assert!(keystore::decrypt_private_key(&replace(&envelope, 1, &[2]), b"passphrase").is_err());
+        assert!(keystore::decrypt_private_key(&replace(&envelope, 5, &[0; 8]), b"passphrase").is_err());
+        assert!(keystore::decrypt_private_key(&[], b"passphrase").is_err());
+    }
+
+    #[test]
+    fn test_keystore_missing_file() {
+        let path = std::env::temp_dir().join("stpc_keystore_test_missing.key");
+        assert!(matches!(keystore::load_private_key(&path, b"passphrase"), Err(StpcError::IoError(_))));
+    }
+}
@@ -1,9 +1,15 @@
+#[cfg(test)]
+mod fixtures;
+
 #[cfg(test)]
 mod core_test;
 
 #[cfg(test)]
 mod crypto_test;
 
+#[cfg(test)]
+mod hash_test;
+
 #[cfg(test)]
 mod logging_test;
 
@@ -16,4 +22,17 @@ mod encoding_test;
 #[cfg(test)]
 mod certs_test;
 
+#[cfg(test)]
+mod crl_test;
+
+#[cfg(test)]
+mod ocsp_test;
+
+#[cfg(test)]
+mod handshake_test;
 
+#[cfg(test)]
+mod record_test;
+
+#[cfg(test)]
+mod keystore_test;
@@ -0,0 +1,607 @@
+use stpc_certs::{Certificate, Validity, CertSerializable};
+use stpc_core::{SignatureAlgorithm, StpcError};
+use stpc_crl::RevocationReason;
+use stpc_ocsp::{CertId, CertStatus, OcspRequest, OcspResponse, ResponderId, ResponseData};
+use stpc_ocsp::responder::{CrlFileStore, MemoryStore, OcspResponder, OcspServer};
+use stpc_ocsp::client::{parse_url, socket_authority, OcspClient, OcspClientConfig};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{cert, dn, keypair, throwaway_key};
+    use stpc_certs::extensions::{ExtensionValue, KeyPurpose};
+    use stpc_crypto::TypedPrivateKey;
+    use stpc_core::Key;
+    use stpc_crl::{RevokedCertificate, TbsCertList};
+    use stpc_time::TimeManager;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use std::time::Duration;
+    use std::sync::Arc;
+
+    struct Pki {
+        ca: Certificate,
+        ca_key: TypedPrivateKey,
+        leaf: Certificate,
+    }
+
+    fn pki() -> Pki {
+        let (ca_key, ca_pub) = keypair(SignatureAlgorithm::Ed25519);
+        let (_, leaf_pub) = keypair(SignatureAlgorithm::Ed25519);
+
+        Pki {
+            ca: cert("CA", "CA").sign(&ca_pub, &ca_key),
+            leaf: cert("Leaf", "CA").sign(&leaf_pub, &ca_key),
+            ca_key,
+        }
+    }
+
+    fn response_data(cert_id: CertId, responder_id: ResponderId, status: CertStatus) -> ResponseData {
+        ResponseData::new(
+            SignatureAlgorithm::Ed25519,
+            responder_id,
+            100,
+            cert_id,
+            status,
+            100,
+            200,
+            Some(vec![7; 16]),
+        )
+    }
+
+    #[test]
+    fn test_ocsp_request_serialization() -> Result<(), StpcError> {
+        let pki = pki();
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        assert!(cert_id.is_issued_by(&pki.ca)?);
+
+        let request = OcspRequest::new(cert_id.clone(), Some(vec![1, 2, 3]));
+        let deserialized = OcspRequest::deserialize(&request.serialize()?)?;
+        assert_eq!(deserialized.cert_id, cert_id);
+        assert_eq!(deserialized.nonce, Some(vec![1, 2, 3]));
+
+        let request = OcspRequest::new(cert_id, None);
+        assert_eq!(OcspRequest::deserialize(&request.serialize()?)?.nonce, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ocsp_response_signed_by_ca() -> Result<(), StpcError> {
+        let pki = pki();
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        let status = CertStatus::Revoked { revocation_time: 50, reason: RevocationReason::KeyCompromise };
+
+        let response = response_data(cert_id, ResponderId::ByName(dn("CA")), status).sign(&pki.ca_key)?;
+        let deserialized = OcspResponse::deserialize(&response.serialize()?)?;
+
+        assert!(deserialized.verify(&pki.ca, 100)?);
+        assert_eq!(deserialized.response_data.cert_status, status);
+        assert_eq!(deserialized.response_data.nonce, Some(vec![7; 16]));
+
+        let mut tampered = deserialized;
+        tampered.response_data.cert_status = CertStatus::Good;
+        assert!(tampered.verify(&pki.ca, 100).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ocsp_response_wrong_issuer() -> Result<(), StpcError> {
+        let other = pki();
+        let pki = pki();
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+        let response = response_data(cert_id, ResponderId::ByName(dn("CA")), CertStatus::Good).sign(&pki.ca_key)?;
+        assert!(response.verify(&other.ca, 100).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ocsp_delegated_responder() -> Result<(), StpcError> {
+        let pki = pki();
+        let (responder_key, responder_pub) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        let responder_id = ResponderId::ByKeyHash(stpc_ocsp::key_hash(responder_pub.key().as_bytes()));
+
+        let responder = cert("Responder", "CA")
+            .extension(false, ExtensionValue::ExtendedKeyUsage(vec![KeyPurpose::OcspSigning]))
+            .sign(&responder_pub, &pki.ca_key);
+        let response = response_data(cert_id.clone(), responder_id.clone(), CertStatus::Good)
+            .sign(&responder_key)?
+            .with_responder_certificate(responder);
+        let deserialized = OcspResponse::deserialize(&response.serialize()?)?;
+        assert!(deserialized.verify(&pki.ca, 100)?);
+
+        // a backdated producedAt does not keep an expired responder certificate usable
+        assert!(matches!(deserialized.verify(&pki.ca, 2000), Err(StpcError::TimeCertValidError(_))));
+
+        // a responder certificate without the OcspSigning purpose is not authorized
+        let unauthorized = cert("Responder", "CA").sign(&responder_pub, &pki.ca_key);
+        let response = response_data(cert_id, responder_id, CertStatus::Good)
+            .sign(&responder_key)?
+            .with_responder_certificate(unauthorized);
+        assert!(matches!(response.verify(&pki.ca, 100), Err(StpcError::OcspError(_))));
+
+        Ok(())
+    }
+
+    /// Copy of the CA key for a responder to own
+    fn ca_key(pki: &Pki) -> TypedPrivateKey {
+        TypedPrivateKey::from_bytes(pki.ca_key.algorithm(), pki.ca_key.key().as_bytes()).expect("Key rejected")
+    }
+
+    fn responder(pki: &Pki, store: MemoryStore) -> OcspResponder {
+        OcspResponder::new(pki.ca.clone(), ca_key(pki), Box::new(store), 60)
+    }
+
+    #[test]
+    fn test_responder_status_and_cache() -> Result<(), StpcError> {
+        let other = pki();
+        let pki = pki();
+        let store = MemoryStore::new();
+        store.revoke(pki.leaf.tbs_certificate.serial_number, 10, RevocationReason::KeyCompromise);
+        let responder = responder(&pki, store);
+
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        let first = responder.respond(&OcspRequest::new(cert_id.clone(), None), 100)?;
+        assert!(first.verify(&pki.ca, 100)?);
+        assert_eq!(first.response_data.next_update, 160);
+        assert_eq!(
+            first.response_data.cert_status,
+            CertStatus::Revoked { revocation_time: 10, reason: RevocationReason::KeyCompromise }
+        );
+
+        // served from cache until nextUpdate
+        let cached = responder.respond(&OcspRequest::new(cert_id.clone(), None), 150)?;
+        assert_eq!(cached.signature_value, first.signature_value);
+        let refreshed = responder.respond(&OcspRequest::new(cert_id.clone(), None), 160)?;
+        assert_eq!(refreshed.response_data.produced_at, 160);
+
+        // nonce requests are always signed fresh
+        let with_nonce = responder.respond(&OcspRequest::new(cert_id, Some(vec![9; 8])), 150)?;
+        assert_eq!(with_nonce.response_data.nonce, Some(vec![9; 8]));
+        assert_eq!(with_nonce.response_data.produced_at, 150);
+
+        // foreign issuer
+        let foreign = CertId::new(&other.leaf, &other.ca.tbs_certificate.subject_public_key)?;
+        assert!(matches!(responder.respond(&OcspRequest::new(foreign, None), 100), Err(StpcError::OcspError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_revoke_invalidates_cache() -> Result<(), StpcError> {
+        let pki = pki();
+        let store = Arc::new(MemoryStore::new());
+        let responder = OcspResponder::new(pki.ca.clone(), ca_key(&pki), Box::new(Arc::clone(&store)), 60);
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        let request = OcspRequest::new(cert_id, None);
+
+        assert_eq!(responder.respond(&request, 100)?.response_data.cert_status, CertStatus::Good);
+
+        store.revoke(pki.leaf.tbs_certificate.serial_number, 110, RevocationReason::KeyCompromise);
+        let revoked = responder.respond(&request, 120)?;
+        assert_eq!(revoked.response_data.produced_at, 120);
+        assert_eq!(
+            revoked.response_data.cert_status,
+            CertStatus::Revoked { revocation_time: 110, reason: RevocationReason::KeyCompromise }
+        );
+
+        // the fresh answer is cached again, until the revocation is lifted
+        assert_eq!(responder.respond(&request, 130)?.signature_value, revoked.signature_value);
+        store.unrevoke(&pki.leaf.tbs_certificate.serial_number);
+        assert_eq!(responder.respond(&request, 140)?.response_data.cert_status, CertStatus::Good);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_delegated_with_other_algorithm() -> Result<(), StpcError> {
+        let pki = pki();
+        let (responder_key, responder_pub) = TypedPrivateKey::generate(SignatureAlgorithm::MlDsa44)?;
+        let certificate = cert("Responder", "CA")
+            .key_algorithm(SignatureAlgorithm::MlDsa44)
+            .extension(false, ExtensionValue::ExtendedKeyUsage(vec![KeyPurpose::OcspSigning]))
+            .sign(&responder_pub, &pki.ca_key);
+
+        let responder = responder(&pki, MemoryStore::new())
+            .with_delegated_responder(certificate, responder_key);
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+        let response = responder.respond(&OcspRequest::new(cert_id, None), 100)?;
+        assert_eq!(response.signature_algorithm, SignatureAlgorithm::MlDsa44);
+        assert!(OcspResponse::deserialize(&response.serialize()?)?.verify(&pki.ca, 100)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_v2_framing() -> Result<(), StpcError> {
+        use stpc_encoding::Framing;
+
+        let pki = pki();
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        let request = OcspRequest::new(cert_id, None).serialize()?;
+
+        let v1 = responder(&pki, MemoryStore::new()).handle(&request, 100)?;
+        let v2 = responder(&pki, MemoryStore::new()).with_framing(Framing::V2).handle(&request, 100)?;
+        assert!(v2.len() + 40 < v1.len());
+
+        let response = OcspResponse::deserialize(&v2)?;
+        assert_eq!(response.response_data.framing, Framing::V2);
+        assert_eq!(response.serialize()?, v2);
+        assert!(response.verify(&pki.ca, 100)?);
+        assert!(OcspResponse::deserialize(&v1)?.verify(&pki.ca, 100)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_presign() -> Result<(), StpcError> {
+        let pki = pki();
+        let responder = responder(&pki, MemoryStore::new());
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+        responder.presign(std::slice::from_ref(&cert_id), 100)?;
+        let response = responder.respond(&OcspRequest::new(cert_id, None), 120)?;
+        assert_eq!(response.response_data.produced_at, 100);
+        assert_eq!(response.response_data.cert_status, CertStatus::Good);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_crl_file_store() -> Result<(), StpcError> {
+        let pki = pki();
+        let crl = TbsCertList::new(
+            SignatureAlgorithm::Ed25519,
+            dn("CA"),
+            100,
+            130,
+            vec![RevokedCertificate::new(pki.leaf.tbs_certificate.serial_number, 90, RevocationReason::Superseded)],
+            1,
+        )
+        .sign(&pki.ca_key)?;
+
+        let path = std::env::temp_dir().join(format!("stpc_ocsp_test_{}.crl", std::process::id()));
+        std::fs::write(&path, crl.serialize()?).unwrap();
+        let store = CrlFileStore::open(&path, &pki.ca)?;
+
+        let responder = OcspResponder::new(pki.ca.clone(), ca_key(&pki), Box::new(store), 60);
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+        let response = responder.respond(&OcspRequest::new(cert_id.clone(), Some(vec![1])), 100)?;
+        assert!(matches!(response.response_data.cert_status, CertStatus::Revoked { revocation_time: 90, .. }));
+        assert_eq!(response.response_data.next_update, 130, "Capped by the CRL nextUpdate");
+
+        // once the CRL is due for renewal there is nothing current to answer from
+        let expired = responder.respond(&OcspRequest::new(cert_id.clone(), None), 130);
+        assert!(matches!(expired, Err(StpcError::OcspError(_))));
+
+        std::fs::remove_file(&path).unwrap();
+        let response = responder.respond(&OcspRequest::new(cert_id, Some(vec![2])), 100);
+        assert!(response.is_err(), "Missing CRL file must not yield a Good answer");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_crl_file_reload_invalidates_cache() -> Result<(), StpcError> {
+        let pki = pki();
+        let serial_number = pki.leaf.tbs_certificate.serial_number;
+        let crl = |revoked: Vec<RevokedCertificate>, crl_number: u64| {
+            TbsCertList::new(SignatureAlgorithm::Ed25519, dn("CA"), 100, 500, revoked, crl_number)
+                .sign(&pki.ca_key)
+                .and_then(|crl| crl.serialize())
+        };
+
+        let path = std::env::temp_dir().join(format!("stpc_ocsp_reload_test_{}.crl", std::process::id()));
+        std::fs::write(&path, crl(Vec::new(), 1)?).unwrap();
+        let store = CrlFileStore::open(&path, &pki.ca)?;
+        let responder = OcspResponder::new(pki.ca.clone(), ca_key(&pki), Box::new(store), 60);
+        let request = OcspRequest::new(CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?, None);
+        assert_eq!(responder.respond(&request, 100)?.response_data.cert_status, CertStatus::Good);
+
+        // publish the next CRL; an explicit mtime keeps coarse timestamps from hiding the change
+        std::fs::write(&path, crl(vec![RevokedCertificate::new(serial_number, 105, RevocationReason::KeyCompromise)], 2)?)
+            .unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(std::time::SystemTime::now() + Duration::from_secs(10)))
+            .unwrap();
+
+        let response = responder.respond(&request, 110);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            response?.response_data.cert_status,
+            CertStatus::Revoked { revocation_time: 105, reason: RevocationReason::KeyCompromise }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_responder_http_server() -> Result<(), StpcError> {
+        let pki = pki();
+        let store = MemoryStore::new();
+        store.revoke(pki.leaf.tbs_certificate.serial_number, 10, RevocationReason::CaCompromise);
+        let responder = Arc::new(responder(&pki, store));
+
+        let server = OcspServer::start(responder, "127.0.0.1:0", Arc::new(TimeManager::new()))?;
+        assert!(server.url().starts_with("http://127.0.0.1:"));
+
+        let cert_id = CertId::new(&pki.leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+        let body = OcspRequest::new(cert_id, Some(vec![1; 8])).serialize()?;
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        ).unwrap();
+        stream.write_all(&body).unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+        let split = raw.windows(4).position(|w| w == b"\r\n\r\n").expect("No header end");
+        assert!(raw.starts_with(b"HTTP/1.1 200 OK"));
+
+        let response = OcspResponse::deserialize(&raw[split + 4..])?;
+        assert!(response.verify(&pki.ca, 100)?);
+        assert_eq!(response.response_data.nonce, Some(vec![1; 8]));
+        assert!(matches!(response.response_data.cert_status, CertStatus::Revoked { .. }));
+
+        Ok(())
+    }
+
+    fn leaf_with_url(pki: &Pki, ocsp_url: String) -> Certificate {
+        cert("Leaf", "CA")
+            .validity(Validity::new(0, u64::MAX))
+            .ocsp_url(&ocsp_url)
+            .sign(&throwaway_key(), &pki.ca_key)
+    }
+
+    fn client(retries: u32) -> OcspClient {
+        let config = OcspClientConfig {
+            timeout: Duration::from_secs(2),
+            retries,
+            retry_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        OcspClient::new(config, Arc::new(TimeManager::new()))
+    }
+
+    #[test]
+    fn test_client_against_local_responder() -> Result<(), StpcError> {
+        let pki = pki();
+        let store = MemoryStore::new();
+        let responder = Arc::new(responder(&pki, store));
+        let mut server = OcspServer::start(responder, "127.0.0.1:0", Arc::new(TimeManager::new()))?;
+
+        let leaf = leaf_with_url(&pki, server.url());
+        let client = client(0);
+        assert_eq!(client.check(&leaf, &pki.ca)?, CertStatus::Good);
+
+        // cached answer survives the responder going away
+        server.stop();
+        assert_eq!(client.check(&leaf, &pki.ca)?, CertStatus::Good);
+
+        client.clear_cache();
+        assert!(matches!(client.check(&leaf, &pki.ca), Err(StpcError::NetworkError(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_rejects_stale_response() -> Result<(), StpcError> {
+        let pki = pki();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leaf = leaf_with_url(&pki, format!("http://{}/ocsp", listener.local_addr().unwrap()));
+        let cert_id = CertId::new(&leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+        // stand-in responder answering with an old, correctly signed response
+        let stale = ResponseData::new(
+            SignatureAlgorithm::Ed25519,
+            ResponderId::ByName(dn("CA")),
+            100,
+            cert_id,
+            CertStatus::Good,
+            100,
+            200,
+            None,
+        )
+        .sign(&pki.ca_key)?
+        .serialize()?;
+        let stand_in = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 4096];
+            let _ = stream.read(&mut buffer).unwrap();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", stale.len()).unwrap();
+            stream.write_all(&stale).unwrap();
+        });
+
+        let config = OcspClientConfig { use_nonce: false, retries: 0, ..Default::default() };
+        let client = OcspClient::new(config, Arc::new(TimeManager::new()));
+        assert!(matches!(client.check(&leaf, &pki.ca), Err(StpcError::OcspError(_))));
+        stand_in.join().unwrap();
+
+        Ok(())
+    }
+
+    /// Answer one connection per status line, with `body` only for 200
+    fn stand_in(listener: TcpListener, statuses: &'static [&'static str], body: Vec<u8>) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0u8; 4096];
+                let _ = stream.read(&mut buffer).unwrap();
+                let body: &[u8] = if status.starts_with("200") { &body } else { &[] };
+                write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n", status, body.len()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        })
+    }
+
+    #[test]
+    fn test_client_retries_server_errors() -> Result<(), StpcError> {
+        let pki = pki();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leaf = leaf_with_url(&pki, format!("http://{}/ocsp", listener.local_addr().unwrap()));
+        let cert_id = CertId::new(&leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+        let now = TimeManager::new().get_time();
+        let fresh = ResponseData::new(
+            SignatureAlgorithm::Ed25519,
+            ResponderId::ByName(dn("CA")),
+            now,
+            cert_id,
+            CertStatus::Good,
+            now,
+            now + 60,
+            None,
+        )
+        .sign(&pki.ca_key)?
+        .serialize()?;
+
+        let config = OcspClientConfig { use_nonce: false, retry_delay: Duration::from_millis(10), ..Default::default() };
+        let client = OcspClient::new(config.clone(), Arc::new(TimeManager::new()));
+        let responder = stand_in(listener, &["503 Service Unavailable", "500 Internal Server Error", "200 OK"], fresh);
+        assert_eq!(client.check(&leaf, &pki.ca)?, CertStatus::Good);
+        responder.join().unwrap();
+
+        // a client error is final
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leaf = leaf_with_url(&pki, format!("http://{}/ocsp", listener.local_addr().unwrap()));
+        let responder = stand_in(listener, &["403 Forbidden"], Vec::new());
+        let client = OcspClient::new(config, Arc::new(TimeManager::new()));
+        assert!(matches!(client.check(&leaf, &pki.ca), Err(StpcError::OcspError(_))));
+        responder.join().unwrap();
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_rejects_bad_produced_at() -> Result<(), StpcError> {
+        let pki = pki();
+        let now = TimeManager::new().get_time();
+
+        // (producedAt, thisUpdate): from the future beyond the skew, and before thisUpdate
+        for (produced_at, this_update) in [(now + 3600, now), (now - 10, now)] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let leaf = leaf_with_url(&pki, format!("http://{}/ocsp", listener.local_addr().unwrap()));
+            let cert_id = CertId::new(&leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+
+            let response = ResponseData::new(
+                SignatureAlgorithm::Ed25519,
+                ResponderId::ByName(dn("CA")),
+                produced_at,
+                cert_id,
+                CertStatus::Good,
+                this_update,
+                now + 7200,
+                None,
+            )
+            .sign(&pki.ca_key)?
+            .serialize()?;
+
+            let config = OcspClientConfig { use_nonce: false, retries: 0, ..Default::default() };
+            let client = OcspClient::new(config, Arc::new(TimeManager::new()));
+            let responder = stand_in(listener, &["200 OK"], response);
+            assert!(matches!(client.check(&leaf, &pki.ca), Err(StpcError::OcspError(_))));
+            responder.join().unwrap();
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_cache_is_bounded() -> Result<(), StpcError> {
+        let pki = pki();
+        let now = TimeManager::new().get_time();
+        let config = OcspClientConfig { use_nonce: false, retries: 0, cache_size: 1, ..Default::default() };
+        let client = OcspClient::new(config, Arc::new(TimeManager::new()));
+
+        // each stand-in answers once, later checks can only be served from the cache
+        let mut leaves = Vec::new();
+        for next_update in [now + 60, now + 120] {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let leaf = leaf_with_url(&pki, format!("http://{}/ocsp", listener.local_addr().unwrap()));
+            let cert_id = CertId::new(&leaf, &pki.ca.tbs_certificate.subject_public_key)?;
+            let response = ResponseData::new(
+                SignatureAlgorithm::Ed25519,
+                ResponderId::ByName(dn("CA")),
+                now,
+                cert_id,
+                CertStatus::Good,
+                now,
+                next_update,
+                None,
+            )
+            .sign(&pki.ca_key)?
+            .serialize()?;
+
+            let responder = stand_in(listener, &["200 OK"], response);
+            assert_eq!(client.check(&leaf, &pki.ca)?, CertStatus::Good);
+            responder.join().unwrap();
+            leaves.push(leaf);
+        }
+
+        // the first answer made room for the second
+        assert!(matches!(client.check(&leaves[0], &pki.ca), Err(StpcError::NetworkError(_))));
+        assert_eq!(client.check(&leaves[1], &pki.ca)?, CertStatus::Good);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_deadline_covers_whole_answer() {
+        let pki = pki();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let leaf = leaf_with_url(&pki, format!("http://{}/ocsp", listener.local_addr().unwrap()));
+
+        // a header byte every 100ms never trips a per-read timeout
+        let trickle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 4096];
+            let _ = stream.read(&mut buffer).unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n");
+            while stream.write_all(b"X").is_ok() {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        let config = OcspClientConfig { timeout: Duration::from_millis(500), retries: 0, ..Default::default() };
+        let client = OcspClient::new(config, Arc::new(TimeManager::new()));
+        let started = std::time::Instant::now();
+        assert!(matches!(client.check(&leaf, &pki.ca), Err(StpcError::NetworkError(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        trickle.join().unwrap();
+    }
+
+    #[test]
+    fn test_client_url_authority() -> Result<(), StpcError> {
+        assert_eq!(parse_url("http://[::1]/ocsp")?, ("[::1]", "/ocsp"));
+        assert_eq!(parse_url("http://[::1]:8080")?, ("[::1]:8080", "/"));
+
+        assert_eq!(socket_authority("[::1]"), "[::1]:80");
+        assert_eq!(socket_authority("[::1]:8080"), "[::1]:8080");
+        assert_eq!(socket_authority("[2001:db8::8]"), "[2001:db8::8]:80");
+        assert_eq!(socket_authority("127.0.0.1"), "127.0.0.1:80");
+        assert_eq!(socket_authority("ocsp.example.com:8888"), "ocsp.example.com:8888");
+
+        let address = socket_authority("[::1]").to_socket_addrs().unwrap().next().unwrap();
+        assert_eq!(address, "[::1]:80".parse().unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_unsupported_url() {
+        let pki = pki();
+        let leaf = leaf_with_url(&pki, "https://ocsp.example.com".to_string());
+
+        assert!(matches!(client(0).check(&leaf, &pki.ca), Err(StpcError::OcspError(_))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,180 @@
+use stpc_core::StpcError;
+use stpc_handshake::record::{CipherSuite, RecordStream, MAX_RECORD_SIZE};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    const CLIENT_SECRET: [u8; 32] = [1; 32];
+    const SERVER_SECRET: [u8; 32] = [2; 32];
+
+    /// Both ends of a socket pair with the client's and server's view of the secrets
+    fn pair(suite: CipherSuite) -> (RecordStream<UnixStream>, RecordStream<UnixStream>) {
+        let (client, server) = UnixStream::pair().expect("Socket pair failed");
+
+        (
+            RecordStream::from_secrets(client, suite, &CLIENT_SECRET, &SERVER_SECRET),
+            RecordStream::from_secrets(server, suite, &SERVER_SECRET, &CLIENT_SECRET),
+        )
+    }
+
+    /// Records written by the client side, captured as raw bytes
+    fn capture(suite: CipherSuite, write: impl FnOnce(&mut RecordStream<Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut stream = RecordStream::from_secrets(Cursor::new(Vec::new()), suite, &CLIENT_SECRET, &SERVER_SECRET);
+        write(&mut stream);
+        stream.into_inner().into_inner()
+    }
+
+    fn server_reading(suite: CipherSuite, bytes: Vec<u8>) -> RecordStream<Cursor<Vec<u8>>> {
+        RecordStream::from_secrets(Cursor::new(bytes), suite, &SERVER_SECRET, &CLIENT_SECRET)
+    }
+
+    fn test_roundtrip(suite: CipherSuite) {
+        let (mut client, mut server) = pair(suite);
+
+        client.send(b"ping").expect("Send failed");
+        assert_eq!(server.receive().expect("Receive failed").expect("Closed").as_slice(), b"ping");
+
+        server.send(b"pong").expect("Send failed");
+        assert_eq!(client.receive().expect("Receive failed").expect("Closed").as_slice(), b"pong");
+
+        client.close().expect("Close failed");
+        assert!(server.receive().expect("Receive failed").is_none());
+    }
+
+    #[test]
+    fn test_record_chacha20_poly1305() {
+        test_roundtrip(CipherSuite::ChaCha20Poly1305);
+    }
+
+    #[test]
+    fn test_record_aes_256_gcm() {
+        test_roundtrip(CipherSuite::Aes256Gcm);
+    }
+
+    #[test]
+    fn test_record_read_write_adapter() {
+        let (mut client, mut server) = pair(CipherSuite::ChaCha20Poly1305);
+        let message: Vec<u8> = (0..3 * MAX_RECORD_SIZE + 100).map(|i| i as u8).collect();
+
+        let expected = message.clone();
+        let reader = thread::spawn(move || {
+            let mut received = Vec::new();
+            server.read_to_end(&mut received).expect("Read failed");
+            assert_eq!(received, expected);
+        });
+
+        client.write_all(&message).expect("Write failed");
+        client.flush().expect("Flush failed");
+        client.close().expect("Close failed");
+        reader.join().expect("Reader panicked");
+    }
+
+    #[test]
+    fn test_record_ciphertext_differs_from_plaintext() {
+        let bytes = capture(CipherSuite::Aes256Gcm, |stream| stream.send(b"attack at dawn").expect("Send failed"));
+
+        assert!(!bytes.windows(14).any(|window| window == b"attack at dawn"));
+    }
+
+    #[test]
+    fn test_record_tampered() {
+        let mut bytes = capture(CipherSuite::ChaCha20Poly1305, |stream| stream.send(b"payload").expect("Send failed"));
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        let mut server = server_reading(CipherSuite::ChaCha20Poly1305, bytes);
+        assert!(matches!(server.receive(), Err(StpcError::RecordAuthenticationError)));
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let record = capture(CipherSuite::ChaCha20Poly1305, |stream| stream.send(b"transfer 100").expect("Send failed"));
+
+        let mut server = server_reading(CipherSuite::ChaCha20Poly1305, [record.clone(), record].concat());
+        assert_eq!(server.receive().expect("Receive failed").expect("Closed").as_slice(), b"transfer 100");
+        assert!(matches!(
+            server.receive(),
+            Err(StpcError::RecordReplayError { expected: 1, received: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_record_dropped() {
+        let mut records = Vec::new();
+        capture(CipherSuite::ChaCha20Poly1305, |stream| {
+            for message in [b"first", b"other"] {
+                let start = stream.get_ref().get_ref().len();
+                stream.send(message).expect("Send failed");
+                records.push(stream.get_ref().get_ref()[start..].to_vec());
+            }
+        });
+
+        let mut server = server_reading(CipherSuite::ChaCha20Poly1305, records.remove(1));
+        assert!(matches!(
+            server.receive(),
+            Err(StpcError::RecordSequenceError { expected: 0, received: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_record_wrong_key() {
+        let bytes = capture(CipherSuite::ChaCha20Poly1305, |stream| stream.send(b"payload").expect("Send failed"));
+
+        let mut server = RecordStream::from_secrets(Cursor::new(bytes), CipherSuite::ChaCha20Poly1305, &SERVER_SECRET, &SERVER_SECRET);
+        assert!(matches!(server.receive(), Err(StpcError::RecordAuthenticationError)));
+    }
+
+    #[test]
+    fn test_record_suite_mismatch() {
+        let bytes = capture(CipherSuite::ChaCha20Poly1305, |stream| stream.send(b"payload").expect("Send failed"));
+
+        let mut server = server_reading(CipherSuite::Aes256Gcm, bytes);
+        assert!(matches!(server.receive(), Err(StpcError::RecordAuthenticationError)));
+    }
+
+    #[test]
+    fn test_record_key_update() {
+        let (mut client, mut server) = pair(CipherSuite::Aes256Gcm);
+
+        client.send(b"before").expect("Send failed");
+        client.update_keys(true).expect("Key update failed");
+        client.send(b"after").expect("Send failed");
+
+        assert_eq!(server.receive().expect("Receive failed").expect("Closed").as_slice(), b"before");
+        assert_eq!(server.receive().expect("Receive failed").expect("Closed").as_slice(), b"after");
+
+        // The server answered the request with its own update
+        server.send(b"reply").expect("Send failed");
+        assert_eq!(client.receive().expect("Receive failed").expect("Closed").as_slice(), b"reply");
+    }
+
+    #[test]
+    fn test_record_key_update_changes_key() {
+        let mut update_size = 0;
+        let bytes = capture(CipherSuite::ChaCha20Poly1305, |stream| {
+            stream.update_keys(false).expect("Key update failed");
+            update_size = stream.get_ref().get_ref().len();
+            stream.send(b"payload").expect("Send failed");
+        });
+
+        // A reader that misses the update cannot open later records
+        let mut stale = server_reading(CipherSuite::ChaCha20Poly1305, bytes[update_size..].to_vec());
+        assert!(matches!(stale.receive(), Err(StpcError::RecordAuthenticationError)));
+
+        let mut server = server_reading(CipherSuite::ChaCha20Poly1305, bytes);
+        assert_eq!(server.receive().expect("Receive failed").expect("Closed").as_slice(), b"payload");
+    }
+
+    #[test]
+    fn test_record_truncated_stream() {
+        let (mut client, server) = pair(CipherSuite::ChaCha20Poly1305);
+        drop(server);
+
+        // End of stream without a close record is an error, not end of data
+        assert!(client.receive().is_err());
+    }
+}
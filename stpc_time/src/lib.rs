@@ -105,6 +105,12 @@ impl TimeManager {
     }
 }
 
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for TimeManager {
     fn drop(&mut self) {
         self.stop();
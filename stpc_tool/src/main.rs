@@ -1,5 +1,6 @@
 use stpc_certs::{TbsCertificate, Certificate, Validity, DistinguishedName, CertSerializable};
-use stpc_core::{CertificateVersion, SignatureAlgorithm, StpcError};
+use stpc_core::{CertificateVersion, HashAlgorithm, Key, SignatureAlgorithm, StpcError};
+use stpc_crypto::TypedPrivateKey;
 
 fn main() -> Result<(), StpcError> {
     // === Создаем пример DistinguishedName для issuer и subject ===
@@ -26,6 +27,9 @@ fn main() -> Result<(), StpcError> {
     // === Создаем пример Validity ===
     let validity = Validity::new(1_700_000_000, 1_800_000_000);
 
+    // === Генерируем ключи ===
+    let (private_key, public_key) = TypedPrivateKey::generate(SignatureAlgorithm::Ed25519)?;
+
     // === Создаем TbsCertificate ===
     let tbs = TbsCertificate::new(
         CertificateVersion::V1,
@@ -33,19 +37,28 @@ fn main() -> Result<(), StpcError> {
         issuer,
         validity,
         subject,
-        vec![0xAA; 32], // пример публичного ключа
+        public_key.key().as_bytes().to_vec(),
         "http://ocsp.example.com".to_string(),
-    );
+    )
+    .with_subject_key_algorithm(SignatureAlgorithm::Ed25519);
 
-    let cert = Certificate::new(tbs.clone(), SignatureAlgorithm::Ed25519, vec![0xBB; 64]); // пример подписи
+    // === Подписываем ===
+    let cert = tbs.sign(&private_key)?;
 
     // === Сериализация ===
     let serialized = cert.serialize()?;
-    println!("Serialized Certificate: {:?}", serialized);
+    println!("Serialized Certificate: {} bytes", serialized.len());
 
     // === Десериализация ===
     let deserialized = Certificate::deserialize(&serialized)?;
-    println!("Deserialized Certificate:\n{:?}", deserialized);
+    let fingerprint = deserialized.fingerprint(HashAlgorithm::Sha256)?;
+    println!("Subject: {}", deserialized.tbs_certificate.subject.common_name);
+    println!("Fingerprint: {}", fingerprint);
+    println!("Fingerprint (base32): {}", fingerprint.to_base32());
+    println!("Subject key id: {}", deserialized.subject_key_id().to_colon_hex());
+
+    // === Проверка подписи ===
+    println!("Signature valid: {}", deserialized.verify(&public_key)?);
 
     Ok(())
 }
\ No newline at end of file